@@ -0,0 +1,161 @@
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::i18n::string;
+use crate::languages::language_dir;
+use crate::paginate::{paginate, remove_extra_pages};
+use crate::post::Post;
+use crate::templates::render_page;
+use chrono::Datelike;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+/// Directory of the archive overview page.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// A year or a month of posts, with the months of a year nested under it.
+#[derive(Debug, Serialize)]
+pub struct Period<'a> {
+    pub year: i32,
+    /// 1-based month, or `None` for a whole year.
+    pub month: Option<u32>,
+    /// `2024` or `March 2024`.
+    pub name: String,
+    pub url: String,
+    pub count: usize,
+    pub posts: Vec<&'a Post>,
+    pub months: Vec<Period<'a>>,
+}
+
+impl<'a> Period<'a> {
+    /// The year or month of `post`, whose page goes under `dir`.
+    fn new(post: &'a Post, month: bool, dir: &str) -> Self {
+        let date = post.date;
+        let (month, name, url) = if month {
+            (
+                Some(date.month()),
+                date.format("%B %Y").to_string(),
+                format!("{}{}/index.html", dir, date.format("%Y/%m")),
+            )
+        } else {
+            (
+                None,
+                date.format("%Y").to_string(),
+                format!("{}{}/index.html", dir, date.format("%Y")),
+            )
+        };
+        Period {
+            year: date.year(),
+            month,
+            name,
+            url,
+            count: 0,
+            posts: Vec::new(),
+            months: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, post: &'a Post) {
+        self.posts.push(post);
+        self.count += 1;
+    }
+
+    fn dir(&self) -> String {
+        self.url.trim_end_matches("index.html").to_string()
+    }
+}
+
+/// Groups `posts`, newest first, by year and then by month, keeping their
+/// order. Their pages go under `dir`.
+pub fn collect_archive<'a>(posts: &'a [Post], dir: &str) -> Vec<Period<'a>> {
+    let mut years: Vec<Period> = Vec::new();
+    for post in posts {
+        if years
+            .last()
+            .is_none_or(|year| year.year != post.date.year())
+        {
+            years.push(Period::new(post, false, dir));
+        }
+        let year = years.last_mut().unwrap();
+        year.push(post);
+        if year
+            .months
+            .last()
+            .is_none_or(|month| month.month != Some(post.date.month()))
+        {
+            year.months.push(Period::new(post, true, dir));
+        }
+        year.months.last_mut().unwrap().push(post);
+    }
+    years
+}
+
+/// Every year followed by its months.
+pub fn periods<'a, 'b>(years: &'b [Period<'a>]) -> impl Iterator<Item = &'b Period<'a>> {
+    years
+        .iter()
+        .flat_map(|year| std::iter::once(year).chain(&year.months))
+}
+
+/// Writes `archive/index.html` listing `years`, and a page for every year and
+/// month at `<year>/index.html` and `<year>/<month>/index.html`, all under
+/// the directory of `lang`. Returns the paths of the pages written.
+pub fn render_archive_pages(
+    tera: &Tera,
+    config: &Config,
+    output_dir: &Path,
+    lang: &str,
+    years: &[Period],
+    pages: &[Post],
+) -> Result<Vec<PathBuf>, BuildError> {
+    let mut written = Vec::new();
+    let dir = language_dir(config, lang);
+    for period in periods(years) {
+        let period_dir = period.dir();
+        let listing = paginate(&period.posts, config.paginate, &period_dir);
+        for page in &listing {
+            let mut context = Context::new();
+            context.insert("config", config);
+            context.insert("section", "archive");
+            context.insert("url", &page.path);
+            context.insert("lang", lang);
+            context.insert("pages", pages);
+            context.insert(
+                "title",
+                &string(config, lang, "period").replace("{name}", &period.name),
+            );
+            context.insert("period", period);
+            context.insert("posts", page.posts);
+            context.insert("paginator", &page.paginator);
+
+            let html_path = output_dir.join(page.path.trim_start_matches('/'));
+            render_page(tera, config, "period.html", &context, &html_path)?;
+            crate::verbose!(
+                "Archive page generated: {:?} ({} posts)",
+                html_path,
+                period.count
+            );
+            written.push(html_path);
+        }
+        remove_extra_pages(output_dir, &period_dir, listing.len())?;
+    }
+
+    let mut context = Context::new();
+    context.insert("config", config);
+    context.insert("section", "archive");
+    context.insert("url", &format!("{}{}/index.html", dir, ARCHIVE_DIR));
+    context.insert("lang", lang);
+    context.insert("pages", pages);
+    context.insert("title", &string(config, lang, "archive"));
+    context.insert("years", years);
+
+    let html_path = output_dir
+        .join(dir.trim_start_matches('/'))
+        .join(ARCHIVE_DIR)
+        .join("index.html");
+    render_page(tera, config, "archive.html", &context, &html_path)?;
+    crate::verbose!("Archive generated: {:?} ({} years)", html_path, years.len());
+    written.push(html_path);
+
+    Ok(written)
+}
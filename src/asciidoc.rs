@@ -0,0 +1,329 @@
+use crate::formats::{code_block, find_date, set_metadata};
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Elements without a closing tag.
+const VOID_ELEMENTS: [&str; 4] = ["hr", "br", "img", "input"];
+
+/// Wrappers asciidoctor puts around sections and their bodies, left out of
+/// the markdown.
+const SECTION_WRAPPERS: [&str; 7] = [
+    "<div id=\"preamble\">",
+    "<div class=\"sectionbody\">",
+    "<div class=\"sect1\">",
+    "<div class=\"sect2\">",
+    "<div class=\"sect3\">",
+    "<div class=\"sect4\">",
+    "<div class=\"sect5\">",
+];
+
+/// Converts an AsciiDoc document by running `asciidoctor` on it. The document
+/// header, `= Title` with the author and revision lines and the attribute
+/// entries after it, like `:revdate:` and `:keywords:`, is returned as its
+/// front matter.
+///
+/// asciidoctor's HTML is kept as it is, except for its section titles and
+/// paragraphs, which become markdown ones so the table of contents, the
+/// excerpt, the word count and search see them.
+pub fn to_markdown(path: &Path, content: &str) -> Result<(Map<String, Value>, String), String> {
+    let html = asciidoctor(path, content)?;
+    Ok((header(content), html_to_markdown(&html)))
+}
+
+/// The front matter set by the header of an AsciiDoc document.
+fn header(content: &str) -> Map<String, Value> {
+    let mut metadata = Map::new();
+    let mut lines = content
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .filter(|line| !line.starts_with("//"))
+        .take_while(|line| !line.trim().is_empty())
+        .peekable();
+    if let Some(title) = lines.peek().and_then(|line| line.strip_prefix("= ")) {
+        set_metadata(&mut metadata, "title", title);
+        lines.next();
+        if let Some(authors) = lines.next_if(|line| !line.starts_with(':')) {
+            let names: Vec<&str> = authors
+                .split(';')
+                .map(|author| author.split('<').next().unwrap_or(author).trim())
+                .collect();
+            match names.as_slice() {
+                [name] => set_metadata(&mut metadata, "author", name),
+                names => set_metadata(&mut metadata, "authors", &names.join(";")),
+            }
+            if let Some(revision) = lines.next_if(|line| !line.starts_with(':')) {
+                if find_date(revision).is_some() {
+                    set_metadata(&mut metadata, "date", revision);
+                }
+            }
+        }
+    }
+    for line in lines {
+        let Some((name, value)) = line
+            .strip_prefix(':')
+            .and_then(|entry| entry.split_once(':'))
+        else {
+            continue;
+        };
+        // `:toc:` sets an attribute and `:toc!:` unsets it.
+        let (name, value) = match (name.strip_suffix('!'), value.trim()) {
+            (Some(name), _) => (name, "false"),
+            (None, "") => (name, "true"),
+            (None, value) => (name, value),
+        };
+        match name {
+            "revdate" | "date" if find_date(value).is_none() => {}
+            "revdate" => set_metadata(&mut metadata, "date", value),
+            name => set_metadata(&mut metadata, name, value),
+        }
+    }
+    metadata
+}
+
+/// The HTML asciidoctor renders `content` into, without the page around it.
+/// Includes are resolved from the directory of `path`.
+fn asciidoctor(path: &Path, content: &str) -> Result<String, String> {
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let mut command = Command::new("asciidoctor");
+    command
+        .args(["--embedded", "--out-file", "-", "--base-dir"])
+        .arg(base_dir)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    crate::verbose!("Running {:?}", command);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("could not run asciidoctor: {}", e))?;
+    let mut stdin = child.stdin.take().unwrap();
+    let input = content.to_string();
+    // Written from another thread, so a document too large for the pipe
+    // doesn't block while asciidoctor's output fills the other one.
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("could not run asciidoctor: {}", e))?;
+    writer
+        .join()
+        .unwrap_or(Ok(()))
+        .map_err(|e| format!("could not run asciidoctor: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!(
+            "asciidoctor failed ({}):\n{}",
+            output.status,
+            stderr.trim_end()
+        ));
+    }
+    for warning in stderr.lines() {
+        let message = ["asciidoctor: ", "WARNING: ", "<stdin>: "]
+            .iter()
+            .fold(warning, |message, prefix| {
+                message.trim_start_matches(prefix)
+            });
+        eprintln!("Warning: {}: {}", path.display(), message);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// asciidoctor's HTML as markdown: section titles become headings keeping
+/// their ids, paragraphs become paragraphs of inline HTML, and the other
+/// blocks are kept as HTML blocks the markdown renderer passes through.
+fn html_to_markdown(html: &str) -> String {
+    let lines: Vec<&str> = html.lines().collect();
+    let mut output = String::with_capacity(html.len());
+    // Number of section wrappers open; every other `<div>` is part of a block.
+    let mut wrappers = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if SECTION_WRAPPERS.contains(&line) {
+            wrappers += 1;
+            continue;
+        }
+        if line == "</div>" && wrappers > 0 {
+            wrappers -= 1;
+            continue;
+        }
+        if let Some(heading) = heading(line) {
+            output.push_str(&heading);
+            output.push_str("\n\n");
+            continue;
+        }
+        if line == "<div class=\"paragraph\">"
+            && lines.get(i).is_some_and(|line| line.starts_with("<p>"))
+        {
+            let end = (i..lines.len())
+                .find(|&end| lines[end].ends_with("</p>"))
+                .unwrap_or(lines.len() - 1);
+            let paragraph = lines[i..=end].join("\n");
+            let text = paragraph.strip_prefix("<p>").unwrap_or(&paragraph);
+            let text = text.strip_suffix("</p>").unwrap_or(text);
+            output.push_str(&inline_markdown(text));
+            output.push_str("\n\n");
+            // Skips the paragraph's closing `</div>`.
+            i = end + 2;
+            continue;
+        }
+
+        // Any other block, up to the line closing the element it opens with.
+        let name: String = line
+            .trim_start_matches('<')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect();
+        let start = i - 1;
+        if !name.is_empty() && !VOID_ELEMENTS.contains(&name.as_str()) {
+            let mut depth = 0;
+            for (n, line) in lines[start..].iter().enumerate() {
+                depth += line.matches(&format!("<{}", name)).count();
+                depth = depth.saturating_sub(line.matches(&format!("</{}>", name)).count());
+                i = start + n + 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+        if line == "<div class=\"listingblock\">" {
+            if let Some(code) = listing(&lines[start..i].join("\n")) {
+                output.push_str(&code);
+                output.push('\n');
+                continue;
+            }
+        }
+        for line in &lines[start..i] {
+            // A blank line would end the HTML block, so blank lines, which
+            // only `<pre>` keeps, hold a space instead.
+            match line.trim().is_empty() {
+                true => output.push_str("&#32;"),
+                false => output.push_str(line),
+            }
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// A listing block of source code as a fenced code block, so it is
+/// highlighted like the others, with its title as the `title=`.
+fn listing(html: &str) -> Option<String> {
+    let title = html
+        .split_once("<div class=\"title\">")
+        .and_then(|(_, rest)| rest.split_once("</div>"))
+        .map(|(title, _)| unescape(&strip_tags(title)));
+    let (_, pre) = html.split_once("<pre")?;
+    let (pre, _) = pre.rsplit_once("</pre>")?;
+    let (attributes, code) = pre.split_once('>')?;
+    let (lang, code) = match code.strip_prefix("<code") {
+        Some(code) => {
+            let (attributes, code) = code.split_once('>')?;
+            let lang = attributes
+                .split_once("data-lang=\"")
+                .and_then(|(_, rest)| rest.split_once('"'))
+                .map_or("", |(lang, _)| lang);
+            (lang, code.strip_suffix("</code>")?)
+        }
+        None if attributes.contains("highlight") => ("", code),
+        None => return None,
+    };
+    let mut info = lang.to_string();
+    if let Some(title) = title {
+        info.push_str(&format!(" title=\"{}\"", title.replace('"', "'")));
+    }
+    let code = unescape(&strip_tags(code));
+    let mut output = String::new();
+    code_block(info.trim(), &code.lines().collect::<Vec<_>>(), &mut output);
+    Some(output)
+}
+
+/// `html` without its tags, like the callout numbers in a listing.
+fn strip_tags(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+    output
+}
+
+/// Text with the entities asciidoctor escapes it with replaced.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// A section title, `<h2 id="_usage">Usage</h2>`, as a markdown heading with
+/// the same id.
+fn heading(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("<h")?;
+    let level = rest.chars().next().filter(|c| ('1'..='6').contains(c))?;
+    let level = level.to_digit(10)? as usize;
+    let rest = rest[1..].strip_prefix(" id=\"")?;
+    let (id, rest) = rest.split_once('"')?;
+    let title = rest
+        .split_once('>')?
+        .1
+        .strip_suffix(&format!("</h{}>", level))?;
+    Some(format!(
+        "{} {} {{#{}}}",
+        "#".repeat(level),
+        inline_markdown(title),
+        id
+    ))
+}
+
+/// Inline HTML with the characters in its text that markdown would read as
+/// markup escaped, tags and entities left as they are.
+fn inline_markdown(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for line in html.lines() {
+        let line = line.trim();
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        let in_text = !in_tag;
+        let mut escaped = escape_text(line, &mut in_tag);
+        // Markdown would start a heading, quote, list or table here.
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if in_text && line.starts_with(['#', '>', '-', '+', '=', '|']) {
+            escaped.insert(0, '\\');
+        } else if in_text && digits > 0 && line[digits..].starts_with(['.', ')']) {
+            escaped.insert(digits, '\\');
+        }
+        output.push_str(&escaped);
+    }
+    output
+}
+
+/// `html` with markdown's special characters outside tags escaped. `in_tag`
+/// carries whether a tag is open from one line to the next.
+fn escape_text(html: &str, in_tag: &mut bool) -> String {
+    let mut output = String::with_capacity(html.len());
+    for c in html.chars() {
+        match c {
+            '<' => *in_tag = true,
+            '>' => *in_tag = false,
+            '\\' | '*' | '_' | '`' | '[' | ']' | '$' | '~' if !*in_tag => output.push('\\'),
+            _ => {}
+        }
+        output.push(c);
+    }
+    output
+}
@@ -0,0 +1,145 @@
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::i18n::string;
+use crate::languages::language_dir;
+use crate::paginate::{paginate, remove_extra_pages};
+use crate::post::Post;
+use crate::templates::render_page;
+use serde::Serialize;
+use slug::slugify;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+/// Directory holding a page of posts per author.
+pub const AUTHORS_DIR: &str = "authors";
+
+/// An author of a post, with the details the config gives under `[authors]`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Author {
+    pub id: String,
+    pub name: String,
+    pub bio: String,
+    pub avatar: String,
+    pub links: BTreeMap<String, String>,
+    /// The page listing the author's posts in the language of the post.
+    pub url: String,
+}
+
+/// An author with the posts they wrote, for their page.
+#[derive(Debug, Serialize)]
+pub struct AuthorPosts<'a> {
+    pub author: &'a Author,
+    pub count: usize,
+    pub posts: Vec<&'a Post>,
+}
+
+/// The authors named by `author:` and `authors:` in the front matter of
+/// `post`, or else its git contributors with `authors_from_git`, linked in
+/// its language. A name is looked up among the configured authors by key,
+/// then by name, then by its slug; authors missing from the config keep the
+/// name as written.
+pub fn post_authors(config: &Config, post: &Post) -> Vec<Author> {
+    let dir = language_dir(config, &post.lang);
+    let metadata = &post.metadata;
+    let mut names: Vec<&String> = metadata.author.iter().chain(&metadata.authors).collect();
+    if names.is_empty() && config.authors_from_git {
+        names = post.contributors.iter().collect();
+    }
+    let mut authors: Vec<Author> = Vec::new();
+    for name in names {
+        let configured = config.authors.get_key_value(name).or_else(|| {
+            config
+                .authors
+                .iter()
+                .find(|(_, settings)| settings.name == *name)
+        });
+        let (id, settings) = match configured {
+            Some((id, settings)) => (id.clone(), Some(settings)),
+            None => {
+                let id = slugify(name);
+                let settings = config.authors.get(&id);
+                (id, settings)
+            }
+        };
+        if id.is_empty() || authors.iter().any(|author| author.id == id) {
+            continue;
+        }
+        let settings = settings.cloned().unwrap_or_default();
+        authors.push(Author {
+            url: format!("{}{}/{}/index.html", dir, AUTHORS_DIR, id),
+            name: match settings.name.is_empty() {
+                true => name.clone(),
+                false => settings.name,
+            },
+            id,
+            bio: settings.bio,
+            avatar: settings.avatar,
+            links: settings.links,
+        });
+    }
+    authors
+}
+
+/// Groups `posts` by author, sorted by id, each keeping the order of `posts`.
+pub fn collect_authors(posts: &[Post]) -> Vec<AuthorPosts<'_>> {
+    let mut authors: BTreeMap<&str, AuthorPosts> = BTreeMap::new();
+    for post in posts {
+        for author in &post.authors {
+            let entry = authors
+                .entry(author.id.as_str())
+                .or_insert_with(|| AuthorPosts {
+                    author,
+                    count: 0,
+                    posts: Vec::new(),
+                });
+            entry.posts.push(post);
+            entry.count += 1;
+        }
+    }
+    authors.into_values().collect()
+}
+
+/// Writes `authors/<id>/index.html` for every author in `authors`, and
+/// `authors/<id>/page/<n>/index.html` when they have more than one page, under
+/// the directory of `lang`. Returns the paths of the pages written.
+pub fn render_author_pages(
+    tera: &Tera,
+    config: &Config,
+    output_dir: &Path,
+    lang: &str,
+    authors: &[AuthorPosts],
+    pages: &[Post],
+) -> Result<Vec<PathBuf>, BuildError> {
+    let mut written = Vec::new();
+    for entry in authors {
+        let author = entry.author;
+        let title = string(config, lang, "posts_by").replace("{name}", &author.name);
+        let dir = author.url.trim_end_matches("index.html");
+        let listing = paginate(&entry.posts, config.paginate, dir);
+        for page in &listing {
+            let mut context = Context::new();
+            context.insert("config", config);
+            context.insert("section", "author");
+            context.insert("url", &page.path);
+            context.insert("lang", lang);
+            context.insert("pages", pages);
+            context.insert("title", &title);
+            context.insert("author", author);
+            context.insert("posts", page.posts);
+            context.insert("paginator", &page.paginator);
+
+            let html_path = output_dir.join(page.path.trim_start_matches('/'));
+            render_page(tera, config, "author.html", &context, &html_path)?;
+            crate::verbose!(
+                "Author page generated: {:?} ({} posts)",
+                html_path,
+                entry.count
+            );
+            written.push(html_path);
+        }
+        remove_extra_pages(output_dir, dir, listing.len())?;
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,147 @@
+use crate::output::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+
+/// Manifest of the previous build, kept in the output directory.
+pub const CACHE_FILE: &str = ".ebolg-cache.json";
+
+/// What the previous build rendered, so unchanged posts can skip both the
+/// markdown renderer and rewriting their page.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BuildCache {
+    /// Hash of everything every page depends on: the ebolg version, the
+    /// configuration and the templates. Any change invalidates every entry.
+    site: u64,
+    /// Keyed by post URL, which unlike the source path does not depend on how
+    /// the source directory was spelled on the command line.
+    posts: BTreeMap<String, CachedPost>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedPost {
+    /// Hash of the markdown body and of what it is rendered with.
+    source: u64,
+    /// The body rendered to HTML.
+    html: String,
+    /// Hash of everything the post's page was rendered from, including its neighbours.
+    page: Option<u64>,
+}
+
+impl BuildCache {
+    /// Reads the manifest from `output_dir`. A missing or unreadable manifest, or
+    /// one written for different templates or config, yields an empty cache.
+    pub fn load(output_dir: &Path, site: u64) -> BuildCache {
+        let cache = fs::read_to_string(output_dir.join(CACHE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<BuildCache>(&content).ok());
+        match cache {
+            Some(cache) if cache.site == site => cache,
+            _ => BuildCache {
+                site,
+                posts: BTreeMap::new(),
+            },
+        }
+    }
+
+    pub fn save(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let cache_path = output_dir.join(CACHE_FILE);
+        write_atomic(&cache_path, serde_json::to_string(self)?)
+            .map_err(|e| format!("Failed to write {}: {}", cache_path.display(), e))?;
+        Ok(())
+    }
+
+    /// The HTML previously rendered for the post at `url`, if its body is unchanged.
+    pub fn html(&self, url: &str, source: u64) -> Option<&str> {
+        self.posts
+            .get(url)
+            .filter(|post| post.source == source)
+            .map(|post| post.html.as_str())
+    }
+
+    /// Records the rendered body of a post, forgetting its page if the body changed.
+    pub fn set_html(&mut self, url: &str, source: u64, html: &str) {
+        match self.posts.get_mut(url) {
+            Some(post) if post.source == source => {}
+            _ => {
+                self.posts.insert(
+                    url.to_string(),
+                    CachedPost {
+                        source,
+                        html: html.to_string(),
+                        page: None,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Whether the page at `url` was last rendered from exactly `page`.
+    pub fn page_is_fresh(&self, url: &str, page: u64) -> bool {
+        self.posts
+            .get(url)
+            .is_some_and(|post| post.page == Some(page))
+    }
+
+    pub fn set_page(&mut self, url: &str, page: u64) {
+        if let Some(post) = self.posts.get_mut(url) {
+            post.page = Some(page);
+        }
+    }
+
+    /// Drops entries for posts that are no longer part of the site.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.posts.retain(|url, _| keep(url));
+    }
+}
+
+/// Whether `output_dir` is empty or holds the cache of an earlier build, so
+/// deleting from it can't take files that ebolg didn't write.
+pub fn is_generated(output_dir: &Path) -> io::Result<bool> {
+    Ok(output_dir.join(CACHE_FILE).is_file() || fs::read_dir(output_dir)?.next().is_none())
+}
+
+/// Hash used for every cache key.
+pub fn hash(value: impl Hash) -> u64 {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// FNV-1a. Unlike the standard library's hasher, which may change between
+/// Rust releases, it gives the same keys whatever toolchain built ebolg, so
+/// upgrading Rust neither invalidates nor mismatches a kept cache.
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_stable() {
+        // A kept cache is matched by these keys, whatever toolchain builds ebolg.
+        assert_eq!(format!("{:016x}", hash("post")), "caf6ba35761fcfa8");
+    }
+}
@@ -0,0 +1,65 @@
+use crate::cache::{is_generated, CACHE_FILE};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Deletes the output directory, refusing when it is (or contains) the source
+/// directory, or when it isn't empty and has no build cache, so a
+/// misconfigured output path cannot take the posts, or anything else, with it.
+/// A dry run only says what would be removed.
+pub fn clean(source_dir: &Path, output_dir: &Path, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    if !output_dir.exists() {
+        println!("Nothing to clean: {:?} does not exist", output_dir);
+        return Ok(());
+    }
+
+    let source_dir = source_dir.canonicalize()?;
+    let output_dir = output_dir.canonicalize()?;
+    if source_dir.starts_with(&output_dir) {
+        return Err(format!(
+            "Refusing to clean {:?}: it contains the source directory",
+            output_dir
+        )
+        .into());
+    }
+    if !is_generated(&output_dir)? {
+        return Err(format!(
+            "Refusing to clean {:?}: it has no {} so it may not have been generated by ebolg",
+            output_dir, CACHE_FILE
+        )
+        .into());
+    }
+
+    if dry_run {
+        println!("Would remove {:?}", output_dir);
+        return Ok(());
+    }
+    fs::remove_dir_all(&output_dir)?;
+    println!("Removed {:?}", output_dir);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn keeps_directories_ebolg_did_not_generate() {
+        let dir = env::temp_dir().join(format!("ebolg-clean-test-{}", std::process::id()));
+        let (source_dir, output_dir) = (dir.join("site"), dir.join("public"));
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(output_dir.join("notes.txt"), "keep me").unwrap();
+
+        let refused = clean(&source_dir, &output_dir, false);
+        assert!(refused.is_err());
+        assert!(output_dir.join("notes.txt").is_file());
+
+        fs::write(output_dir.join(CACHE_FILE), "{}").unwrap();
+        clean(&source_dir, &output_dir, false).unwrap();
+        assert!(!output_dir.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+use crate::config::{Config, TAGS};
+use crate::formats::find_date;
+use crate::html;
+use crate::links::describe;
+use crate::post::METADATA_KEYS;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Component, Path};
+use std::time::Duration;
+
+/// Formats the bodies of pulled posts can be in.
+pub const BODY_FORMATS: [&str; 2] = ["markdown", "html"];
+
+/// How long to wait for the CMS to answer a request.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pages of posts read at most, in case an API's next page never runs out.
+const MAX_PAGES: usize = 1000;
+
+/// Hidden file in `dir` listing the files the last pull wrote, the only ones
+/// a pull removes or overwrites.
+const MANIFEST_FILE: &str = ".ebolg-cms.json";
+
+/// Pulls the posts of the CMS `[cms]` configures into its `dir` under
+/// `source_dir`, one markdown file with JSON front matter per post, named by
+/// its slug. Files are only written when a post changed, and those of posts
+/// the CMS no longer lists are removed, so a watched build isn't set off
+/// again by its own pull. Only files an earlier pull wrote, as its manifest
+/// records, are removed or overwritten. When the CMS can't be reached, the posts pulled
+/// last time are built, with a warning.
+pub fn pull(source_dir: &Path, config: &Config) -> Result<(), Box<dyn Error>> {
+    let cms = &config.cms;
+    if cms.url.is_empty() {
+        return Ok(());
+    }
+    let dir_path = &cms.dir;
+    if !dir_path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        || cms.dir.as_os_str().is_empty()
+    {
+        return Err(format!(
+            "Invalid dir {:?} under [cms], expected a directory inside the source directory, like \"cms\"",
+            cms.dir
+        )
+        .into());
+    }
+    if !BODY_FORMATS.contains(&cms.body_format.as_str()) {
+        return Err(format!(
+            "Unknown body_format {:?} under [cms], expected one of: {}",
+            cms.body_format,
+            BODY_FORMATS.join(", ")
+        )
+        .into());
+    }
+    let items = match fetch(config) {
+        Ok(items) => items,
+        Err(reason) => {
+            eprintln!(
+                "Warning: could not pull posts from {}: {}; building those pulled before",
+                cms.url, reason
+            );
+            return Ok(());
+        }
+    };
+
+    // Front-matter keys read from each post, unless `fields` points elsewhere.
+    let mut keys: Vec<&str> = METADATA_KEYS.to_vec();
+    keys.push(TAGS);
+    keys.extend(config.taxonomies.keys().map(String::as_str));
+    keys.extend(cms.fields.keys().map(String::as_str));
+    keys.sort_unstable();
+    keys.dedup();
+    let mut files = BTreeMap::new();
+    for (n, item) in items.iter().enumerate() {
+        match post(config, item, &keys) {
+            Ok((slug, contents)) => {
+                let name = format!("{}.md", slug);
+                if files.contains_key(&name) {
+                    eprintln!(
+                        "Warning: post {} from {} has the slug {:?} of another, skipped",
+                        n + 1,
+                        cms.url,
+                        slug
+                    );
+                    continue;
+                }
+                files.insert(name, contents);
+            }
+            Err(reason) => eprintln!(
+                "Warning: post {} from {} skipped: {}",
+                n + 1,
+                cms.url,
+                reason
+            ),
+        }
+    }
+
+    let dir = source_dir.join(dir_path);
+    fs::create_dir_all(&dir)?;
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let pulled: BTreeSet<String> = fs::read_to_string(&manifest_path)
+        .ok()
+        .and_then(|manifest| serde_json::from_str(&manifest).ok())
+        .unwrap_or_default();
+    for name in &pulled {
+        let path = dir.join(name);
+        if !files.contains_key(name) && Path::new(name).file_name() == Some(OsStr::new(name)) {
+            crate::verbose!("Removing {:?}: no longer in the CMS", path);
+            if path.is_file() {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    let mut written = BTreeSet::new();
+    for (name, contents) in &files {
+        let path = dir.join(name);
+        let current = fs::read_to_string(&path).ok();
+        if current.as_ref() != Some(contents) {
+            // A file of the same name that no pull wrote is the user's own.
+            if current.is_some() && !pulled.contains(name) {
+                eprintln!(
+                    "Warning: {} wasn't pulled from the CMS, so the post of that name was skipped",
+                    path.display()
+                );
+                continue;
+            }
+            fs::write(&path, contents)?;
+        }
+        written.insert(name.clone());
+    }
+    fs::write(&manifest_path, serde_json::to_string(&written)?)?;
+    println!("Pulled {} posts from {}", written.len(), cms.url);
+    Ok(())
+}
+
+/// Every post the CMS lists, following the `next` page of each response.
+fn fetch(config: &Config) -> Result<Vec<Value>, String> {
+    let cms = &config.cms;
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let token = env::var("EBOLG_CMS_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .unwrap_or_else(|| cms.token.clone());
+    let mut items = Vec::new();
+    let mut url = cms.url.clone();
+    for _ in 0..MAX_PAGES {
+        crate::verbose!("Pulling {}", url);
+        let mut request = agent.get(&url).set("Accept", "application/json");
+        if !token.is_empty() {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let body = request
+            .call()
+            .map_err(describe)?
+            .into_string()
+            .map_err(|e| e.to_string())?;
+        let response: Value =
+            serde_json::from_str(&body).map_err(|e| format!("invalid JSON: {}", e))?;
+        let page = response
+            .pointer(&cms.items)
+            .and_then(Value::as_array)
+            .ok_or_else(|| format!("the response has no list of posts at {:?}", cms.items))?;
+        items.extend(page.iter().cloned());
+        let next = match cms.next.is_empty() {
+            true => None,
+            false => response.pointer(&cms.next).and_then(Value::as_str),
+        };
+        match next.filter(|next| !next.is_empty()) {
+            Some(next) => url = absolute_url(&url, next),
+            None => break,
+        }
+    }
+    Ok(items)
+}
+
+/// A pulled post's slug and markdown file, with the front matter read from
+/// `keys` of its fields.
+fn post(config: &Config, item: &Value, keys: &[&str]) -> Result<(String, String), String> {
+    let cms = &config.cms;
+    let mut metadata = Map::new();
+    for key in keys {
+        let pointer = match cms.fields.get(*key) {
+            Some(pointer) => pointer.clone(),
+            None => format!("/{}", key),
+        };
+        if let Some(value) = item
+            .pointer(&pointer)
+            .and_then(|value| metadata_value(key, value))
+        {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+    let slug = match (metadata.get("slug"), metadata.get("title")) {
+        (Some(Value::String(slug)), _) => slug::slugify(slug),
+        (_, Some(Value::String(title))) => slug::slugify(title),
+        _ => return Err("it has neither a slug nor a title".to_string()),
+    };
+    if slug.is_empty() {
+        return Err("its slug is empty".to_string());
+    }
+    let body = item
+        .pointer(&cms.body)
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let body = match cms.body_format.as_str() {
+        "html" => html::to_markdown(body),
+        _ => body.trim().to_string(),
+    };
+    let contents = format!("{}\n\n{}\n", Value::Object(metadata), body);
+    Ok((slug, contents))
+}
+
+/// A field of a pulled post as the value of front-matter `key`: dates with
+/// their time left out, and the terms and authors APIs give as objects by
+/// their `name` or `title`.
+fn metadata_value(key: &str, value: &Value) -> Option<Value> {
+    let name = |value: &Value| match value {
+        Value::String(name) => Some(Value::from(name.as_str())),
+        value => ["name", "title"]
+            .iter()
+            .find_map(|field| value.get(*field)?.as_str())
+            .map(Value::from),
+    };
+    match (key, value) {
+        (_, Value::Null) => None,
+        ("date" | "updated", value) => Some(Value::from(find_date(value.as_str()?)?)),
+        ("author", value) => name(value),
+        (_, Value::Array(values)) => Some(Value::Array(values.iter().filter_map(name).collect())),
+        (_, value) => Some(value.clone()),
+    }
+}
+
+/// The URL a `next` link points at from the page at `url`, which APIs give as
+/// a full URL, a path or a query.
+fn absolute_url(url: &str, next: &str) -> String {
+    if next.contains("://") {
+        return next.to_string();
+    }
+    if let Some(query) = next.strip_prefix('?') {
+        let path = url.split('?').next().unwrap_or(url);
+        return format!("{}?{}", path, query);
+    }
+    let origin_end = url
+        .find("://")
+        .and_then(|scheme| url[scheme + 3..].find('/').map(|end| scheme + 3 + end))
+        .unwrap_or(url.len());
+    format!("{}/{}", &url[..origin_end], next.trim_start_matches('/'))
+}
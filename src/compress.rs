@@ -0,0 +1,115 @@
+use crate::output::write_atomic;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Formats `precompress` writes, by the extension of their files.
+pub const PRECOMPRESS_FORMATS: [(&str, &str); 2] = [("gzip", "gz"), ("brotli", "br")];
+
+/// Text files worth compressing: generated pages, stylesheets and scripts,
+/// the search index, feeds and sitemaps, and vector images.
+const COMPRESSED_EXTENSIONS: [&str; 6] = ["html", "css", "js", "json", "xml", "svg"];
+
+/// Files handed to one run of the `brotli` CLI, which compresses each of its
+/// arguments, so a large site doesn't hit the limit on their length.
+const BROTLI_BATCH: usize = 100;
+
+/// Writes a compressed sibling in each of `formats`, like `index.html.gz`, of
+/// every text file among `files` that doesn't have an up-to-date one, for
+/// hosts serving precompressed files. gzip is written directly; brotli needs
+/// the `brotli` command. Returns the number of files written.
+pub fn precompress(formats: &[String], files: &[PathBuf]) -> Result<usize, Box<dyn Error>> {
+    let files: Vec<&PathBuf> = files.iter().filter(|file| is_compressible(file)).collect();
+    let mut written = 0;
+    for (format, extension) in PRECOMPRESS_FORMATS {
+        if !formats.iter().any(|wanted| wanted == format) {
+            continue;
+        }
+        let stale: Vec<&PathBuf> = files
+            .iter()
+            .copied()
+            .filter(|file| !is_fresh(file, &compressed_path(file, extension)))
+            .collect();
+        match format {
+            "gzip" => stale.par_iter().try_for_each(|file| gzip(file))?,
+            _ => {
+                for batch in stale.chunks(BROTLI_BATCH) {
+                    brotli(batch)?;
+                }
+            }
+        }
+        written += stale.len();
+    }
+    Ok(written)
+}
+
+/// Whether `path` is a compressed copy, written by `precompress`, of one of
+/// the `produced` files.
+pub fn is_compressed_copy(path: &Path, produced: &HashSet<PathBuf>) -> bool {
+    PRECOMPRESS_FORMATS.iter().any(|(_, extension)| {
+        path.extension() == Some(OsStr::new(extension))
+            && produced.contains(&path.with_extension(""))
+    })
+}
+
+fn is_compressible(path: &Path) -> bool {
+    // Hidden files are the build cache and half-written temporary files.
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    !hidden
+        && path
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|extension| COMPRESSED_EXTENSIONS.contains(&extension))
+}
+
+fn compressed_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// Whether `compressed` was written after `path` last changed.
+fn is_fresh(path: &Path, compressed: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(path), modified(compressed)) {
+        (Ok(original), Ok(compressed)) => compressed >= original,
+        _ => false,
+    }
+}
+
+fn gzip(path: &Path) -> io::Result<()> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&fs::read(path)?)?;
+    let compressed_path = compressed_path(path, "gz");
+    write_atomic(&compressed_path, encoder.finish()?)?;
+    crate::verbose!("Compressed: {:?}", compressed_path);
+    Ok(())
+}
+
+fn brotli(files: &[&PathBuf]) -> Result<(), Box<dyn Error>> {
+    let mut command = Command::new("brotli");
+    command.args(["--force", "--best"]).args(files);
+    crate::verbose!("Running {:?}", command);
+    let output = command
+        .output()
+        .map_err(|e| format!("Could not run brotli for precompress: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "brotli failed ({}):\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,880 @@
+use crate::data::Data;
+use crate::error::BuildError;
+use crate::fingerprint::Fingerprints;
+use crate::formats::SOURCE_FORMATS;
+use crate::i18n::Strings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the site configuration file, looked up at the source root.
+pub const CONFIG_FILE: &str = "ebolg.toml";
+
+/// Permalink used when none is configured: the source path with an `.html`
+/// extension, or the front-matter slug in place of the file stem.
+pub const DEFAULT_PERMALINK: &str = "/{dir}/{slug}.html";
+
+/// The taxonomy every site has, read from `tags:` in the front matter.
+pub const TAGS: &str = "tags";
+
+/// Placeholders a permalink pattern may contain.
+pub const PERMALINK_PLACEHOLDERS: [&str; 5] = ["year", "month", "day", "dir", "slug"];
+
+/// Site-wide settings read from `ebolg.toml`. Every field is optional so a
+/// site without a config file builds exactly as before.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Site title, used for the index page and appended to page titles.
+    pub title: String,
+    /// Absolute URL the site is served from, e.g. `https://example.com`.
+    pub base_url: String,
+    pub author: Option<String>,
+    /// Authors posts can name with `author:` or `authors:`, by the key they use.
+    pub authors: BTreeMap<String, AuthorConfig>,
+    /// Credit posts that name no author to the authors of the commits
+    /// changing them.
+    pub authors_from_git: bool,
+    /// Output directory used when none is given on the command line,
+    /// relative to the source directory.
+    pub output_dir: Option<PathBuf>,
+    /// Output path of each post, e.g. `/{year}/{month}/{slug}/index.html`.
+    /// See `PERMALINK_PLACEHOLDERS`; a pattern ending in `/` gets an `index.html`.
+    pub permalink: String,
+    /// Posts per page of the index and archive pages; 0 puts them all on one.
+    pub paginate: usize,
+    /// Minify generated pages and copied CSS files.
+    pub minify: bool,
+    /// Write compressed copies of the text files in the output directory,
+    /// like `index.html.gz`, in each of these `PRECOMPRESS_FORMATS`.
+    pub precompress: Vec<String>,
+    /// Formats posts and pages are written in, by file extension, out of
+    /// `SOURCE_FORMATS`: `md`, `org` for org-mode, `rst` for reStructuredText,
+    /// `ipynb` for Jupyter notebooks and, with the `asciidoc` feature, `adoc`.
+    /// All of them by default.
+    pub formats: Vec<String>,
+    /// Glob patterns, relative to the source directory, of the files to
+    /// build, posts and assets alike. Every file is built when empty.
+    pub include: Vec<String>,
+    /// Glob patterns, relative to the source directory, of the files and
+    /// directories to leave out of the build, like `drafts/**` or `*.bak`.
+    /// They win over `include`.
+    #[serde(alias = "exclude")]
+    pub ignore: Vec<String>,
+    /// Build hidden files, like `.notes.md`, too. They are skipped by default,
+    /// along with the backup and lock files editors leave behind.
+    pub include_hidden: bool,
+    pub theme: ThemeConfig,
+    pub assets: AssetsConfig,
+    pub markdown: MarkdownConfig,
+    pub code: CodeConfig,
+    pub typography: TypographyConfig,
+    pub emoji: EmojiConfig,
+    pub sanitize: SanitizeConfig,
+    pub external_links: ExternalLinksConfig,
+    pub images: ImagesConfig,
+    pub tailwind: TailwindConfig,
+    pub related: RelatedConfig,
+    pub search: SearchConfig,
+    pub comments: CommentsConfig,
+    pub analytics: AnalyticsConfig,
+    pub front_matter: FrontMatterConfig,
+    pub deploy: DeployConfig,
+    pub cms: CmsConfig,
+    /// Ways of grouping posts, each read from the front-matter key of the
+    /// same name. Entries extend the default `tags` taxonomy.
+    pub taxonomies: BTreeMap<String, TaxonomyConfig>,
+    /// CSS selector to the classes added to matching elements of rendered
+    /// markdown. Entries extend the defaults; an empty value removes one.
+    pub classes: BTreeMap<String, String>,
+    /// Links in the navigation bar, ordered by `weight`. Without any, it links
+    /// the posts, the archive and every standalone page.
+    pub menu: Vec<MenuItem>,
+    /// Language of posts whose file name names none, like `hello.md`.
+    pub default_language: String,
+    /// Languages posts are translated into, by code. `hello.fr.md` is the
+    /// French version of `hello.md`, published under `/fr/`. An entry for the
+    /// default language only names it.
+    pub languages: BTreeMap<String, LanguageConfig>,
+    /// Contents of the `data/` directory, filled in when the site is loaded
+    /// rather than read from the config file.
+    #[serde(skip)]
+    pub data: Data,
+    /// UI strings of every language, see `load_strings`, filled in with `data`.
+    #[serde(skip)]
+    pub strings: BTreeMap<String, Strings>,
+    /// Hashes of the assets published under fingerprinted names, filled in
+    /// once the assets are found when `assets.fingerprint` is set.
+    #[serde(skip)]
+    pub fingerprints: Fingerprints,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Directory under `themes/` to take templates and static files from
+    /// before the built-in ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub stylesheet: String,
+    pub body_class: String,
+    /// Syntect theme used to highlight fenced code blocks.
+    pub code_theme: String,
+    /// CSS variables, named without the leading `--`, for the light and dark
+    /// color schemes. Entries extend the default palettes.
+    pub light: BTreeMap<String, String>,
+    pub dark: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetsConfig {
+    /// Directory, relative to the source directory, copied as-is to the root
+    /// of the output directory.
+    pub static_dir: PathBuf,
+    /// Extensions of the files outside `static_dir` that are copied alongside
+    /// the posts. Setting this replaces the default list.
+    pub extensions: Vec<String>,
+    /// Glob patterns, relative to the source directory, of files copied
+    /// as-is whatever their extension, even markdown.
+    pub copy: Vec<String>,
+    /// Also publish stylesheets, scripts and images under a name holding a
+    /// hash of their contents, like `style.1a2b3c4d.css`, and point the
+    /// generated pages at those copies.
+    pub fingerprint: bool,
+}
+
+/// Optional markdown extensions. Tables, footnotes, definition lists and
+/// `{#id .class}` attributes are always enabled.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkdownConfig {
+    /// `~~struck out~~` text.
+    pub strikethrough: bool,
+    /// `- [ ]` and `- [x]` list items rendered as checkboxes.
+    pub tasklists: bool,
+    /// Curly quotes, dashes and ellipses in place of their ASCII forms.
+    pub smart_punctuation: bool,
+    /// Render `$...$` and `$$...$$` with KaTeX. Posts can override this with
+    /// `math:` in their front matter.
+    pub math: bool,
+}
+
+/// Extras of fenced code blocks. Lines listed in braces after the language,
+/// as in ```` ```rust {3-5} ````, are highlighted either way.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CodeConfig {
+    /// Number the lines of every fenced code block.
+    pub line_numbers: bool,
+    /// A button copying the code of each block to the clipboard.
+    pub copy_button: bool,
+}
+
+/// Typographic touches applied to the prose of posts, leaving code alone.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypographyConfig {
+    /// Turns on the touches below along with `markdown.smart_punctuation`,
+    /// for curly quotes and dashes.
+    pub enabled: bool,
+    /// A no-break space between numbers and their units, as in `5 km`.
+    pub units: bool,
+    /// A no-break space between the last two words of each paragraph, so
+    /// its last line isn't a single word.
+    pub widows: bool,
+}
+
+impl Default for TypographyConfig {
+    fn default() -> Self {
+        TypographyConfig {
+            enabled: false,
+            units: true,
+            widows: true,
+        }
+    }
+}
+
+/// `:rocket:`-style shortcodes in posts.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmojiConfig {
+    pub enabled: bool,
+    /// One of `EMOJI_STYLES`: the Unicode characters, or Twemoji's images.
+    pub style: String,
+    /// Directory URL of the Twemoji SVG files.
+    pub twemoji_url: String,
+    /// Shortcodes to add or replace, by name without the colons.
+    pub custom: BTreeMap<String, String>,
+}
+
+impl Default for EmojiConfig {
+    fn default() -> Self {
+        EmojiConfig {
+            enabled: false,
+            style: String::from("unicode"),
+            twemoji_url: String::from(
+                "https://cdn.jsdelivr.net/gh/jdecked/twemoji@15.1.0/assets/svg/",
+            ),
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+/// Stripping the raw HTML in posts down to an allowlist, for sites taking
+/// posts from contributors who can't be trusted with scripts.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SanitizeConfig {
+    pub enabled: bool,
+    /// Elements raw HTML may use. Others are dropped but keep their content,
+    /// except `script` and `style`, which go with it. Setting this replaces
+    /// the default list.
+    pub tags: Vec<String>,
+    /// Attributes each allowed element may keep, by tag name; those under
+    /// `"*"` are allowed on all of them. Links and sources other than
+    /// `http:`, `https:`, `mailto:` and relative ones are dropped. Setting
+    /// this replaces the default table.
+    pub attributes: BTreeMap<String, Vec<String>>,
+}
+
+/// How links in posts to other sites, outside `base_url`, are marked up.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExternalLinksConfig {
+    /// Open them in a new tab, with `target="_blank"` and `rel="noopener
+    /// noreferrer"`. Links with a `target` of their own keep it.
+    pub new_tab: bool,
+    /// Class added to them, for styling them with an external-link icon.
+    pub class: String,
+}
+
+/// Resized copies of the PNG and JPEG images posts embed, offered to browsers
+/// through `srcset`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImagesConfig {
+    /// Widths, in pixels, to resize images to. Only widths smaller than the
+    /// original are generated; an empty list turns resizing off.
+    pub widths: Vec<u32>,
+    /// `sizes` attribute added alongside `srcset`.
+    pub sizes: String,
+}
+
+/// The "Related posts" list at the bottom of each post.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelatedConfig {
+    /// Most posts listed; 0 turns the list off.
+    pub count: usize,
+    /// Empty uses the `related_posts` UI string.
+    pub heading: String,
+}
+
+/// Services whose comment threads can be embedded under posts.
+pub const COMMENT_PROVIDERS: [&str; 3] = ["giscus", "utterances", "disqus"];
+
+/// The comment thread embedded under each post.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommentsConfig {
+    /// One of `COMMENT_PROVIDERS`, or empty for no comments.
+    pub provider: String,
+    /// GitHub repository holding the threads, as `owner/name` (giscus and utterances).
+    pub repo: String,
+    /// giscus ids of the repository and of the discussion category.
+    pub repo_id: String,
+    pub category: String,
+    pub category_id: String,
+    /// How a post is matched to its thread (giscus and utterances).
+    pub mapping: String,
+    /// Site shortname (Disqus).
+    pub shortname: String,
+}
+
+/// Services whose tracking script can be added to every page.
+pub const ANALYTICS_PROVIDERS: [&str; 3] = ["plausible", "umami", "google"];
+
+/// The analytics script added to the head of every page.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    /// One of `ANALYTICS_PROVIDERS`, or empty for no analytics.
+    pub provider: String,
+    /// The site's domain for Plausible, website id for Umami or measurement
+    /// id for Google Analytics.
+    pub id: String,
+    /// URL of the tracking script, for a self-hosted instance. Empty uses the
+    /// provider's hosted one.
+    pub script: String,
+}
+
+/// Hosts `ebolg deploy` can publish to.
+pub const DEPLOY_TARGETS: [&str; 4] = ["rsync", "s3", "github-pages", "netlify"];
+
+/// Where `ebolg deploy` publishes the output directory.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeployConfig {
+    /// One of `DEPLOY_TARGETS`. `--target` overrides it.
+    pub target: String,
+    /// `user@host:path` for rsync over ssh.
+    pub destination: String,
+    /// S3 bucket, with an optional `/prefix`.
+    pub bucket: String,
+    /// CloudFront distribution invalidated after an S3 upload.
+    pub distribution: String,
+    /// AWS CLI profile. Empty uses its default credentials.
+    pub profile: String,
+    /// Git remote for GitHub Pages. Empty uses the `origin` of the source
+    /// directory.
+    pub repo: String,
+    /// Branch GitHub Pages serves.
+    pub branch: String,
+    /// Custom domain written to the branch's `CNAME` file. Empty uses the
+    /// host of `base_url`, unless it is a `github.io` one.
+    pub cname: String,
+    /// Replace the branch with a single commit on each deploy instead of
+    /// adding one on top.
+    pub force: bool,
+    /// Netlify site id, or `NETLIFY_SITE_ID` from the environment.
+    pub site_id: String,
+    /// Netlify personal access token. `NETLIFY_AUTH_TOKEN` from the
+    /// environment wins, and is safer than a token committed with the config.
+    #[serde(skip_serializing)]
+    pub token: String,
+}
+
+/// A headless CMS whose posts are pulled in at the start of each build, with
+/// the `cms` feature, and built along with the local ones.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CmsConfig {
+    /// Endpoint of the CMS's API listing the posts as JSON. Nothing is pulled
+    /// when empty.
+    pub url: String,
+    /// Bearer token of the API. `EBOLG_CMS_TOKEN` from the environment wins,
+    /// and is safer than a token committed with the config.
+    #[serde(skip_serializing)]
+    pub token: String,
+    /// JSON pointer to the list of posts in a response, like `/data`. Empty
+    /// when the response is the list.
+    pub items: String,
+    /// JSON pointer to the URL of the next page of posts in a response, like
+    /// `/links/next`, for APIs that list them a page at a time.
+    pub next: String,
+    /// JSON pointer to each front-matter key in a post, like
+    /// `title = "/attributes/title"`. Keys without one are read from the
+    /// post's field of the same name.
+    pub fields: BTreeMap<String, String>,
+    /// JSON pointer to the body of a post.
+    pub body: String,
+    /// Whether bodies are `markdown` or `html`, which is converted to markdown.
+    pub body_format: String,
+    /// Directory, relative to the source directory, the pulled posts are
+    /// written to and built from. ebolg keeps it in step with the CMS, so it
+    /// shouldn't hold anything else.
+    pub dir: PathBuf,
+}
+
+/// Checks on the front matter of posts, reported as warnings or, with
+/// `--strict`, as failures.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontMatterConfig {
+    /// Keys every post must set, like `date`. Standalone pages are exempt.
+    pub required: Vec<String>,
+    /// Keys of the site's own, read by its templates, which aren't reported
+    /// as unknown.
+    pub extra: Vec<String>,
+}
+
+/// Full-text search without a server.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// Write `search-index.json`, listing every post and page with its text.
+    pub index: bool,
+    /// Also write a search page querying the index in the browser.
+    pub page: bool,
+}
+
+/// Generating the stylesheet with the Tailwind CLI after each build.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TailwindConfig {
+    /// Command running the Tailwind CLI, such as `npx tailwindcss`. Empty
+    /// leaves the stylesheet to be built by other means.
+    pub command: String,
+    /// CSS file with the `@tailwind` directives, relative to the source
+    /// directory. Tailwind's own base, components and utilities without one.
+    pub input: Option<PathBuf>,
+    pub minify: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MenuItem {
+    pub label: String,
+    pub url: String,
+    /// Items with a lower weight come first; equal weights keep their order.
+    #[serde(default)]
+    pub weight: i64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthorConfig {
+    /// Name shown in bylines. Empty uses the name posts give.
+    pub name: String,
+    pub bio: String,
+    /// URL of a picture of the author.
+    pub avatar: String,
+    /// Links to the author's other places on the web, by label.
+    pub links: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageConfig {
+    /// Label of the language in the language switcher, like `Français`.
+    /// Empty shows its code.
+    pub name: String,
+    /// Site title on the pages in this language. Empty uses `title`.
+    pub title: String,
+    /// UI strings replacing the built-in ones and those in `i18n/<lang>.yaml`.
+    pub strings: Strings,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaxonomyConfig {
+    /// Lists the posts of each term oldest first and links every post to the
+    /// parts before and after it, as for a series.
+    pub ordered: bool,
+    /// Terms posts may use, compared by slug. Any term is allowed when empty.
+    pub terms: Vec<String>,
+    /// Writes feeds of each term's posts next to its archive page, like
+    /// `tags/rust/feed.xml`, once `base_url` is set.
+    pub feed: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            title: String::new(),
+            base_url: String::new(),
+            author: None,
+            authors: BTreeMap::new(),
+            authors_from_git: false,
+            output_dir: None,
+            permalink: DEFAULT_PERMALINK.to_string(),
+            paginate: 0,
+            minify: false,
+            precompress: Vec::new(),
+            formats: SOURCE_FORMATS
+                .iter()
+                .map(|format| format.to_string())
+                .collect(),
+            include: Vec::new(),
+            ignore: Vec::new(),
+            include_hidden: false,
+            theme: ThemeConfig::default(),
+            assets: AssetsConfig::default(),
+            markdown: MarkdownConfig::default(),
+            code: CodeConfig::default(),
+            typography: TypographyConfig::default(),
+            emoji: EmojiConfig::default(),
+            sanitize: SanitizeConfig::default(),
+            external_links: ExternalLinksConfig::default(),
+            images: ImagesConfig::default(),
+            tailwind: TailwindConfig::default(),
+            related: RelatedConfig::default(),
+            search: SearchConfig::default(),
+            comments: CommentsConfig::default(),
+            analytics: AnalyticsConfig::default(),
+            front_matter: FrontMatterConfig::default(),
+            deploy: DeployConfig::default(),
+            cms: CmsConfig::default(),
+            taxonomies: default_taxonomies(),
+            classes: default_classes(),
+            menu: Vec::new(),
+            default_language: String::from("en"),
+            languages: BTreeMap::new(),
+            data: Data::new(),
+            strings: BTreeMap::new(),
+            fingerprints: Fingerprints::new(),
+        }
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            name: None,
+            stylesheet: String::from("/style/tailwind.css"),
+            body_class: String::from("bg-[var(--background)] text-[var(--text)]"),
+            code_theme: String::from("base16-ocean.dark"),
+            light: light_palette(),
+            dark: dark_palette(),
+        }
+    }
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        MarkdownConfig {
+            strikethrough: true,
+            tasklists: true,
+            smart_punctuation: true,
+            math: false,
+        }
+    }
+}
+
+impl Default for SanitizeConfig {
+    fn default() -> Self {
+        let tags = [
+            "a",
+            "abbr",
+            "b",
+            "blockquote",
+            "br",
+            "caption",
+            "cite",
+            "code",
+            "dd",
+            "del",
+            "details",
+            "div",
+            "dl",
+            "dt",
+            "em",
+            "figcaption",
+            "figure",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+            "hr",
+            "i",
+            "img",
+            "ins",
+            "kbd",
+            "li",
+            "mark",
+            "ol",
+            "p",
+            "pre",
+            "q",
+            "s",
+            "samp",
+            "small",
+            "span",
+            "strong",
+            "sub",
+            "summary",
+            "sup",
+            "table",
+            "tbody",
+            "td",
+            "tfoot",
+            "th",
+            "thead",
+            "tr",
+            "u",
+            "ul",
+        ];
+        let attributes = [
+            ("*", &["title", "lang"][..]),
+            ("a", &["href"]),
+            ("img", &["src", "alt", "width", "height"]),
+            ("td", &["colspan", "rowspan"]),
+            ("th", &["colspan", "rowspan"]),
+        ];
+        SanitizeConfig {
+            enabled: false,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            attributes: attributes
+                .iter()
+                .map(|(tag, names)| {
+                    let names = names.iter().map(|name| name.to_string()).collect();
+                    (tag.to_string(), names)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            widths: vec![480, 960, 1440],
+            sizes: String::from("(max-width: 768px) 100vw, 768px"),
+        }
+    }
+}
+
+impl Default for RelatedConfig {
+    fn default() -> Self {
+        RelatedConfig {
+            count: 3,
+            heading: String::new(),
+        }
+    }
+}
+
+impl Default for CommentsConfig {
+    fn default() -> Self {
+        CommentsConfig {
+            provider: String::new(),
+            repo: String::new(),
+            repo_id: String::new(),
+            category: String::new(),
+            category_id: String::new(),
+            mapping: String::from("pathname"),
+            shortname: String::new(),
+        }
+    }
+}
+
+impl Default for DeployConfig {
+    fn default() -> Self {
+        DeployConfig {
+            target: String::new(),
+            destination: String::new(),
+            bucket: String::new(),
+            distribution: String::new(),
+            profile: String::new(),
+            repo: String::new(),
+            branch: String::from("gh-pages"),
+            cname: String::new(),
+            force: false,
+            site_id: String::new(),
+            token: String::new(),
+        }
+    }
+}
+
+impl Default for CmsConfig {
+    fn default() -> Self {
+        CmsConfig {
+            url: String::new(),
+            token: String::new(),
+            items: String::new(),
+            next: String::new(),
+            fields: BTreeMap::new(),
+            body: String::from("/body"),
+            body_format: String::from("markdown"),
+            dir: PathBuf::from("cms"),
+        }
+    }
+}
+
+impl Default for TailwindConfig {
+    fn default() -> Self {
+        TailwindConfig {
+            command: String::new(),
+            input: None,
+            minify: true,
+        }
+    }
+}
+
+impl Default for AssetsConfig {
+    fn default() -> Self {
+        let extensions = [
+            "css", "js", "png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "ico", "woff",
+            "woff2", "ttf", "otf", "pdf", "txt",
+        ];
+        AssetsConfig {
+            static_dir: PathBuf::from("static"),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            copy: Vec::new(),
+            fingerprint: false,
+        }
+    }
+}
+
+impl Default for TaxonomyConfig {
+    fn default() -> Self {
+        TaxonomyConfig {
+            ordered: false,
+            terms: Vec::new(),
+            feed: true,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file at `config_path`, falling back to the defaults
+    /// when it does not exist.
+    pub fn load(config_path: &Path) -> Result<Config, BuildError> {
+        if !config_path.is_file() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(config_path).map_err(|source| BuildError::Read {
+            path: config_path.to_path_buf(),
+            source,
+        })?;
+        let mut config: Config = toml::from_str(&content).map_err(|source| BuildError::Config {
+            path: config_path.to_path_buf(),
+            source,
+        })?;
+
+        let mut taxonomies = default_taxonomies();
+        taxonomies.extend(std::mem::take(&mut config.taxonomies));
+        config.taxonomies = taxonomies;
+        let mut classes = default_classes();
+        classes.extend(std::mem::take(&mut config.classes));
+        config.classes = classes;
+        let mut light = light_palette();
+        light.extend(std::mem::take(&mut config.theme.light));
+        config.theme.light = light;
+        let mut dark = dark_palette();
+        dark.extend(std::mem::take(&mut config.theme.dark));
+        config.theme.dark = dark;
+        config.menu.sort_by_key(|item| item.weight);
+        crate::verbose!("Config loaded: {:?}", config_path);
+
+        Ok(config)
+    }
+
+    /// Absolute URL for a site-relative path such as `/posts/hello.html`.
+    pub fn absolute_url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+}
+
+fn default_taxonomies() -> BTreeMap<String, TaxonomyConfig> {
+    BTreeMap::from([(TAGS.to_string(), TaxonomyConfig::default())])
+}
+
+fn palette(colors: [(&str, &str); 7]) -> BTreeMap<String, String> {
+    colors
+        .into_iter()
+        .map(|(name, color)| (name.to_string(), color.to_string()))
+        .collect()
+}
+
+fn light_palette() -> BTreeMap<String, String> {
+    palette([
+        ("background", "#f9fafb"),
+        ("surface", "#e5e7eb"),
+        ("border", "#d1d5db"),
+        ("text", "#111827"),
+        ("muted", "#4b5563"),
+        ("accent", "#15803d"),
+        ("accent-hover", "#166534"),
+    ])
+}
+
+fn dark_palette() -> BTreeMap<String, String> {
+    palette([
+        ("background", "#1f2937"),
+        ("surface", "#374151"),
+        ("border", "#4b5563"),
+        ("text", "#ffffff"),
+        ("muted", "#9ca3af"),
+        ("accent", "#86efac"),
+        ("accent-hover", "#4ade80"),
+    ])
+}
+
+fn default_classes() -> BTreeMap<String, String> {
+    [
+        ("h1", "text-3xl font-bold"),
+        ("h2", "text-2xl font-bold mb-2"),
+        ("h3", "text-xl font-semibold mb-2"),
+        ("h4, h5, h6", "text-lg font-semibold mb-2"),
+        ("p", "text-[var(--muted)] mb-4"),
+        ("ul", "list-disc ml-6 mb-4 text-[var(--muted)]"),
+        ("ol", "list-decimal ml-6 mb-4 text-[var(--muted)]"),
+        ("li", "mb-1"),
+        ("dl", "mb-4 text-[var(--muted)]"),
+        ("dt", "font-semibold text-[var(--text)]"),
+        ("dd", "ml-6 mb-2"),
+        ("li > ul, li > ol", "mt-1 !mb-0"),
+        (
+            "pre.line-numbers .line",
+            "before:content-[attr(data-line)] before:inline-block before:w-8 before:mr-4 before:text-right before:opacity-50 before:select-none",
+        ),
+        (".line.highlight", "inline-block w-full bg-[var(--border)]"),
+        (".code-block", "mb-4"),
+        (
+            ".code-title",
+            "bg-[var(--border)] text-[var(--muted)] text-sm font-mono px-4 py-1 rounded-t",
+        ),
+        (".code-block pre", "!mb-0 !rounded-t-none"),
+        (
+            "blockquote",
+            "border-l-4 border-[var(--border)] pl-4 italic text-[var(--muted)] mb-4 [&>:last-child]:mb-0",
+        ),
+        (
+            "a:not(.anchor)",
+            "text-[var(--accent)] hover:text-[var(--accent-hover)]",
+        ),
+        ("img", "max-w-full h-auto rounded mb-4"),
+        (".table-wrapper", "overflow-x-auto mb-4"),
+        ("table", "table-auto border-collapse"),
+        ("thead", "border-b-2 border-[var(--border)]"),
+        (
+            "th",
+            "border border-[var(--border)] bg-[var(--surface)] px-3 py-1 text-left",
+        ),
+        ("td", "border border-[var(--border)] px-3 py-1"),
+        ("hr", "border-[var(--border)] my-8"),
+        ("del", "text-[var(--muted)]"),
+        ("input[type=checkbox]", "mr-2 accent-green-500"),
+        (
+            ".footnote-definition",
+            "flex gap-2 text-sm text-[var(--muted)]",
+        ),
+        (
+            ".admonition",
+            "border-l-4 rounded p-4 mb-4 [&>:last-child]:mb-0",
+        ),
+        (
+            ".admonition-title",
+            "flex items-center gap-2 font-semibold mb-2",
+        ),
+        (
+            ".admonition-note, .admonition-info",
+            "border-blue-500 bg-blue-500/10",
+        ),
+        (".admonition-tip", "border-green-500 bg-green-500/10"),
+        (
+            ".admonition-important",
+            "border-purple-500 bg-purple-500/10",
+        ),
+        (".admonition-warning", "border-yellow-500 bg-yellow-500/10"),
+        (".admonition-danger", "border-red-500 bg-red-500/10"),
+        (".notebook-cell", "mb-4 [&>:last-child]:mb-0"),
+        (
+            ".notebook-output",
+            "border-l-4 border-[var(--border)] pl-4 mb-4 overflow-x-auto [&>:last-child]:mb-0",
+        ),
+        (".notebook-stderr", "border-yellow-500"),
+        (".notebook-error", "border-red-500"),
+        (
+            "a.anchor",
+            "text-[var(--muted)] hover:text-[var(--accent)] opacity-0 group-hover:opacity-100",
+        ),
+        (
+            "pre",
+            "bg-[var(--surface)] text-[var(--accent)] p-4 rounded mb-4 overflow-x-auto",
+        ),
+        (
+            ":not(pre) > code",
+            "inline-block bg-[var(--surface)] text-[var(--accent)] px-1 rounded",
+        ),
+        ("pre code", "text-sm"),
+    ]
+    .into_iter()
+    .map(|(selector, classes)| (selector.to_string(), classes.to_string()))
+    .collect()
+}
@@ -0,0 +1,155 @@
+use crate::math::fenced_code_end;
+use pulldown_cmark::escape::escape_html;
+use pulldown_cmark::{Event, Tag};
+
+/// Kinds of `:::` containers, with the icon and default title of their box.
+const CONTAINERS: [(&str, &str, &str); 6] = [
+    ("note", "ℹ️", "Note"),
+    ("info", "ℹ️", "Info"),
+    ("tip", "💡", "Tip"),
+    ("important", "❗", "Important"),
+    ("warning", "⚠️", "Warning"),
+    ("danger", "⛔", "Danger"),
+];
+
+/// Mark the start and end of a placeholder, holding the index of its markup.
+/// Like math's, both are noncharacters.
+const OPEN: char = '\u{fdd2}';
+const CLOSE: char = '\u{fdd3}';
+
+/// An opening or closing line of a container.
+enum Fence<'a> {
+    /// `:::kind Optional title`, with the number of colons.
+    Open(usize, &'a str, &'a str),
+    /// `:::`, with the number of colons.
+    Close(usize),
+}
+
+/// Replaces the lines opening and closing `:::note` ... `:::` containers
+/// outside code with placeholder paragraphs, so the markdown between them is
+/// parsed as usual. Returns the markdown to parse and the markup of the
+/// placeholders, which `render` puts in once raw HTML has been sanitized.
+pub fn protect(markdown_content: &str) -> (String, Vec<String>) {
+    let mut markup = Vec::new();
+    let output = rewrite(markdown_content, |indent, fence| {
+        let placeholder = format!("\n{}{}{}{}\n\n", indent, OPEN, markup.len(), CLOSE);
+        markup.push(match fence {
+            Some((kind, title)) => opening_html(kind, title),
+            None => "</div>\n".to_string(),
+        });
+        placeholder
+    });
+    (output, markup)
+}
+
+/// `markdown_content` without the lines opening and closing containers, for
+/// the excerpt and the plain text of a post.
+pub fn strip(markdown_content: &str) -> String {
+    rewrite(markdown_content, |_, _| "\n".to_string())
+}
+
+/// Replaces the placeholders left by `protect`, and the paragraphs they are
+/// alone in, with the markup of their containers.
+pub fn render<'a>(events: impl Iterator<Item = Event<'a>>, markup: &[String]) -> Vec<Event<'a>> {
+    let mut output: Vec<Event<'a>> = Vec::new();
+    // Position in `output` of the last placeholder's markup, while nothing
+    // has followed it.
+    let mut placeholder: Option<usize> = None;
+    for event in events {
+        match event {
+            Event::Text(ref text) if text.starts_with(OPEN) => {
+                let index = text[OPEN.len_utf8()..].trim_end_matches(CLOSE);
+                if let Some(html) = index.parse::<usize>().ok().and_then(|i| markup.get(i)) {
+                    placeholder = Some(output.len());
+                    output.push(Event::Html(html.clone().into()));
+                    continue;
+                }
+                placeholder = None;
+            }
+            Event::End(Tag::Paragraph) if placeholder.is_some_and(|i| i + 1 == output.len()) => {
+                let i = placeholder.take().unwrap();
+                if i > 0 && output[i - 1] == Event::Start(Tag::Paragraph) {
+                    output.remove(i - 1);
+                    continue;
+                }
+            }
+            _ => placeholder = None,
+        }
+        output.push(event);
+    }
+    output
+}
+
+fn opening_html(kind: &str, title: &str) -> String {
+    let (kind, icon, default_title) = CONTAINERS
+        .into_iter()
+        .find(|(name, _, _)| *name == kind)
+        .unwrap_or(CONTAINERS[0]);
+    let mut title_html = String::new();
+    let _ = escape_html(&mut title_html, title);
+    if title_html.is_empty() {
+        title_html.push_str(default_title);
+    }
+    format!(
+        "<div class=\"admonition admonition-{}\" role=\"note\">\n\
+         <div class=\"admonition-title\"><span class=\"admonition-icon\" aria-hidden=\"true\">{}</span> {}</div>\n",
+        kind, icon, title_html
+    )
+}
+
+/// Passes every line of `markdown_content` through, except those opening and
+/// closing containers, which are replaced with `replace(indent, fence)`.
+/// `fence` is the kind and title of an opening line, and `None` for a closing
+/// one. Containers left open are closed at the end.
+fn rewrite(
+    markdown_content: &str,
+    mut replace: impl FnMut(&str, Option<(&str, &str)>) -> String,
+) -> String {
+    let mut output = String::with_capacity(markdown_content.len());
+    // Number of colons of the open containers, innermost last.
+    let mut open: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while i < markdown_content.len() {
+        let rest = &markdown_content[i..];
+        if let Some(end) = fenced_code_end(rest) {
+            output.push_str(&rest[..end]);
+            i += end;
+            continue;
+        }
+        let line = rest.split_inclusive('\n').next().unwrap_or(rest);
+        i += line.len();
+        let indent = &line[..line.len() - line.trim_start().len()];
+        match parse_fence(line) {
+            Some(Fence::Open(colons, kind, title)) => {
+                open.push(colons);
+                output.push_str(&replace(indent, Some((kind, title))));
+            }
+            Some(Fence::Close(colons)) if open.last().is_some_and(|&last| colons >= last) => {
+                open.pop();
+                output.push_str(&replace(indent, None));
+            }
+            _ => output.push_str(line),
+        }
+    }
+    for _ in open {
+        output.push_str(&replace("", None));
+    }
+    output
+}
+
+fn parse_fence(line: &str) -> Option<Fence<'_>> {
+    let line = line.trim();
+    let colons = line.len() - line.trim_start_matches(':').len();
+    if colons < 3 {
+        return None;
+    }
+    let rest = line[colons..].trim_start();
+    if rest.is_empty() {
+        return Some(Fence::Close(colons));
+    }
+    let (kind, title) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    CONTAINERS
+        .iter()
+        .any(|(name, _, _)| *name == kind)
+        .then(|| Fence::Open(colons, kind, title.trim()))
+}
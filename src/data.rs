@@ -0,0 +1,76 @@
+use crate::site::sorted_entries;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+/// Directory at the source root whose YAML, TOML and JSON files are available
+/// to templates as `data.<file stem>`.
+pub const DATA_DIR: &str = "data";
+
+/// Contents of the data files, keyed by file stem.
+pub type Data = BTreeMap<String, Value>;
+
+/// Reads every `.yaml`, `.yml`, `.toml` and `.json` file in `<source>/data/`,
+/// which may not exist. Other files are ignored, and two files with the same
+/// stem are an error.
+pub fn load_data(source_dir: &Path) -> Result<Data, Box<dyn Error>> {
+    let mut data = Data::new();
+    let data_dir = source_dir.join(DATA_DIR);
+    if !data_dir.is_dir() {
+        return Ok(data);
+    }
+
+    for path in sorted_entries(&data_dir)? {
+        let Some(extension) = path.extension().and_then(OsStr::to_str) else {
+            continue;
+        };
+        if !matches!(extension, "yaml" | "yml" | "toml" | "json") || !path.is_file() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let parse_error = |e: &dyn Error| format!("Invalid data file {:?}: {}", path, e);
+        let value: Value = match extension {
+            "toml" => {
+                let table: toml::Table = toml::from_str(&content).map_err(|e| parse_error(&e))?;
+                from_toml(toml::Value::Table(table))
+            }
+            "json" => serde_json::from_str(&content).map_err(|e| parse_error(&e))?,
+            _ => serde_yaml::from_str(&content).map_err(|e| parse_error(&e))?,
+        };
+
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        if data.contains_key(&name) {
+            return Err(format!(
+                "Data file {:?} clashes with another file named {:?}",
+                path, name
+            )
+            .into());
+        }
+        crate::verbose!("Data file loaded: {:?}", path);
+        data.insert(name, value);
+    }
+
+    Ok(data)
+}
+
+/// TOML dates and times become strings, as they are written in the file.
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        toml::Value::Float(float) => Value::from(float),
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => Value::Array(array.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, from_toml(value)))
+                .collect(),
+        ),
+    }
+}
@@ -0,0 +1,102 @@
+use pulldown_cmark::{Event, Tag};
+
+/// Turns paragraphs holding a term followed by `: ` lines into definition
+/// lists, as in PHP Markdown Extra:
+///
+/// ```markdown
+/// Term
+/// : Its definition
+/// ```
+///
+/// Every line before the first definition is a term, and lines after a
+/// definition continue it. Consecutive lists, entries divided by blank lines,
+/// are merged into one.
+pub fn definition_lists<'a>(events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+    let mut output: Vec<Event<'a>> = Vec::new();
+    // The inline events of the open paragraph.
+    let mut paragraph: Option<Vec<Event<'a>>> = None;
+    // Whether the last event in `output` closes a definition list.
+    let mut after_list = false;
+    for event in events {
+        if let Some(inline) = &mut paragraph {
+            if event != Event::End(Tag::Paragraph) {
+                inline.push(event);
+                continue;
+            }
+            match definition_items(paragraph.take().unwrap()) {
+                Ok(items) => {
+                    if after_list {
+                        output.pop();
+                    } else {
+                        output.push(Event::Html("<dl>\n".into()));
+                    }
+                    output.extend(items);
+                    output.push(Event::Html("</dl>\n".into()));
+                    after_list = true;
+                }
+                Err(inline) => {
+                    output.push(Event::Start(Tag::Paragraph));
+                    output.extend(inline);
+                    output.push(event);
+                    after_list = false;
+                }
+            }
+            continue;
+        }
+        if event == Event::Start(Tag::Paragraph) {
+            paragraph = Some(Vec::new());
+            continue;
+        }
+        output.push(event);
+        after_list = false;
+    }
+    output
+}
+
+/// The `<dt>` and `<dd>` items of a paragraph of terms and definitions, or
+/// the paragraph back if it isn't one.
+fn definition_items(inline: Vec<Event<'_>>) -> Result<Vec<Event<'_>>, Vec<Event<'_>>> {
+    let mut lines: Vec<Vec<Event<'_>>> = vec![Vec::new()];
+    for event in inline {
+        match event {
+            Event::SoftBreak => lines.push(Vec::new()),
+            event => lines.last_mut().unwrap().push(event),
+        }
+    }
+    if is_definition(&lines[0]) || !lines.iter().any(|line| is_definition(line)) {
+        return Err(lines.join(&Event::SoftBreak));
+    }
+
+    let mut items = Vec::new();
+    let mut close: Option<&str> = None;
+    for mut line in lines {
+        let definition = is_definition(&line);
+        if !definition && close == Some("</dd>\n") {
+            items.push(Event::SoftBreak);
+            items.extend(line);
+            continue;
+        }
+        if definition {
+            if let Event::Text(text) = &line[0] {
+                line[0] = Event::Text(text[1..].trim_start().to_string().into());
+            }
+        }
+        if let Some(close) = close {
+            items.push(Event::Html(close.into()));
+        }
+        let (open, end) = match definition {
+            true => ("<dd>", "</dd>\n"),
+            false => ("<dt>", "</dt>\n"),
+        };
+        items.push(Event::Html(open.into()));
+        items.extend(line);
+        close = Some(end);
+    }
+    items.extend(close.map(|close| Event::Html(close.into())));
+    Ok(items)
+}
+
+/// Whether a line of a paragraph starts a definition, with `: `.
+fn is_definition(line: &[Event<'_>]) -> bool {
+    matches!(line.first(), Some(Event::Text(text)) if text.starts_with(": "))
+}
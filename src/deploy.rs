@@ -0,0 +1,509 @@
+use crate::cache::CACHE_FILE;
+use crate::config::{Config, DeployConfig, DEPLOY_TARGETS};
+use crate::dry_run::relative_files;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Netlify's endpoint for creating a deploy of a site from a zip file.
+const NETLIFY_API: &str = "https://api.netlify.com/api/v1/sites";
+
+/// Marks a GitHub Pages branch as plain files, so Jekyll leaves `_`-prefixed
+/// paths alone.
+const NOJEKYLL_FILE: &str = ".nojekyll";
+
+/// Names the custom domain of a GitHub Pages site.
+const CNAME_FILE: &str = "CNAME";
+
+/// Git credential helper answering with `GITHUB_TOKEN`, as GitHub Actions
+/// provides it.
+const GITHUB_TOKEN_HELPER: &str =
+    "credential.helper=!f() { echo username=x-access-token; echo \"password=$GITHUB_TOKEN\"; }; f";
+
+/// `target`, or else the target of `[deploy]`, checked to be one of
+/// `DEPLOY_TARGETS` before the site is built for it.
+pub fn deploy_target<'a>(
+    config: &'a DeployConfig,
+    target: Option<&'a str>,
+) -> Result<&'a str, Box<dyn Error>> {
+    match target.unwrap_or(&config.target) {
+        "" => Err(format!(
+            "No deploy target: set target under [deploy] in ebolg.toml or pass --target, one of: {}",
+            DEPLOY_TARGETS.join(", ")
+        )
+        .into()),
+        target if DEPLOY_TARGETS.contains(&target) => Ok(target),
+        target => Err(format!(
+            "Unknown deploy target {:?}, expected one of: {}",
+            target,
+            DEPLOY_TARGETS.join(", ")
+        )
+        .into()),
+    }
+}
+
+/// Publishes the built site in `output_dir` to `target`, see `deploy_target`.
+/// rsync, S3 and GitHub Pages go through the `rsync`, `aws` and `git`
+/// commands, which find their credentials as usual; Netlify is called
+/// directly. A dry run runs rsync and the AWS CLI in their own dry-run modes
+/// and only says what would be pushed elsewhere.
+pub fn deploy(
+    config: &Config,
+    target: &str,
+    source_dir: &Path,
+    output_dir: &Path,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    match target {
+        "rsync" => deploy_rsync(&config.deploy, output_dir, dry_run),
+        "s3" => deploy_s3(&config.deploy, output_dir, dry_run),
+        "github-pages" => deploy_github_pages(config, source_dir, output_dir, dry_run),
+        "netlify" => deploy_netlify(&config.deploy, output_dir, dry_run),
+        _ => deploy_target(&config.deploy, Some(target)).map(|_| ()),
+    }
+}
+
+fn deploy_rsync(
+    config: &DeployConfig,
+    output_dir: &Path,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let destination = required(&config.destination, "destination", "rsync")?;
+    let mut command = Command::new("rsync");
+    command
+        .args(["--archive", "--compress", "--delete", "--verbose"])
+        .arg(format!("--exclude=/{}", CACHE_FILE));
+    if dry_run {
+        command.arg("--dry-run");
+    }
+    // The trailing slash copies the directory's contents, not the directory.
+    command
+        .arg(format!("{}/", output_dir.display()))
+        .arg(destination);
+    run(&mut command)?;
+    println!("Deployed {:?} to {}", output_dir, destination);
+    Ok(())
+}
+
+fn deploy_s3(
+    config: &DeployConfig,
+    output_dir: &Path,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let bucket = required(&config.bucket, "bucket", "s3")?;
+    let mut sync = aws(config);
+    sync.args(["s3", "sync", "--delete", "--exclude", CACHE_FILE])
+        .arg(output_dir)
+        .arg(format!("s3://{}", bucket.trim_start_matches("s3://")));
+    if dry_run {
+        sync.arg("--dryrun");
+    }
+    run(&mut sync)?;
+
+    if !config.distribution.is_empty() {
+        let mut invalidate = aws(config);
+        invalidate
+            .args(["cloudfront", "create-invalidation", "--paths", "/*"])
+            .args(["--distribution-id", &config.distribution]);
+        match dry_run {
+            true => println!(
+                "Would invalidate CloudFront distribution {}",
+                config.distribution
+            ),
+            false => run(&mut invalidate)?,
+        }
+    }
+    println!("Deployed {:?} to s3://{}", output_dir, bucket);
+    Ok(())
+}
+
+fn aws(config: &DeployConfig) -> Command {
+    let mut command = Command::new("aws");
+    if !config.profile.is_empty() {
+        command.args(["--profile", &config.profile]);
+    }
+    command
+}
+
+/// Commits the output directory to the Pages branch from a scratch
+/// repository, on top of the branch's last commit, and pushes it. With
+/// `force`, the branch is replaced by a single commit instead. With
+/// `GITHUB_TOKEN` set, as in GitHub Actions, it authenticates pushes to
+/// `https://github.com/` remotes.
+fn deploy_github_pages(
+    config: &Config,
+    source_dir: &Path,
+    output_dir: &Path,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let deploy = &config.deploy;
+    let repo = match deploy.repo.as_str() {
+        "" => git_output(source_dir, &["remote", "get-url", "origin"])
+            .ok_or("No repo under [deploy] and the source directory has no origin remote")?,
+        repo => repo.to_string(),
+    };
+    let branch = deploy.branch.as_str();
+    // The output directory holds the CNAME of the last deploy, so only the
+    // static directory's own one takes precedence over the config.
+    let static_cname = source_dir.join(&config.assets.static_dir).join(CNAME_FILE);
+    let cname = cname(config).filter(|_| !static_cname.exists());
+    let message = match git_output(source_dir, &["log", "-1", "--format=%h: %s"]) {
+        Some(commit) => format!("Deploy {}", commit),
+        None => String::from("Deploy site"),
+    };
+    let files = site_files(output_dir)?;
+    if dry_run {
+        println!(
+            "Would push {} files to the {} branch of {}{}: {:?}",
+            files.len(),
+            branch,
+            repo,
+            cname
+                .map(|domain| format!(" with a CNAME for {}", domain))
+                .unwrap_or_default(),
+            message
+        );
+        return Ok(());
+    }
+
+    fs::write(output_dir.join(NOJEKYLL_FILE), "")?;
+    if let Some(domain) = &cname {
+        fs::write(output_dir.join(CNAME_FILE), format!("{}\n", domain))?;
+    }
+    // The helper reads the token from the environment when git asks for it,
+    // so it never shows up in a command line.
+    let use_token = repo.starts_with("https://github.com/")
+        && env::var("GITHUB_TOKEN").is_ok_and(|token| !token.is_empty());
+    let git_dir = env::temp_dir().join(format!("ebolg-deploy-{}", std::process::id()));
+    if git_dir.exists() {
+        fs::remove_dir_all(&git_dir)?;
+    }
+    let git = |args: &[&str]| {
+        let mut command = Command::new("git");
+        command
+            .arg("--git-dir")
+            .arg(&git_dir)
+            .arg("--work-tree")
+            .arg(output_dir)
+            .args(["-c", "user.name=ebolg", "-c", "user.email=ebolg@localhost"]);
+        if use_token {
+            command.args(["-c", "credential.helper=", "-c", GITHUB_TOKEN_HELPER]);
+        }
+        command.args(args);
+        command
+    };
+    let result = commit_pages(&git, &git_dir, &repo, branch, deploy.force, &message);
+    fs::remove_dir_all(&git_dir)?;
+    match result? {
+        true => println!(
+            "Deployed {:?} to the {} branch of {}",
+            output_dir, branch, repo
+        ),
+        false => println!(
+            "Nothing to deploy: the {} branch of {} is up to date",
+            branch, repo
+        ),
+    }
+    Ok(())
+}
+
+/// Commits the work tree of `git` and pushes it to `branch` of `repo`,
+/// returning whether there was anything to push.
+fn commit_pages(
+    git: &dyn Fn(&[&str]) -> Command,
+    git_dir: &Path,
+    repo: &str,
+    branch: &str,
+    force: bool,
+    message: &str,
+) -> Result<bool, Box<dyn Error>> {
+    run(&mut git(&["init", "--quiet"]))?;
+    fs::write(git_dir.join("info").join("exclude"), CACHE_FILE)?;
+    // The branch doesn't exist before the first deploy, so the fetch may fail.
+    let fetched = !force
+        && git(&["fetch", "--quiet", "--depth", "1", repo, branch])
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+    if fetched {
+        run(&mut git(&["update-ref", "HEAD", "FETCH_HEAD"]))?;
+    }
+    run(&mut git(&["add", "--all"]))?;
+    let unchanged = fetched
+        && git(&["diff", "--cached", "--quiet", "HEAD"])
+            .status()
+            .is_ok_and(|status| status.success());
+    if unchanged {
+        return Ok(false);
+    }
+    run(&mut git(&["commit", "--quiet", "--message", message]))?;
+    let refspec = format!("HEAD:refs/heads/{}", branch);
+    let mut push = git(&["push", "--quiet", repo, &refspec]);
+    if force || !fetched {
+        push.arg("--force");
+    }
+    run(&mut push)?;
+    Ok(true)
+}
+
+/// The domain GitHub Pages serves the site at: `cname` under `[deploy]`, or
+/// else the host of `base_url` unless it is a `github.io` one.
+fn cname(config: &Config) -> Option<String> {
+    if !config.deploy.cname.is_empty() {
+        return Some(config.deploy.cname.clone());
+    }
+    let host = config.base_url.split_once("://")?.1;
+    let host = host.split(['/', ':']).next()?;
+    match host.is_empty() || host == "localhost" || host.ends_with(".github.io") {
+        true => None,
+        false => Some(host.to_string()),
+    }
+}
+
+/// The trimmed output of `git args` in `dir`, when it succeeds.
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !text.is_empty()).then_some(text)
+}
+
+/// Uploads the output directory to Netlify as a zip file, which Netlify
+/// publishes as the site's production deploy.
+fn deploy_netlify(
+    config: &DeployConfig,
+    output_dir: &Path,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let site_id = env::var("NETLIFY_SITE_ID").unwrap_or_else(|_| config.site_id.clone());
+    let token = env::var("NETLIFY_AUTH_TOKEN").unwrap_or_else(|_| config.token.clone());
+    if site_id.is_empty() || token.is_empty() {
+        return Err(
+            "Deploying to netlify needs site_id and token under [deploy] in ebolg.toml, or NETLIFY_SITE_ID and NETLIFY_AUTH_TOKEN"
+                .into(),
+        );
+    }
+    let files = site_files(output_dir)?;
+    if dry_run {
+        println!(
+            "Would upload {} files to Netlify site {}",
+            files.len(),
+            site_id
+        );
+        return Ok(());
+    }
+
+    let archive = zip(output_dir, &files)?;
+    crate::verbose!("Uploading {} bytes to Netlify", archive.len());
+    let response = ureq::post(&format!("{}/{}/deploys", NETLIFY_API, site_id))
+        .set("Authorization", &format!("Bearer {}", token))
+        .set("Content-Type", "application/zip")
+        .send_bytes(&archive)
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => format!(
+                "Netlify answered {}: {}",
+                code,
+                response.into_string().unwrap_or_default().trim()
+            ),
+            ureq::Error::Transport(transport) => format!("Could not reach Netlify: {}", transport),
+        })?;
+    let deploy: serde_json::Value = serde_json::from_str(&response.into_string()?)?;
+    let url = deploy["ssl_url"]
+        .as_str()
+        .or(deploy["url"].as_str())
+        .unwrap_or(&site_id);
+    println!("Deployed {:?} to {}", output_dir, url);
+    Ok(())
+}
+
+fn required<'a>(value: &'a str, key: &str, target: &str) -> Result<&'a str, Box<dyn Error>> {
+    match value {
+        "" => Err(format!(
+            "Deploying to {} needs {} under [deploy] in ebolg.toml",
+            target, key
+        )
+        .into()),
+        value => Ok(value),
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), Box<dyn Error>> {
+    crate::verbose!("Running {:?}", command);
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|e| format!("Could not run {}: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("{} failed ({})", program, status).into());
+    }
+    Ok(())
+}
+
+/// Every file of the built site, relative to `output_dir`, or an error when
+/// there is none.
+fn site_files(output_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !output_dir.is_dir() {
+        return Err(format!("Nothing to deploy: {:?} does not exist", output_dir).into());
+    }
+    relative_files(output_dir)
+}
+
+/// A zip archive of `files` under `dir`, deflated, with every entry dated
+/// 1980-01-01 so the same site makes the same archive.
+fn zip(dir: &Path, files: &[PathBuf]) -> Result<Vec<u8>, Box<dyn Error>> {
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    let too_large = || format!("{:?} is too large to upload as one zip file", dir);
+    let mut archive = Vec::new();
+    let mut central = Vec::new();
+    for file in files {
+        let name = file
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| format!("{:?} has too long a path to zip", file))?;
+        let contents = fs::read(dir.join(file))?;
+        let mut crc = Crc::new();
+        crc.update(&contents);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&contents)?;
+        let compressed = encoder.finish()?;
+
+        let offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(contents.len()).map_err(|_| too_large())?;
+        let compressed_size = u32::try_from(compressed.len()).map_err(|_| too_large())?;
+        // Version 2.0, UTF-8 names, deflated.
+        let mut fields = Vec::new();
+        for value in [20u16, 0x0800, 8, 0, DOS_DATE] {
+            fields.extend(value.to_le_bytes());
+        }
+        for value in [crc.sum(), compressed_size, size] {
+            fields.extend(value.to_le_bytes());
+        }
+        fields.extend(name_len.to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+
+        archive.extend(0x04034b50u32.to_le_bytes());
+        archive.extend(&fields);
+        archive.extend(name.as_bytes());
+        archive.extend(&compressed);
+
+        central.extend(0x02014b50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend(&fields);
+        // No comment, on disk 0, no attributes.
+        central.extend([0; 10]);
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+
+    let count = u16::try_from(files.len()).map_err(|_| too_large())?;
+    let central_offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+    let central_len = u32::try_from(central.len()).map_err(|_| too_large())?;
+    archive.extend(&central);
+    archive.extend(0x06054b50u32.to_le_bytes());
+    archive.extend([0; 4]);
+    archive.extend(count.to_le_bytes());
+    archive.extend(count.to_le_bytes());
+    archive.extend(central_len.to_le_bytes());
+    archive.extend(central_offset.to_le_bytes());
+    archive.extend(0u16.to_le_bytes());
+    Ok(archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    fn u16_at(bytes: &[u8], at: usize) -> usize {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]]) as usize
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// The names and contents of the entries of `archive`, read through its
+    /// central directory as unzip reads them.
+    fn unzip(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(archive, end), 0x06054b50);
+        let count = u16_at(archive, end + 10);
+        let mut at = u32_at(archive, end + 16) as usize;
+        assert_eq!(at + u32_at(archive, end + 12) as usize, end);
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(archive, at), 0x02014b50);
+            let crc = u32_at(archive, at + 16);
+            let compressed_size = u32_at(archive, at + 20) as usize;
+            let size = u32_at(archive, at + 24) as usize;
+            let name_len = u16_at(archive, at + 28);
+            let extra_len = u16_at(archive, at + 30) + u16_at(archive, at + 32);
+            let offset = u32_at(archive, at + 42) as usize;
+            let name = String::from_utf8(archive[at + 46..at + 46 + name_len].to_vec()).unwrap();
+            at += 46 + name_len + extra_len;
+
+            assert_eq!(u32_at(archive, offset), 0x04034b50);
+            assert_eq!(u16_at(archive, offset + 8), 8, "deflated");
+            let start = offset + 30 + u16_at(archive, offset + 26) + u16_at(archive, offset + 28);
+            let mut contents = Vec::new();
+            DeflateDecoder::new(&archive[start..start + compressed_size])
+                .read_to_end(&mut contents)
+                .unwrap();
+            assert_eq!(contents.len(), size);
+            let mut sum = Crc::new();
+            sum.update(&contents);
+            assert_eq!(sum.sum(), crc);
+            entries.push((name, contents));
+        }
+        entries
+    }
+
+    #[test]
+    fn zip_round_trips() {
+        let dir = env::temp_dir().join(format!("ebolg-zip-test-{}", std::process::id()));
+        let files = [
+            (PathBuf::from("index.html"), b"<h1>Hello</h1>".repeat(100)),
+            (
+                PathBuf::from("posts/caf\u{e9}.html"),
+                b"<p>\xc3\xa9</p>".to_vec(),
+            ),
+            (PathBuf::from("empty.txt"), Vec::new()),
+        ];
+        for (path, contents) in &files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        let paths: Vec<PathBuf> = files.iter().map(|(path, _)| path.clone()).collect();
+        let archive = zip(&dir, &paths);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let expected: Vec<(String, Vec<u8>)> = files
+            .into_iter()
+            .map(|(path, contents)| (path.to_string_lossy().into_owned(), contents))
+            .collect();
+        assert_eq!(unzip(&archive.unwrap()), expected);
+    }
+
+    #[test]
+    fn zip_refuses_names_too_long() {
+        let dir = env::temp_dir();
+        let name = PathBuf::from("x".repeat(usize::from(u16::MAX) + 1));
+        let error = zip(&dir, &[name]).unwrap_err();
+        assert!(error.to_string().contains("too long a path"), "{}", error);
+    }
+}
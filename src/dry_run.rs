@@ -0,0 +1,76 @@
+use crate::cache::CACHE_FILE;
+use fs_extra::dir::CopyOptions;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Copies `output_dir`, if it exists, to a scratch directory for a dry run to
+/// build into, so incremental builds and removals behave as they would there.
+pub fn scratch_copy(output_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let scratch_dir = std::env::temp_dir().join(format!("ebolg-dry-run-{}", std::process::id()));
+    if scratch_dir.exists() {
+        fs::remove_dir_all(&scratch_dir)?;
+    }
+    fs::create_dir_all(&scratch_dir)?;
+    if output_dir.is_dir() {
+        fs_extra::dir::copy(
+            output_dir,
+            &scratch_dir,
+            &CopyOptions::new().content_only(true),
+        )?;
+    }
+    Ok(scratch_dir)
+}
+
+/// Lists the files the build in `scratch_dir` created, changed and deleted
+/// compared to `output_dir`, then removes the scratch directory.
+pub fn report_changes(output_dir: &Path, scratch_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let before = relative_files(output_dir)?;
+    let after = relative_files(scratch_dir)?;
+
+    let (mut created, mut updated, mut deleted) = (0, 0, 0);
+    for path in &after {
+        let target = output_dir.join(path);
+        if !before.contains(path) {
+            println!("Would create {:?}", target);
+            created += 1;
+        } else if fs::read(&target)? != fs::read(scratch_dir.join(path))? {
+            println!("Would update {:?}", target);
+            updated += 1;
+        }
+    }
+    for path in before.iter().filter(|path| !after.contains(path)) {
+        println!("Would delete {:?}", output_dir.join(path));
+        deleted += 1;
+    }
+    println!(
+        "Dry run: {} files would be created, {} updated and {} deleted in {:?}",
+        created, updated, deleted, output_dir
+    );
+
+    fs::remove_dir_all(scratch_dir)?;
+    Ok(())
+}
+
+/// Every file under `dir`, relative to it and sorted, except the build cache.
+pub fn relative_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        if !current.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Ok(relative) = path.strip_prefix(dir) {
+                if relative != Path::new(CACHE_FILE) {
+                    files.push(relative.to_path_buf());
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
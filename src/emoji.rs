@@ -0,0 +1,518 @@
+use crate::config::EmojiConfig;
+use pulldown_cmark::{CowStr, Event, Tag};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+/// What `[emoji] style` can turn shortcodes into: the Unicode characters, or
+/// Twemoji's SVG images of them.
+pub const EMOJI_STYLES: [&str; 2] = ["unicode", "twemoji"];
+
+/// The built-in shortcodes, named as on GitHub. `[emoji] custom` adds more.
+const EMOJI: [(&str, &str); 405] = [
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("1st_place_medal", "🥇"),
+    ("airplane", "✈️"),
+    ("alarm_clock", "⏰"),
+    ("alien", "👽"),
+    ("anchor", "⚓"),
+    ("angry", "😠"),
+    ("apple", "🍎"),
+    ("arrow_down", "⬇️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("arrow_up", "⬆️"),
+    ("arrows_counterclockwise", "🔄"),
+    ("art", "🎨"),
+    ("astonished", "😲"),
+    ("avocado", "🥑"),
+    ("bacon", "🥓"),
+    ("balloon", "🎈"),
+    ("ballot_box_with_check", "☑️"),
+    ("banana", "🍌"),
+    ("bangbang", "‼️"),
+    ("bar_chart", "📊"),
+    ("basketball", "🏀"),
+    ("bat", "🦇"),
+    ("battery", "🔋"),
+    ("beach_umbrella", "🏖️"),
+    ("bear", "🐻"),
+    ("bee", "🐝"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("bell", "🔔"),
+    ("bike", "🚲"),
+    ("bird", "🐦"),
+    ("birthday", "🎂"),
+    ("black_circle", "⚫"),
+    ("black_heart", "🖤"),
+    ("blue_heart", "💙"),
+    ("blush", "😊"),
+    ("book", "📖"),
+    ("bookmark", "🔖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("bouquet", "💐"),
+    ("brain", "🧠"),
+    ("bread", "🍞"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("burrito", "🌯"),
+    ("bus", "🚌"),
+    ("butterfly", "🦋"),
+    ("cactus", "🌵"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("camera", "📷"),
+    ("camping", "🏕️"),
+    ("candle", "🕯️"),
+    ("candy", "🍬"),
+    ("car", "🚗"),
+    ("carrot", "🥕"),
+    ("cat", "🐱"),
+    ("cd", "💿"),
+    ("chains", "⛓️"),
+    ("champagne", "🍾"),
+    ("chart_with_downwards_trend", "📉"),
+    ("chart_with_upwards_trend", "📈"),
+    ("checkered_flag", "🏁"),
+    ("cheese", "🧀"),
+    ("cherries", "🍒"),
+    ("cherry_blossom", "🌸"),
+    ("chicken", "🐔"),
+    ("chocolate_bar", "🍫"),
+    ("christmas_tree", "🎄"),
+    ("clap", "👏"),
+    ("clipboard", "📋"),
+    ("cloud", "☁️"),
+    ("clown_face", "🤡"),
+    ("cocktail", "🍸"),
+    ("coffee", "☕"),
+    ("collision", "💥"),
+    ("computer", "💻"),
+    ("computer_mouse", "🖱️"),
+    ("confetti_ball", "🎊"),
+    ("confused", "😕"),
+    ("construction", "🚧"),
+    ("cookie", "🍪"),
+    ("cool", "🆒"),
+    ("copyright", "©️"),
+    ("corn", "🌽"),
+    ("cow", "🐮"),
+    ("crab", "🦀"),
+    ("credit_card", "💳"),
+    ("crescent_moon", "🌙"),
+    ("crossed_fingers", "🤞"),
+    ("crown", "👑"),
+    ("cry", "😢"),
+    ("dart", "🎯"),
+    ("dash", "💨"),
+    ("date", "📅"),
+    ("deciduous_tree", "🌳"),
+    ("desert", "🏜️"),
+    ("desktop_computer", "🖥️"),
+    ("disappointed", "😞"),
+    ("dizzy", "💫"),
+    ("dna", "🧬"),
+    ("dog", "🐶"),
+    ("dollar", "💵"),
+    ("dolphin", "🐬"),
+    ("doughnut", "🍩"),
+    ("dragon", "🐉"),
+    ("droplet", "💧"),
+    ("dvd", "📀"),
+    ("eagle", "🦅"),
+    ("earth_africa", "🌍"),
+    ("earth_americas", "🌎"),
+    ("earth_asia", "🌏"),
+    ("egg", "🥚"),
+    ("electric_plug", "🔌"),
+    ("email", "📧"),
+    ("envelope", "✉️"),
+    ("evergreen_tree", "🌲"),
+    ("exclamation", "❗"),
+    ("expressionless", "😑"),
+    ("eyeglasses", "👓"),
+    ("eyes", "👀"),
+    ("fallen_leaf", "🍂"),
+    ("file_folder", "📁"),
+    ("fire", "🔥"),
+    ("fish", "🐟"),
+    ("fist", "👊"),
+    ("flashlight", "🔦"),
+    ("floppy_disk", "💾"),
+    ("flushed", "😳"),
+    ("football", "🏈"),
+    ("four_leaf_clover", "🍀"),
+    ("fox_face", "🦊"),
+    ("free", "🆓"),
+    ("fries", "🍟"),
+    ("frog", "🐸"),
+    ("full_moon", "🌕"),
+    ("game_die", "🎲"),
+    ("gear", "⚙️"),
+    ("gem", "💎"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("grapes", "🍇"),
+    ("green_apple", "🍏"),
+    ("green_circle", "🟢"),
+    ("green_heart", "💚"),
+    ("grey_exclamation", "❕"),
+    ("grey_question", "❔"),
+    ("grimacing", "😬"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("guitar", "🎸"),
+    ("hamburger", "🍔"),
+    ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠️"),
+    ("handshake", "🤝"),
+    ("headphones", "🎧"),
+    ("hear_no_evil", "🙉"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("heavy_check_mark", "✔️"),
+    ("heavy_minus_sign", "➖"),
+    ("heavy_multiplication_x", "✖️"),
+    ("heavy_plus_sign", "➕"),
+    ("herb", "🌿"),
+    ("honeybee", "🐝"),
+    ("horse", "🐴"),
+    ("hospital", "🏥"),
+    ("hot_pepper", "🌶️"),
+    ("hotdog", "🌭"),
+    ("hourglass", "⌛"),
+    ("hourglass_flowing_sand", "⏳"),
+    ("house", "🏠"),
+    ("hugs", "🤗"),
+    ("inbox_tray", "📥"),
+    ("information_source", "ℹ️"),
+    ("innocent", "😇"),
+    ("interrobang", "⁉️"),
+    ("iphone", "📱"),
+    ("jeans", "👖"),
+    ("jigsaw", "🧩"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("keyboard", "⌨️"),
+    ("kissing_heart", "😘"),
+    ("koala", "🐨"),
+    ("label", "🏷️"),
+    ("large_blue_circle", "🔵"),
+    ("laughing", "😆"),
+    ("ledger", "📒"),
+    ("lemon", "🍋"),
+    ("link", "🔗"),
+    ("lion", "🦁"),
+    ("lock", "🔒"),
+    ("mag", "🔍"),
+    ("mag_right", "🔎"),
+    ("magnet", "🧲"),
+    ("mailbox", "📫"),
+    ("maple_leaf", "🍁"),
+    ("mask", "😷"),
+    ("medal_sports", "🏅"),
+    ("memo", "📝"),
+    ("metal", "🤘"),
+    ("microphone", "🎤"),
+    ("microscope", "🔬"),
+    ("moneybag", "💰"),
+    ("monkey_face", "🐵"),
+    ("mortar_board", "🎓"),
+    ("mountain", "⛰️"),
+    ("mouse", "🐭"),
+    ("movie_camera", "🎥"),
+    ("muscle", "💪"),
+    ("mushroom", "🍄"),
+    ("musical_note", "🎵"),
+    ("national_park", "🏞️"),
+    ("necktie", "👔"),
+    ("nerd_face", "🤓"),
+    ("neutral_face", "😐"),
+    ("new", "🆕"),
+    ("new_moon", "🌑"),
+    ("newspaper", "📰"),
+    ("no_bell", "🔕"),
+    ("no_entry", "⛔"),
+    ("no_entry_sign", "🚫"),
+    ("no_mouth", "😶"),
+    ("notebook", "📓"),
+    ("notes", "🎶"),
+    ("nut_and_bolt", "🔩"),
+    ("ocean", "🌊"),
+    ("octopus", "🐙"),
+    ("office", "🏢"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("old_key", "🗝️"),
+    ("open_file_folder", "📂"),
+    ("open_hands", "👐"),
+    ("open_mouth", "😮"),
+    ("orange_circle", "🟠"),
+    ("orange_heart", "🧡"),
+    ("outbox_tray", "📤"),
+    ("owl", "🦉"),
+    ("package", "📦"),
+    ("page_facing_up", "📄"),
+    ("palm_tree", "🌴"),
+    ("panda_face", "🐼"),
+    ("paperclip", "📎"),
+    ("partly_sunny", "⛅"),
+    ("partying_face", "🥳"),
+    ("peach", "🍑"),
+    ("pen", "🖊️"),
+    ("pencil2", "✏️"),
+    ("penguin", "🐧"),
+    ("pensive", "😔"),
+    ("performing_arts", "🎭"),
+    ("pig", "🐷"),
+    ("pill", "💊"),
+    ("pirate_flag", "🏴‍☠️"),
+    ("pizza", "🍕"),
+    ("pleading_face", "🥺"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "👆"),
+    ("poop", "💩"),
+    ("popcorn", "🍿"),
+    ("pray", "🙏"),
+    ("printer", "🖨️"),
+    ("purple_circle", "🟣"),
+    ("purple_heart", "💜"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rabbit", "🐰"),
+    ("radio", "📻"),
+    ("rage", "😡"),
+    ("rainbow", "🌈"),
+    ("rainbow_flag", "🏳️‍🌈"),
+    ("raised_eyebrow", "🤨"),
+    ("raised_hands", "🙌"),
+    ("ramen", "🍜"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("registered", "®️"),
+    ("relieved", "😌"),
+    ("repeat", "🔁"),
+    ("ribbon", "🎀"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("roll_eyes", "🙄"),
+    ("rose", "🌹"),
+    ("round_pushpin", "📍"),
+    ("sailboat", "⛵"),
+    ("school", "🏫"),
+    ("scissors", "✂️"),
+    ("scream", "😱"),
+    ("scroll", "📜"),
+    ("see_no_evil", "🙈"),
+    ("seedling", "🌱"),
+    ("shark", "🦈"),
+    ("shield", "🛡️"),
+    ("ship", "🚢"),
+    ("shirt", "👕"),
+    ("shushing_face", "🤫"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("sleepy", "😪"),
+    ("slightly_frowning_face", "🙁"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("snail", "🐌"),
+    ("snake", "🐍"),
+    ("snowflake", "❄️"),
+    ("snowman", "⛄"),
+    ("sob", "😭"),
+    ("soccer", "⚽"),
+    ("sos", "🆘"),
+    ("spaghetti", "🍝"),
+    ("sparkles", "✨"),
+    ("sparkling_heart", "💖"),
+    ("speak_no_evil", "🙊"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("star_struck", "🤩"),
+    ("statue_of_liberty", "🗽"),
+    ("stopwatch", "⏱️"),
+    ("strawberry", "🍓"),
+    ("stuck_out_tongue", "😛"),
+    ("stuck_out_tongue_winking_eye", "😜"),
+    ("sunflower", "🌻"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀️"),
+    ("sushi", "🍣"),
+    ("sweat", "😓"),
+    ("sweat_drops", "💦"),
+    ("sweat_smile", "😅"),
+    ("syringe", "💉"),
+    ("taco", "🌮"),
+    ("tada", "🎉"),
+    ("taxi", "🚕"),
+    ("tea", "🍵"),
+    ("telephone_receiver", "📞"),
+    ("telescope", "🔭"),
+    ("tennis", "🎾"),
+    ("tent", "⛺"),
+    ("test_tube", "🧪"),
+    ("thinking", "🤔"),
+    ("thought_balloon", "💭"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tiger", "🐯"),
+    ("tired_face", "😫"),
+    ("tm", "™️"),
+    ("toolbox", "🧰"),
+    ("tophat", "🎩"),
+    ("train", "🚆"),
+    ("triangular_flag_on_post", "🚩"),
+    ("triumph", "😤"),
+    ("trophy", "🏆"),
+    ("tropical_fish", "🐠"),
+    ("tulip", "🌷"),
+    ("turtle", "🐢"),
+    ("tv", "📺"),
+    ("two_hearts", "💕"),
+    ("umbrella", "☂️"),
+    ("unamused", "😒"),
+    ("unicorn", "🦄"),
+    ("unlock", "🔓"),
+    ("up", "🆙"),
+    ("upside_down_face", "🙃"),
+    ("v", "✌️"),
+    ("video_camera", "📹"),
+    ("video_game", "🎮"),
+    ("warning", "⚠️"),
+    ("wastebasket", "🗑️"),
+    ("watch", "⌚"),
+    ("watermelon", "🍉"),
+    ("wave", "👋"),
+    ("weary", "😩"),
+    ("whale", "🐳"),
+    ("white_check_mark", "✅"),
+    ("white_circle", "⚪"),
+    ("wine_glass", "🍷"),
+    ("wink", "😉"),
+    ("wolf", "🐺"),
+    ("worried", "😟"),
+    ("wrench", "🔧"),
+    ("writing_hand", "✍️"),
+    ("x", "❌"),
+    ("yawning_face", "🥱"),
+    ("yellow_circle", "🟡"),
+    ("yellow_heart", "💛"),
+    ("yum", "😋"),
+    ("zany_face", "🤪"),
+    ("zap", "⚡"),
+    ("zipper_mouth_face", "🤐"),
+    ("zzz", "💤"),
+];
+
+/// Turns `:rocket:`-style shortcodes in the text of a post into emoji. Code
+/// is left alone, and so are unknown names, so times like `10:30:00` are too.
+pub struct EmojiReplacer {
+    names: HashMap<String, String>,
+    /// Directory URL of the Twemoji images, when `style` asks for them.
+    images: Option<String>,
+    shortcode: Regex,
+}
+
+impl EmojiReplacer {
+    pub fn new(config: &EmojiConfig) -> EmojiReplacer {
+        let mut names: HashMap<String, String> = EMOJI
+            .iter()
+            .map(|(name, emoji)| (name.to_string(), emoji.to_string()))
+            .collect();
+        names.extend(config.custom.clone());
+        EmojiReplacer {
+            names,
+            images: (config.style == "twemoji")
+                .then(|| format!("{}/", config.twemoji_url.trim_end_matches('/'))),
+            shortcode: Regex::new(r":([a-z0-9_+-]+):").unwrap(),
+        }
+    }
+
+    pub fn replace<'a>(&self, events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+        let mut output = Vec::new();
+        let mut in_code_block = false;
+        // Text comes in pieces, split wherever markdown could have started
+        // emphasis, like the `_` of `:white_check_mark:`.
+        let mut text = String::new();
+        for event in events {
+            match event {
+                Event::Text(piece) if !in_code_block => {
+                    text.push_str(&piece);
+                    continue;
+                }
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                _ => {}
+            }
+            self.flush(&mut text, &mut output);
+            output.push(event);
+        }
+        self.flush(&mut text, &mut output);
+        output
+    }
+
+    fn flush<'a>(&self, text: &mut String, output: &mut Vec<Event<'a>>) {
+        if text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(text);
+        let Some(images) = &self.images else {
+            let replaced = self.shortcode.replace_all(&text, |captures: &Captures| {
+                match self.names.get(&captures[1]) {
+                    Some(emoji) => emoji.clone(),
+                    None => captures[0].to_string(),
+                }
+            });
+            output.push(Event::Text(CowStr::from(replaced.into_owned())));
+            return;
+        };
+        let mut start = 0;
+        for captures in self.shortcode.captures_iter(&text) {
+            let Some(emoji) = self.names.get(&captures[1]) else {
+                continue;
+            };
+            let shortcode = captures.get(0).unwrap();
+            if shortcode.start() > start {
+                output.push(Event::Text(
+                    text[start..shortcode.start()].to_string().into(),
+                ));
+            }
+            output.push(Event::Html(twemoji_image(images, emoji).into()));
+            start = shortcode.end();
+        }
+        if start < text.len() {
+            output.push(Event::Text(text[start..].to_string().into()));
+        }
+    }
+}
+
+/// An `<img>` of Twemoji's picture of `emoji`, sized to the text around it.
+/// The inline style wins over `[classes]` meant for the images of posts.
+/// Its files are named after the code points, without the variation selector
+/// unless they join several emoji.
+fn twemoji_image(images: &str, emoji: &str) -> String {
+    let joined = emoji.contains('\u{200d}');
+    let code_points: Vec<String> = emoji
+        .chars()
+        .filter(|&c| joined || c != '\u{fe0f}')
+        .map(|c| format!("{:x}", c as u32))
+        .collect();
+    format!(
+        "<img class=\"emoji\" src=\"{}{}.svg\" alt=\"{}\" style=\"height: 1em; width: 1em; margin: 0 0.05em 0 0.1em; vertical-align: -0.1em\">",
+        images,
+        code_points.join("-"),
+        emoji
+    )
+}
@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Anything that can go wrong turning one source file into one output file.
+/// Every variant names the file involved.
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("Failed to read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("Failed to write {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+
+    #[error("Invalid config in {}: {source}", path.display())]
+    Config {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    /// `line` and `column` point into the post itself, not the extracted YAML.
+    #[error("Invalid front matter in {}:{line}:{column}: {message}", path.display())]
+    FrontMatter {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    /// Two sources rendered to the same page, as far as a case-insensitive
+    /// file system can tell.
+    #[error("{} and {} would both be published at {url}", other.display(), path.display())]
+    DuplicateOutput {
+        path: PathBuf,
+        other: PathBuf,
+        url: String,
+    },
+
+    /// A permalink or `slug:` climbing out of the output directory with `..`.
+    #[error("{} would be published at {url}, outside the output directory", path.display())]
+    OutsideOutput { path: PathBuf, url: String },
+
+    #[error("Failed to process image {}: {source}", path.display())]
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+
+    #[error("Image {src} in {} has no alt text", path.display())]
+    MissingAlt { path: PathBuf, src: String },
+
+    #[error("Broken link {href} in {}:{line}: {reason}", path.display())]
+    BrokenLink {
+        path: PathBuf,
+        line: usize,
+        href: String,
+        reason: String,
+    },
+
+    #[error("Failed to include {include} in {}: {reason}", path.display())]
+    Include {
+        path: PathBuf,
+        include: String,
+        reason: String,
+    },
+
+    /// An org-mode, reStructuredText or AsciiDoc source that couldn't be
+    /// turned into markdown, like when `asciidoctor` fails.
+    #[error("Failed to convert {}: {reason}", path.display())]
+    Convert { path: PathBuf, reason: String },
+
+    #[error("Failed to render markdown in {}: {source}", path.display())]
+    Markdown {
+        path: PathBuf,
+        source: Box<dyn Error + Send + Sync>,
+    },
+
+    /// Tera reports the actual cause (e.g. a missing variable) as the source of
+    /// a generic "failed to render" error, so the whole chain is shown.
+    #[error("Failed to render {template} into {}: {}", path.display(), error_chain(source))]
+    Template {
+        template: String,
+        path: PathBuf,
+        source: tera::Error,
+    },
+}
+
+/// Formats an error followed by each of its sources.
+fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,667 @@
+use crate::formats::find_date;
+use crate::html::{self, decode_entities};
+use crate::links::percent_decode;
+use quick_xml::events::Event;
+use quick_xml::{Reader, XmlVersion};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Elements WordPress doesn't put in a paragraph when it displays a post of
+/// the classic editor.
+const WORDPRESS_BLOCKS: [&str; 22] = [
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "blockquote",
+    "pre",
+    "table",
+    "figure",
+    "hr",
+    "iframe",
+    "form",
+    "address",
+    "section",
+    "script",
+];
+
+/// WordPress and Jetpack shortcodes ebolg has nothing for, left in posts with
+/// a warning.
+const WORDPRESS_SHORTCODES: [&str; 12] = [
+    "gallery",
+    "audio",
+    "video",
+    "playlist",
+    "contact-form",
+    "contact-form-7",
+    "wpvideo",
+    "youtube",
+    "tweet",
+    "instagram",
+    "gist",
+    "latex",
+];
+
+/// The placeholder Ghost exports write in place of the site's URL.
+pub const GHOST_URL: &str = "__GHOST_URL__";
+
+/// The posts and pages of a WordPress or Ghost export.
+pub struct Export {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    /// The author of every post, when all of them have the same.
+    pub author: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+/// A post or page of an export, with its front matter in ebolg's keys.
+pub struct Entry {
+    pub slug: String,
+    pub page: bool,
+    /// The path the platform published it at, like `/2024/01/02/hello/`,
+    /// when the export records it.
+    pub url_path: Option<String>,
+    pub metadata: Map<String, Value>,
+    pub html: String,
+}
+
+/// `slug` as the name of the file a post is imported as. One that could
+/// name a path, like `../pwned` or `2024/hello`, is slugified, and an empty
+/// one is made from `title`.
+fn file_slug(slug: &str, title: &str) -> String {
+    let is_path = slug.contains(['/', '\\']) || slug == "." || slug == "..";
+    let slug = match is_path {
+        true => slug::slugify(slug),
+        false => slug.to_string(),
+    };
+    match slug.is_empty() {
+        true => slug::slugify(title),
+        false => slug,
+    }
+}
+
+/// An element of an XML document, with its text and the elements in it.
+#[derive(Default)]
+struct XmlElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The trimmed text of the first child named `name`, or `""`.
+    fn text_of(&self, name: &str) -> &str {
+        self.child(name).map_or("", |child| child.text.trim())
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parses an XML document into the element at its root.
+fn parse_xml(content: &str) -> Result<XmlElement, String> {
+    let mut reader = Reader::from_str(content);
+    let mut open = vec![XmlElement::default()];
+    let error = |reader: &Reader<&[u8]>, e: &dyn std::fmt::Display| {
+        format!("invalid XML at byte {}: {}", reader.buffer_position(), e)
+    };
+    loop {
+        let event = reader.read_event().map_err(|e| error(&reader, &e))?;
+        match event {
+            Event::Start(ref start) | Event::Empty(ref start) => {
+                let empty = matches!(event, Event::Empty(_));
+                let mut element = XmlElement {
+                    name: start.name().as_ref().to_string(),
+                    ..XmlElement::default()
+                };
+                for attribute in start.attributes().flatten() {
+                    let value = attribute
+                        .normalized_value(XmlVersion::Implicit1_0)
+                        .map_err(|e| error(&reader, &e))?;
+                    element
+                        .attributes
+                        .push((attribute.key.as_ref().to_string(), value.into_owned()));
+                }
+                match empty {
+                    true => open.last_mut().unwrap().children.push(element),
+                    false => open.push(element),
+                }
+            }
+            Event::End(_) if open.len() > 1 => {
+                let element = open.pop().unwrap();
+                open.last_mut().unwrap().children.push(element);
+            }
+            Event::Text(text) => open.last_mut().unwrap().text.push_str(&text.into_inner()),
+            Event::CData(data) => open.last_mut().unwrap().text.push_str(&data.into_inner()),
+            Event::GeneralRef(reference) => {
+                let resolved = match reference.resolve_char_ref() {
+                    Ok(Some(c)) => c.to_string(),
+                    _ => decode_entities(&format!("&{};", reference.into_inner())),
+                };
+                open.last_mut().unwrap().text.push_str(&resolved);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let mut document = open.swap_remove(0);
+    document
+        .children
+        .pop()
+        .ok_or_else(|| "the file is empty".to_string())
+}
+
+/// Reads a WordPress export, the WXR file of Tools › Export: its posts and
+/// pages that aren't in the trash, with their categories, tags, excerpts and
+/// featured images. The classic editor's posts are put in paragraphs the way
+/// WordPress displays them, and the shortcodes that have an HTML equivalent,
+/// `[caption]`, `[embed]` and `[code]`, are replaced by it.
+pub fn wordpress(content: &str) -> Result<Export, String> {
+    if !content.trim_start().starts_with('<') {
+        return Err("not a WordPress export, it isn't XML".to_string());
+    }
+    let rss = parse_xml(content)?;
+    let channel = rss
+        .child("channel")
+        .filter(|_| rss.name == "rss")
+        .ok_or("not a WordPress export, it has no <channel>")?;
+    let url = [channel.text_of("wp:base_blog_url"), channel.text_of("link")]
+        .into_iter()
+        .find(|url| !url.is_empty())
+        .map(|url| url.trim_end_matches('/').to_string());
+    let authors: BTreeMap<&str, &str> = channel
+        .children("wp:author")
+        .map(|author| {
+            let login = author.text_of("wp:author_login");
+            let name = author.text_of("wp:author_display_name");
+            (login, if name.is_empty() { login } else { name })
+        })
+        .collect();
+    let items: Vec<&XmlElement> = channel.children("item").collect();
+    let attachments: BTreeMap<&str, &str> = items
+        .iter()
+        .filter(|item| item.text_of("wp:post_type") == "attachment")
+        .map(|item| {
+            (
+                item.text_of("wp:post_id"),
+                item.text_of("wp:attachment_url"),
+            )
+        })
+        .collect();
+    let shortcodes = Shortcodes::new();
+
+    let mut entries = Vec::new();
+    let mut creators = BTreeSet::new();
+    for item in items {
+        let page = match item.text_of("wp:post_type") {
+            "post" => false,
+            "page" => true,
+            _ => continue,
+        };
+        let status = item.text_of("wp:status");
+        if matches!(status, "trash" | "auto-draft" | "inherit") {
+            continue;
+        }
+        let title = decode_entities(item.text_of("title"));
+        let slug = file_slug(&percent_decode(item.text_of("wp:post_name")), &title);
+
+        let mut metadata = Map::new();
+        if !title.is_empty() {
+            metadata.insert("title".to_string(), Value::from(title.as_str()));
+        }
+        // Drafts that were never published are dated 0000-00-00.
+        let date = find_date(item.text_of("wp:post_date")).filter(|date| !date.starts_with("0000"));
+        if let Some(date) = date {
+            metadata.insert("date".to_string(), Value::from(date));
+        }
+        if let Some(updated) = find_date(item.text_of("wp:post_modified"))
+            .filter(|updated| date.is_some_and(|date| date < *updated))
+        {
+            metadata.insert("updated".to_string(), Value::from(updated));
+        }
+        let excerpt = html::to_text(item.text_of("excerpt:encoded"));
+        if !excerpt.is_empty() {
+            metadata.insert("description".to_string(), Value::from(excerpt));
+        }
+        // Scheduled posts keep their date in the future, which leaves them
+        // out of builds until then.
+        if !matches!(status, "publish" | "future") {
+            metadata.insert("draft".to_string(), Value::Bool(true));
+        }
+        let creator = item.text_of("dc:creator");
+        if !creator.is_empty() {
+            let name = authors.get(creator).copied().unwrap_or(creator);
+            creators.insert(name.to_string());
+            metadata.insert("author".to_string(), Value::from(name));
+        }
+        for (domain, key) in [("post_tag", "tags"), ("category", "categories")] {
+            let terms: Vec<Value> = item
+                .children("category")
+                .filter(|category| category.attribute("domain") == Some(domain))
+                .filter(|category| category.attribute("nicename") != Some("uncategorized"))
+                .map(|category| Value::from(decode_entities(category.text.trim())))
+                .collect();
+            if !terms.is_empty() {
+                metadata.insert(key.to_string(), Value::Array(terms));
+            }
+        }
+        let thumbnail = item
+            .children("wp:postmeta")
+            .find(|meta| meta.text_of("wp:meta_key") == "_thumbnail_id")
+            .and_then(|meta| attachments.get(meta.text_of("wp:meta_value")));
+        if let Some(image) = thumbnail {
+            metadata.insert("image".to_string(), Value::from(*image));
+        }
+
+        // Links of unpublished posts, and of sites without permalinks, are
+        // `?p=123`.
+        let url_path = url.as_deref().and_then(|url| {
+            let path = item.text_of("link").strip_prefix(url)?;
+            let path = percent_decode(path);
+            // Pages are imported at their path, which must stay under pages/.
+            let is_safe = !path
+                .split(['/', '\\'])
+                .any(|segment| segment == "." || segment == "..");
+            (path.starts_with('/') && !path.contains('?') && is_safe).then_some(path)
+        });
+        let mut html = shortcodes.replace(&slug, item.text_of("content:encoded"));
+        // Posts of the block editor are already in paragraphs, marked by
+        // `<!-- wp:paragraph -->` comments.
+        if !html.contains("<!-- wp:") {
+            html = autop(&html);
+        }
+        entries.push(Entry {
+            slug,
+            page,
+            url_path,
+            metadata,
+            html,
+        });
+    }
+
+    // A single author is the site's, rather than each post's.
+    let author = match creators.len() {
+        1 => creators.pop_first(),
+        _ => None,
+    };
+    if author.is_some() {
+        for entry in &mut entries {
+            entry.metadata.remove("author");
+        }
+    }
+    let title = Some(decode_entities(channel.text_of("title"))).filter(|title| !title.is_empty());
+    Ok(Export {
+        title,
+        url,
+        author,
+        entries,
+    })
+}
+
+/// The WordPress shortcodes rewritten as HTML.
+struct Shortcodes {
+    /// `[caption id="..." align="..."]<img ...> The caption[/caption]`.
+    caption: Regex,
+    /// `[embed]https://...[/embed]`.
+    embed: Regex,
+    /// `[code lang="rust"]...[/code]` and `[sourcecode language="rust"]`.
+    code: Regex,
+    /// The language argument of a code shortcode.
+    language: Regex,
+    /// Any shortcode, to warn about those left in posts.
+    any: Regex,
+}
+
+impl Shortcodes {
+    fn new() -> Self {
+        Shortcodes {
+            caption: Regex::new(r"(?s)\[caption[^\]]*\](.*?)\[/caption\]").unwrap(),
+            embed: Regex::new(r"\[embed[^\]]*\]\s*(\S+?)\s*\[/embed\]").unwrap(),
+            code: Regex::new(
+                r"(?s)\[(code|sourcecode)([^\]]*)\]\n?(.*?)\n?\[/(?:code|sourcecode)\]",
+            )
+            .unwrap(),
+            language: Regex::new(r#"(?:lang|language)=["']?([\w+#-]+)"#).unwrap(),
+            any: Regex::new(r"\[([a-z][\w-]*)[\s\]]").unwrap(),
+        }
+    }
+
+    fn replace(&self, slug: &str, html: &str) -> String {
+        let html = self.caption.replace_all(html, |captures: &Captures| {
+            // The caption is the text after the image, or the link around it.
+            let content = captures[1].trim();
+            let (image, caption) = match content.rfind('>') {
+                Some(end) => content.split_at(end + 1),
+                None => ("", content),
+            };
+            format!(
+                "<figure>{}<figcaption>{}</figcaption></figure>",
+                image.trim(),
+                caption.trim()
+            )
+        });
+        let html = self.embed.replace_all(&html, |captures: &Captures| {
+            format!("<p><a href=\"{0}\">{0}</a></p>", &captures[1])
+        });
+        let html = self.code.replace_all(&html, |captures: &Captures| {
+            let class = self
+                .language
+                .captures(&captures[2])
+                .map(|language| format!(" class=\"language-{}\"", &language[1]))
+                .unwrap_or_default();
+            // The code of a shortcode is written as it is, not escaped.
+            let code = captures[3]
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            format!("<pre><code{}>{}</code></pre>", class, code)
+        });
+        let left: BTreeSet<&str> = self
+            .any
+            .captures_iter(&html)
+            .map(|captures| captures.get(1).unwrap().as_str())
+            .filter(|name| WORDPRESS_SHORTCODES.contains(name))
+            .collect();
+        for name in left {
+            eprintln!(
+                "Warning: {}: left the [{}] shortcode as it is, ebolg has nothing for it",
+                slug, name
+            );
+        }
+        html.into_owned()
+    }
+}
+
+/// A post of WordPress's classic editor in paragraphs, as WordPress displays
+/// it: text between blank lines that isn't a block is a paragraph, and a line
+/// break in one a `<br>`. Preformatted text is left as it is.
+fn autop(html: &str) -> String {
+    let html = html.replace("\r\n", "\n");
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    while !rest.is_empty() {
+        let (text, pre) = match rest.find("<pre") {
+            Some(start) => {
+                let end = rest[start..]
+                    .find("</pre>")
+                    .map_or(rest.len(), |end| start + end + 6);
+                (&rest[..start], &rest[start..end])
+            }
+            None => (rest, ""),
+        };
+        let mut chunks = text.split("\n\n").map(str::trim);
+        while let Some(chunk) = chunks.next() {
+            if chunk.is_empty() {
+                continue;
+            }
+            let name: String = chunk
+                .strip_prefix('<')
+                .unwrap_or_default()
+                .trim_start_matches('/')
+                .chars()
+                .take_while(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_ascii_lowercase();
+            if !WORDPRESS_BLOCKS.contains(&name.as_str()) {
+                output.push_str(&format!("<p>{}</p>\n\n", chunk.replace('\n', "<br>\n")));
+                continue;
+            }
+            // A block with blank lines in it goes on to its end tag.
+            let mut block = chunk.to_string();
+            let (start, end) = (format!("<{}", name), format!("</{}>", name));
+            while block.matches(&start).count() > block.matches(&end).count() {
+                match chunks.next() {
+                    Some(more) => block.push_str(&format!("\n\n{}", more)),
+                    None => break,
+                }
+            }
+            output.push_str(&block);
+            output.push_str("\n\n");
+        }
+        if !pre.is_empty() {
+            output.push_str(pre);
+            output.push_str("\n\n");
+        }
+        rest = &rest[text.len() + pre.len()..];
+    }
+    output
+}
+
+/// A Ghost export, as Settings › Labs › Export writes it, or the `db` entry
+/// of one.
+#[derive(Deserialize)]
+struct GhostExport {
+    #[serde(default)]
+    db: Vec<GhostDatabase>,
+    data: Option<GhostData>,
+}
+
+#[derive(Deserialize)]
+struct GhostDatabase {
+    data: GhostData,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct GhostData {
+    posts: Vec<GhostPost>,
+    tags: Vec<GhostTag>,
+    posts_tags: Vec<GhostPostTag>,
+    users: Vec<GhostUser>,
+    posts_authors: Vec<GhostPostAuthor>,
+    settings: Vec<GhostSetting>,
+}
+
+/// A post or page. Ids are strings, or numbers in the exports of Ghost 0.x.
+#[derive(Deserialize)]
+struct GhostPost {
+    id: Value,
+    #[serde(default)]
+    title: String,
+    slug: String,
+    html: Option<String>,
+    feature_image: Option<String>,
+    /// `post` or `page`, where Ghost 1 and earlier set `page`.
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    page: Value,
+    #[serde(default)]
+    status: String,
+    published_at: Option<String>,
+    updated_at: Option<String>,
+    custom_excerpt: Option<String>,
+    /// The author of exports from before posts had several.
+    author_id: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct GhostTag {
+    id: Value,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GhostPostTag {
+    post_id: Value,
+    tag_id: Value,
+}
+
+#[derive(Deserialize)]
+struct GhostUser {
+    id: Value,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GhostPostAuthor {
+    post_id: Value,
+    author_id: Value,
+}
+
+#[derive(Deserialize)]
+struct GhostSetting {
+    key: String,
+    value: Option<Value>,
+}
+
+/// Reads a Ghost export: its posts and pages with their tags, authors,
+/// excerpts and feature images. Internal tags, named `#like-this`, are left
+/// out.
+pub fn ghost(content: &str) -> Result<Export, String> {
+    let export: GhostExport =
+        serde_json::from_str(content).map_err(|e| format!("not a Ghost export: {}", e))?;
+    let data = match (export.db.into_iter().next(), export.data) {
+        (Some(database), _) => database.data,
+        (None, Some(data)) => data,
+        (None, None) => return Err("not a Ghost export, it has no data".to_string()),
+    };
+    let tags: BTreeMap<String, &str> = data
+        .tags
+        .iter()
+        .filter(|tag| !tag.name.starts_with('#'))
+        .map(|tag| (tag.id.to_string(), tag.name.as_str()))
+        .collect();
+    let users: BTreeMap<String, &str> = data
+        .users
+        .iter()
+        .map(|user| (user.id.to_string(), user.name.as_str()))
+        .collect();
+
+    let mut entries = Vec::new();
+    let mut creators = BTreeSet::new();
+    for post in &data.posts {
+        let slug = file_slug(&post.slug, &post.title);
+        let page = match post.kind.as_deref() {
+            Some(kind) => kind == "page",
+            None => post.page == true || post.page == 1,
+        };
+        let mut metadata = Map::new();
+        if !post.title.is_empty() {
+            metadata.insert("title".to_string(), Value::from(post.title.as_str()));
+        }
+        let date = post.published_at.as_deref().and_then(find_date);
+        if let Some(date) = date {
+            metadata.insert("date".to_string(), Value::from(date));
+        }
+        if let Some(updated) = post
+            .updated_at
+            .as_deref()
+            .and_then(find_date)
+            .filter(|updated| date.is_some_and(|date| date < *updated))
+        {
+            metadata.insert("updated".to_string(), Value::from(updated));
+        }
+        if let Some(excerpt) = post.custom_excerpt.as_deref().map(str::trim) {
+            if !excerpt.is_empty() {
+                metadata.insert("description".to_string(), Value::from(excerpt));
+            }
+        }
+        if !matches!(post.status.as_str(), "published" | "scheduled") {
+            metadata.insert("draft".to_string(), Value::Bool(true));
+        }
+        let mut authors: Vec<&str> = data
+            .posts_authors
+            .iter()
+            .filter(|author| author.post_id == post.id)
+            .filter_map(|author| users.get(&author.author_id.to_string()).copied())
+            .collect();
+        if authors.is_empty() {
+            authors.extend(
+                post.author_id
+                    .as_ref()
+                    .and_then(|author| users.get(&author.to_string())),
+            );
+        }
+        creators.extend(authors.iter().map(|author| author.to_string()));
+        match authors.as_slice() {
+            [] => {}
+            [author] => {
+                metadata.insert("author".to_string(), Value::from(*author));
+            }
+            authors => {
+                metadata.insert("authors".to_string(), Value::from(authors.to_vec()));
+            }
+        }
+        let post_tags: Vec<Value> = data
+            .posts_tags
+            .iter()
+            .filter(|tag| tag.post_id == post.id)
+            .filter_map(|tag| tags.get(&tag.tag_id.to_string()))
+            .map(|name| Value::from(*name))
+            .collect();
+        if !post_tags.is_empty() {
+            metadata.insert("tags".to_string(), Value::Array(post_tags));
+        }
+        if let Some(image) = post
+            .feature_image
+            .as_deref()
+            .filter(|image| !image.is_empty())
+        {
+            metadata.insert("image".to_string(), Value::from(image));
+        }
+
+        let html = match &post.html {
+            Some(html) => html.clone(),
+            None => {
+                eprintln!(
+                    "Warning: {}: has no HTML, export it again with Ghost 1 or later",
+                    slug
+                );
+                String::new()
+            }
+        };
+        entries.push(Entry {
+            url_path: Some(format!("/{}/", slug)),
+            slug,
+            page,
+            metadata,
+            html,
+        });
+    }
+
+    let author = match creators.len() {
+        1 => creators.pop_first(),
+        _ => None,
+    };
+    if author.is_some() {
+        for entry in &mut entries {
+            entry.metadata.remove("author");
+        }
+    }
+    let title = data
+        .settings
+        .iter()
+        .find(|setting| setting.key == "title")
+        .and_then(|setting| setting.value.as_ref()?.as_str())
+        .map(str::to_string);
+    Ok(Export {
+        title,
+        url: None,
+        author,
+        entries,
+    })
+}
@@ -0,0 +1,206 @@
+use crate::config::{Config, TAGS};
+use crate::error::BuildError;
+use crate::output::write_atomic;
+use crate::post::Post;
+use crate::templates::render_page;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+/// File name of the Atom feed, written at the root of the output directory.
+pub const FEED_FILE: &str = "feed.xml";
+
+/// File name of the JSON Feed, written next to the Atom feed.
+pub const JSON_FEED_FILE: &str = "feed.json";
+
+/// Version URL every JSON Feed opens with.
+const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+#[derive(Debug, Serialize)]
+struct FeedEntry<'a> {
+    title: &'a str,
+    url: String,
+    updated: String,
+    published: String,
+    summary: &'a str,
+    content: &'a str,
+    authors: Vec<&'a str>,
+}
+
+/// A JSON Feed 1.1, see <https://www.jsonfeed.org/version/1.1/>.
+#[derive(Debug, Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'a str,
+    home_page_url: String,
+    feed_url: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonFeedAuthor>,
+    items: Vec<JsonFeedItem<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonFeedItem<'a> {
+    id: &'a str,
+    url: &'a str,
+    title: &'a str,
+    content_html: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    summary: &'a str,
+    date_published: &'a str,
+    date_modified: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonFeedAuthor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<&'a str>,
+    language: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonFeedAuthor {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avatar: Option<String>,
+}
+
+/// Atom timestamps must carry a time, so dates are pinned to midnight UTC.
+fn atom_timestamp(date: NaiveDate) -> String {
+    format!("{}T00:00:00Z", date.format("%Y-%m-%d"))
+}
+
+/// Writes an Atom feed and a JSON Feed titled `title` of `posts` (newest
+/// first) into `dir`, which starts and ends with `/`. Feed and entry ids must
+/// be absolute, so nothing is written until `base_url` is configured. Atom
+/// feeds must be dated, so one without posts is updated `today`.
+pub fn render_feed(
+    tera: &Tera,
+    config: &Config,
+    output_dir: &Path,
+    dir: &str,
+    title: &str,
+    posts: &[Post],
+    today: NaiveDate,
+) -> Result<(), BuildError> {
+    if config.base_url.is_empty() {
+        println!(
+            "Skipping {} and {}: set base_url in ebolg.toml to generate them",
+            FEED_FILE, JSON_FEED_FILE
+        );
+        return Ok(());
+    }
+
+    let posts: Vec<&Post> = posts.iter().collect();
+    let written = write_feeds(tera, config, output_dir, dir, title, &posts, today)?;
+    println!("Feed generated: {:?} ({} entries)", written[0], posts.len());
+    Ok(())
+}
+
+/// Writes the feeds `render_feed` describes, once `base_url` is known to be
+/// set, and returns their paths, the Atom feed's first.
+pub fn write_feeds(
+    tera: &Tera,
+    config: &Config,
+    output_dir: &Path,
+    dir: &str,
+    title: &str,
+    posts: &[&Post],
+    today: NaiveDate,
+) -> Result<Vec<PathBuf>, BuildError> {
+    let entries: Vec<FeedEntry> = posts
+        .iter()
+        .map(|post| FeedEntry {
+            title: &post.title,
+            url: config.absolute_url(&post.url),
+            updated: atom_timestamp(post.updated),
+            published: atom_timestamp(post.date),
+            summary: &post.summary,
+            content: &post.html,
+            authors: post
+                .authors
+                .iter()
+                .map(|author| author.name.as_str())
+                .collect(),
+        })
+        .collect();
+    let updated = atom_timestamp(posts.iter().map(|post| post.updated).max().unwrap_or(today));
+
+    let mut context = Context::new();
+    context.insert("config", config);
+    context.insert("title", title);
+    context.insert("site_url", &config.absolute_url(dir));
+    context.insert(
+        "feed_url",
+        &config.absolute_url(&format!("{}{}", dir, FEED_FILE)),
+    );
+    context.insert("updated", &updated);
+    context.insert("entries", &entries);
+
+    let feed_path = output_dir.join(dir.trim_start_matches('/')).join(FEED_FILE);
+    render_page(tera, config, FEED_FILE, &context, &feed_path)?;
+
+    let json_feed = JsonFeed {
+        version: JSON_FEED_VERSION,
+        title,
+        home_page_url: config.absolute_url(dir),
+        feed_url: config.absolute_url(&format!("{}{}", dir, JSON_FEED_FILE)),
+        authors: config
+            .author
+            .iter()
+            .map(|name| JsonFeedAuthor {
+                name: name.clone(),
+                url: None,
+                avatar: None,
+            })
+            .collect(),
+        items: posts
+            .iter()
+            .zip(&entries)
+            .map(|(post, entry)| JsonFeedItem {
+                id: &entry.url,
+                url: &entry.url,
+                title: entry.title,
+                content_html: entry.content,
+                summary: entry.summary,
+                date_published: &entry.published,
+                date_modified: &entry.updated,
+                authors: post
+                    .authors
+                    .iter()
+                    .map(|author| JsonFeedAuthor {
+                        name: author.name.clone(),
+                        url: Some(config.absolute_url(author.url.trim_end_matches("index.html"))),
+                        avatar: Some(&author.avatar)
+                            .filter(|avatar| !avatar.is_empty())
+                            .map(|avatar| absolute_link(config, avatar)),
+                    })
+                    .collect(),
+                tags: post.terms(TAGS),
+                language: &post.lang,
+            })
+            .collect(),
+    };
+    let json_feed_path = output_dir
+        .join(dir.trim_start_matches('/'))
+        .join(JSON_FEED_FILE);
+    // Serializing borrowed strings and plain values can't fail.
+    let json = serde_json::to_string(&json_feed).unwrap();
+    write_atomic(&json_feed_path, json).map_err(|source| BuildError::Write {
+        path: json_feed_path.clone(),
+        source,
+    })?;
+    crate::verbose!("JSON feed generated: {:?}", json_feed_path);
+
+    Ok(vec![feed_path, json_feed_path])
+}
+
+/// `link` as an absolute URL: site-relative paths are resolved against
+/// `base_url`, and URLs with a scheme are kept.
+fn absolute_link(config: &Config, link: &str) -> String {
+    match link.starts_with('/') {
+        true => config.absolute_url(link),
+        false => link.to_string(),
+    }
+}
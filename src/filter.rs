@@ -0,0 +1,86 @@
+use crate::config::Config;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::error::Error;
+use std::path::{Component, Path, PathBuf};
+
+/// Which files under the source directory are built, and how, by the glob
+/// patterns of the config. Patterns are matched against paths relative to
+/// the source directory.
+#[derive(Debug)]
+pub struct SourceFilter {
+    root: PathBuf,
+    /// Files to build, or every file when `None`.
+    include: Option<GlobSet>,
+    ignore: GlobSet,
+    /// Files copied as assets whatever their extension.
+    copy: GlobSet,
+    include_hidden: bool,
+}
+
+impl SourceFilter {
+    pub fn new(source_dir: &Path, config: &Config) -> Result<SourceFilter, Box<dyn Error>> {
+        Ok(SourceFilter {
+            root: source_dir.to_path_buf(),
+            include: match config.include.is_empty() {
+                true => None,
+                false => Some(glob_set(&config.include, "include")?),
+            },
+            ignore: glob_set(&config.ignore, "ignore")?,
+            copy: glob_set(&config.assets.copy, "assets.copy")?,
+            include_hidden: config.include_hidden,
+        })
+    }
+
+    /// Whether the file or directory at `path` is left out: hidden files and
+    /// editor leftovers, unless `include_hidden` is set, and those matching
+    /// `ignore`. Paths outside the source directory never are.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let Some(relative) = self.relative(path) else {
+            return false;
+        };
+        if !self.include_hidden
+            && relative.components().any(|component| match component {
+                Component::Normal(name) => is_hidden(&name.to_string_lossy()),
+                _ => false,
+            })
+        {
+            return true;
+        }
+        self.ignore.is_match(relative)
+    }
+
+    /// Whether the file at `path` matches `include`, when it is set.
+    pub fn is_included(&self, path: &Path) -> bool {
+        match (&self.include, self.relative(path)) {
+            (Some(include), Some(relative)) => include.is_match(relative),
+            _ => true,
+        }
+    }
+
+    /// Whether the file at `path` matches `assets.copy`, so it is copied
+    /// rather than rendered or skipped.
+    pub fn is_copied(&self, path: &Path) -> bool {
+        self.relative(path)
+            .is_some_and(|relative| self.copy.is_match(relative))
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.root).ok()
+    }
+}
+
+fn glob_set(patterns: &[String], key: &str) -> Result<GlobSet, Box<dyn Error>> {
+    let mut set = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| format!("Invalid pattern {:?} in {}: {}", pattern, key, e))?;
+        set.add(glob);
+    }
+    Ok(set.build()?)
+}
+
+/// Dotfiles, which also covers vim's `.post.md.swp` and emacs' `.#post.md`,
+/// and the `#post.md#` and `post.md~` files editors leave behind.
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with('#') || name.ends_with('~')
+}
@@ -0,0 +1,101 @@
+use crate::cache::hash;
+use crate::links::{normalize, percent_decode, site_path};
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Extensions of the assets `assets.fingerprint` publishes under a name
+/// holding their hash.
+pub const FINGERPRINT_EXTENSIONS: [&str; 9] = [
+    "css", "js", "png", "jpg", "jpeg", "gif", "svg", "webp", "avif",
+];
+
+/// Site paths of fingerprinted assets, like `/style.css`, mapped to the hash
+/// their copies are named after.
+pub type Fingerprints = BTreeMap<String, String>;
+
+/// Short hash of an asset's contents, for its file name.
+pub fn fingerprint(contents: &[u8]) -> String {
+    format!("{:08x}", hash(contents) >> 32)
+}
+
+/// `name` with `fingerprint` before its extension: `style.css` becomes
+/// `style.1a2b3c4d.css`.
+pub fn fingerprinted_name(name: &str, fingerprint: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}.{}.{}", stem, fingerprint, extension),
+        None => format!("{}.{}", name, fingerprint),
+    }
+}
+
+/// Points the `href`, `src` and `srcset` references of the page at
+/// `page_url` to the fingerprinted copies of the assets they name. Only the
+/// file name changes, so relative references stay relative.
+pub fn rewrite_asset_urls(
+    html: &str,
+    page_url: &str,
+    base_url: &str,
+    fingerprints: &Fingerprints,
+) -> String {
+    let page_dir = match page_url.rfind('/') {
+        Some(end) => &page_url[..=end],
+        None => "/",
+    };
+    let rewrite = |reference: &str| -> Option<String> {
+        let path = site_path(reference, base_url)?;
+        let decoded = percent_decode(path);
+        let target = match decoded.starts_with('/') {
+            true => decoded,
+            false => format!("{}{}", page_dir, decoded),
+        };
+        let target = normalize(Path::new(&target));
+        let fingerprint = fingerprints.get(target.to_str()?)?;
+        // `path` is a slice of `reference`, so its file name can be swapped
+        // in place, keeping any query or fragment after it.
+        let start = path.as_ptr() as usize - reference.as_ptr() as usize;
+        let name_start = start + path.rfind('/').map_or(0, |i| i + 1);
+        let name_end = start + path.len();
+        Some(format!(
+            "{}{}{}",
+            &reference[..name_start],
+            fingerprinted_name(&reference[name_start..name_end], fingerprint),
+            &reference[name_end..]
+        ))
+    };
+    let settings = RewriteStrSettings::new().append_element_content_handler(element!(
+        "[href], [src], [srcset]",
+        |el| {
+            for name in ["href", "src"] {
+                if let Some(url) = el.get_attribute(name).as_deref().and_then(rewrite) {
+                    el.set_attribute(name, &url)?;
+                }
+            }
+            if let Some(srcset) = el.get_attribute("srcset") {
+                let mut changed = false;
+                let candidates: Vec<String> = srcset
+                    .split(',')
+                    .map(|candidate| {
+                        let candidate = candidate.trim();
+                        let (url, descriptor) = candidate
+                            .split_once(char::is_whitespace)
+                            .unwrap_or((candidate, ""));
+                        let Some(url) = rewrite(url) else {
+                            return candidate.to_string();
+                        };
+                        changed = true;
+                        match descriptor.trim() {
+                            "" => url,
+                            descriptor => format!("{} {}", url, descriptor),
+                        }
+                    })
+                    .collect();
+                if changed {
+                    el.set_attribute("srcset", &candidates.join(", "))?;
+                }
+            }
+            Ok(())
+        }
+    ));
+    // The handler only fails to set attributes with valid names, which it can't.
+    rewrite_str(html, settings).unwrap_or_else(|_| html.to_string())
+}
@@ -0,0 +1,184 @@
+#[cfg(feature = "asciidoc")]
+use crate::asciidoc;
+use crate::error::BuildError;
+use crate::front_matter;
+use crate::{notebook, org, rst};
+use serde_json::{Map, Value};
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Formats posts and pages can be written in, by file extension. Files in the
+/// others are converted to markdown, front matter included, as they are read,
+/// and rendered like markdown from then on.
+#[cfg(not(feature = "asciidoc"))]
+pub const SOURCE_FORMATS: [&str; 4] = ["md", "org", "rst", "ipynb"];
+#[cfg(feature = "asciidoc")]
+pub const SOURCE_FORMATS: [&str; 5] = ["md", "org", "rst", "ipynb", "adoc"];
+
+/// Converts the body of a source file to markdown, returning the front matter
+/// it sets along with it, or why it can't be converted.
+type Converter = fn(&Path, &str) -> Result<(Map<String, Value>, String), String>;
+
+/// Front matter keys whose values are flags, written `t`, `true` or `yes`.
+const FLAG_KEYS: [&str; 5] = ["draft", "unlisted", "toc", "math", "comments"];
+
+/// Whether `path` is a source written in one of `formats`.
+pub fn is_source(path: &Path, formats: &[String]) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|extension| formats.iter().any(|format| *format == extension))
+}
+
+/// The markdown of the source file at `path`, converted from org-mode,
+/// reStructuredText, a Jupyter notebook or AsciiDoc by its extension. Front
+/// matter a file opens with is kept as it is; otherwise the converter's
+/// `#+TITLE:` lines, field list, notebook metadata or document header make up
+/// the front matter.
+pub fn to_markdown(path: &Path, content: String) -> Result<String, BuildError> {
+    let extension = path.extension().and_then(OsStr::to_str);
+    let converter: Converter = match extension {
+        Some("org") => |_, content| Ok(org::to_markdown(content)),
+        Some("rst") => |_, content| Ok(rst::to_markdown(content)),
+        Some("ipynb") => notebook::to_markdown,
+        #[cfg(feature = "asciidoc")]
+        Some("adoc") => asciidoc::to_markdown,
+        _ => return Ok(content),
+    };
+    let convert = |content| {
+        converter(path, content).map_err(|reason| BuildError::Convert {
+            path: path.to_path_buf(),
+            reason,
+        })
+    };
+    let front_matter = match extension {
+        // A notebook is a JSON object, not a file opening with JSON front matter.
+        Some("ipynb") => None,
+        _ => front_matter::split(&content).ok().flatten(),
+    };
+    if let Some(front_matter) = front_matter {
+        let body_start = front_matter.body.as_ptr() as usize - content.as_ptr() as usize;
+        let (_, body) = convert(front_matter.body)?;
+        return Ok(format!("{}\n{}", &content[..body_start], body));
+    }
+    let (metadata, body) = convert(&content)?;
+    Ok(match metadata.is_empty() {
+        true => body,
+        false => format!("{}\n\n{}", Value::Object(metadata), body),
+    })
+}
+
+/// Records the front matter `key` set by a keyword or field of a converted
+/// file, if it is one posts have. Tags are a list separated by commas,
+/// spaces or, as org writes them, colons, and authors one separated by commas
+/// or semicolons.
+pub fn set_metadata(metadata: &mut Map<String, Value>, key: &str, value: &str) {
+    let key = key.to_ascii_lowercase();
+    let key = match key.as_str() {
+        "filetags" | "keywords" => "tags",
+        key => key,
+    };
+    let value = value.trim();
+    let parsed = match key {
+        "tags" => Value::from(
+            value
+                .split([',', ' ', ':'])
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>(),
+        ),
+        "authors" => Value::from(
+            value
+                .split([',', ';'])
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>(),
+        ),
+        "date" | "updated" => match find_date(value) {
+            Some(date) => Value::from(date),
+            None => Value::from(value),
+        },
+        key if FLAG_KEYS.contains(&key) => Value::from(matches!(
+            value.to_ascii_lowercase().as_str(),
+            "t" | "true" | "yes"
+        )),
+        "title" | "slug" | "description" | "author" | "layout" => Value::from(value),
+        _ => return,
+    };
+    metadata.insert(key.to_string(), parsed);
+}
+
+/// The `YYYY-MM-DD` date in a timestamp like org's `<2024-01-02 Tue 10:00>`.
+pub fn find_date(value: &str) -> Option<&str> {
+    let bytes = value.as_bytes();
+    (0..value.len().saturating_sub(9)).find_map(|start| {
+        let date = &bytes[start..start + 10];
+        let digits = [0, 1, 2, 3, 5, 6, 8, 9]
+            .iter()
+            .all(|&i| date[i].is_ascii_digit());
+        (digits && date[4] == b'-' && date[7] == b'-').then(|| &value[start..start + 10])
+    })
+}
+
+/// `code` as a markdown code span, fenced by more backticks than it holds in
+/// a row.
+pub fn code_span(code: &str) -> String {
+    let ticks = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(ticks + 1);
+    match ticks {
+        0 => format!("{}{}{}", fence, code, fence),
+        _ => format!("{} {} {}", fence, code, fence),
+    }
+}
+
+/// Backslash-escapes the characters of `text` markdown would read as markup
+/// where the source format reads them as text.
+pub fn escape_markdown(text: &str, output: &mut String) {
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '`' | '[' | ']' | '<' | '$' | '~' | '&') {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+}
+
+/// A fenced code block of `lines`, which are written as they are.
+pub fn code_block(info: &str, lines: &[impl AsRef<str>], output: &mut String) {
+    let ticks = lines
+        .iter()
+        .flat_map(|line| line.as_ref().split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(ticks.max(2) + 1);
+    output.push_str(&format!("{}{}\n", fence, info));
+    for line in lines {
+        output.push_str(line.as_ref());
+        output.push('\n');
+    }
+    output.push_str(&fence);
+    output.push('\n');
+}
+
+/// The number of colons a `:::` container around `markdown` needs, so none
+/// of the containers inside it closes it.
+pub fn container_colons(markdown: &str) -> usize {
+    markdown
+        .lines()
+        .map(|line| line.trim_start().len() - line.trim_start().trim_start_matches(':').len())
+        .filter(|colons| *colons >= 3)
+        .max()
+        .map_or(3, |colons| colons + 1)
+}
+
+/// `lines` without the indentation they all share. Blank lines don't count.
+pub fn dedent<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect()
+}
@@ -0,0 +1,184 @@
+use serde::de::DeserializeOwned;
+
+/// How a post's front matter is written, detected from its opening delimiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Between `---` lines.
+    Yaml,
+    /// Between `+++` lines, as written for Hugo.
+    Toml,
+    /// A JSON object at the very start of the file.
+    Json,
+}
+
+/// The metadata block of a post, split from its markdown body.
+#[derive(Debug)]
+pub struct FrontMatter<'a> {
+    pub format: Format,
+    pub text: &'a str,
+    /// Line of the file `text` starts on, so errors can point into the file.
+    pub line: usize,
+    pub body: &'a str,
+}
+
+/// A front-matter parse error, positioned in the post file.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Splits `content` into front matter and body. Front matter must open on the
+/// first line, with `---` or `+++` on a line of its own or a JSON object, and
+/// `---`/`+++` blocks end at the next line holding just the same delimiter.
+/// Returns `None` when the post has no front matter.
+pub fn split(content: &str) -> Result<Option<FrontMatter<'_>>, ParseError> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    if content.starts_with('{') {
+        let mut objects =
+            serde_json::Deserializer::from_str(content).into_iter::<serde_json::Value>();
+        // A malformed object is still treated as JSON front matter, so its
+        // error is reported instead of the object being rendered as text.
+        let end = match objects.next() {
+            Some(Ok(_)) => objects.byte_offset(),
+            _ => content.len(),
+        };
+        return Ok(Some(FrontMatter {
+            format: Format::Json,
+            text: &content[..end],
+            line: 1,
+            body: content[end..].trim(),
+        }));
+    }
+
+    let mut lines = content.split_inclusive('\n');
+    let (format, delimiter) = match lines.next().map(str::trim_end) {
+        Some("---") => (Format::Yaml, "---"),
+        Some("+++") => (Format::Toml, "+++"),
+        _ => return Ok(None),
+    };
+
+    let text_start = content.find('\n').map_or(content.len(), |i| i + 1);
+    let mut offset = text_start;
+    for line in lines {
+        if line.trim_end() == delimiter {
+            return Ok(Some(FrontMatter {
+                format,
+                text: &content[text_start..offset],
+                line: 2,
+                body: content[offset + line.len()..].trim(),
+            }));
+        }
+        offset += line.len();
+    }
+
+    Err(ParseError {
+        line: 1,
+        column: 1,
+        message: format!("front matter opened with `{}` is never closed", delimiter),
+    })
+}
+
+impl FrontMatter<'_> {
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, ParseError> {
+        match self.format {
+            Format::Yaml => serde_yaml::from_str(self.text).map_err(|e| self.yaml_error(e)),
+            Format::Toml => self.parse_toml(),
+            Format::Json => serde_json::from_str(self.text).map_err(|e| {
+                self.error_at(
+                    e.line(),
+                    e.column(),
+                    strip_position(&e.to_string(), " at line "),
+                )
+            }),
+        }
+    }
+
+    /// serde_yaml counts from the start of the block and appends that position
+    /// to its message, so the message is trimmed and the line shifted.
+    fn yaml_error(&self, error: serde_yaml::Error) -> ParseError {
+        let message = error.to_string();
+        match error.location() {
+            Some(location) => self.error_at(
+                location.line(),
+                location.column(),
+                strip_position(&message, " at line "),
+            ),
+            None => self.error_at(1, 1, &message),
+        }
+    }
+
+    /// TOML dates are their own type rather than strings, so the table goes
+    /// through JSON with dates written out before it is deserialized.
+    fn parse_toml<T: DeserializeOwned>(&self) -> Result<T, ParseError> {
+        let table: toml::Table = self.text.parse().map_err(|e: toml::de::Error| {
+            let (line, column) = match e.span() {
+                Some(span) => line_and_column(self.text, span.start),
+                None => (1, 1),
+            };
+            self.error_at(line, column, e.message())
+        })?;
+
+        serde_json::from_value(toml_to_json(toml::Value::Table(table)))
+            .map_err(|e| self.error_at(1, 1, &e.to_string()))
+    }
+
+    /// Line of the file where the top-level `key` is set, or where the front
+    /// matter starts if it can't be found.
+    pub fn key_line(&self, key: &str) -> usize {
+        let quoted = format!("\"{}\"", key);
+        let set_with = |line: &str, separator: char| {
+            [key, quoted.as_str()].iter().any(|key| {
+                line.strip_prefix(key)
+                    .is_some_and(|rest| rest.trim_start().starts_with(separator))
+            })
+        };
+        let position = self.text.lines().position(|line| match self.format {
+            Format::Yaml => set_with(line, ':'),
+            Format::Toml => set_with(line, '='),
+            Format::Json => set_with(line.trim_start().trim_start_matches('{').trim_start(), ':'),
+        });
+        self.line + position.unwrap_or(0)
+    }
+
+    fn error_at(&self, line: usize, column: usize, message: &str) -> ParseError {
+        ParseError {
+            line: self.line + line.saturating_sub(1),
+            column,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn strip_position<'a>(message: &'a str, marker: &str) -> &'a str {
+    message
+        .rsplit_once(marker)
+        .map_or(message, |(message, _)| message)
+}
+
+/// 1-based line and column of the byte at `offset`.
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+pub fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(datetime) => serde_json::Value::String(datetime.to_string()),
+        toml::Value::Array(values) => values.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => serde_json::Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
@@ -0,0 +1,103 @@
+use crate::post::Post;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Starts the line of each commit in the log `History::load` reads, which
+/// can't occur in a file name or an author name.
+const COMMIT_MARKER: char = '\u{1e}';
+
+/// What the git history says about one file.
+#[derive(Debug, Clone)]
+pub struct FileHistory {
+    /// Date of the first commit adding the file.
+    pub created: NaiveDate,
+    /// Date of the last commit changing it.
+    pub modified: NaiveDate,
+    /// Names of the commit authors, by their first commit to the file.
+    pub contributors: Vec<String>,
+}
+
+impl FileHistory {
+    /// Dates `post` by the history where its front matter doesn't, and lists
+    /// its contributors.
+    pub fn apply(&self, post: &mut Post) {
+        if post.metadata.date.is_none() {
+            post.date = self.created;
+        }
+        if post.metadata.updated.is_none() {
+            post.updated = self.modified;
+        }
+        post.updated = post.updated.max(post.date);
+        post.contributors = self.contributors.clone();
+    }
+}
+
+/// The git history of every file under a source directory, read once per
+/// build. Empty when the directory isn't in a git repository or git isn't
+/// installed, so posts fall back to their modification dates.
+#[derive(Debug, Default)]
+pub struct History {
+    root: PathBuf,
+    files: HashMap<PathBuf, FileHistory>,
+}
+
+impl History {
+    pub fn load(source_dir: &Path) -> History {
+        let output = Command::new("git")
+            .current_dir(source_dir)
+            .args(["-c", "core.quotePath=false", "log", "--no-renames"])
+            .arg(format!("--format={}%ct\t%an", COMMIT_MARKER))
+            .args(["--name-only", "--relative", "--", "."])
+            .stderr(Stdio::null())
+            .output();
+        let log = match output {
+            Ok(output) if output.status.success() => output.stdout,
+            _ => return History::default(),
+        };
+
+        // The log runs newest first, so each commit pushes the file's
+        // creation date back and its author to the front.
+        let mut files: HashMap<PathBuf, FileHistory> = HashMap::new();
+        let mut commit = None;
+        for line in String::from_utf8_lossy(&log).lines() {
+            if let Some(header) = line.strip_prefix(COMMIT_MARKER) {
+                commit = header.split_once('\t').and_then(|(timestamp, author)| {
+                    let timestamp = timestamp.parse().ok()?;
+                    let datetime = DateTime::from_timestamp(timestamp, 0)?;
+                    Some((
+                        datetime.with_timezone(&Local).date_naive(),
+                        author.to_string(),
+                    ))
+                });
+                continue;
+            }
+            let Some((date, author)) = commit.as_ref().filter(|_| !line.is_empty()) else {
+                continue;
+            };
+            let history = files
+                .entry(PathBuf::from(line))
+                .or_insert_with(|| FileHistory {
+                    created: *date,
+                    modified: *date,
+                    contributors: Vec::new(),
+                });
+            history.created = *date;
+            history.contributors.retain(|name| name != author);
+            history.contributors.insert(0, author.clone());
+        }
+        crate::verbose!("Git history loaded: {} files", files.len());
+
+        History {
+            root: source_dir.to_path_buf(),
+            files,
+        }
+    }
+
+    /// The history of the file at `path`, within the source directory, if it
+    /// was ever committed.
+    pub fn file(&self, path: &Path) -> Option<&FileHistory> {
+        self.files.get(path.strip_prefix(&self.root).ok()?)
+    }
+}
@@ -0,0 +1,659 @@
+use crate::formats::{code_block, code_span, escape_markdown};
+
+/// Elements without a closing tag.
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose content is text rather than markup.
+const RAW_TEXT_ELEMENTS: [&str; 3] = ["script", "style", "textarea"];
+
+/// Elements markdown has nothing for, kept as they are.
+const HTML_BLOCKS: [&str; 15] = [
+    "table", "iframe", "video", "audio", "object", "embed", "form", "details", "svg", "script",
+    "style", "dl", "math", "canvas", "textarea",
+];
+
+/// Elements that only group others, left out around their content.
+const WRAPPERS: [&str; 11] = [
+    "div", "section", "article", "main", "header", "footer", "aside", "nav", "center", "body",
+    "html",
+];
+
+/// Blocks that end an open paragraph, as browsers read them.
+const BLOCKS: [&str; 15] = [
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+    "pre",
+    "hr",
+    "figure",
+    "figcaption",
+];
+
+/// Inline elements kept as HTML around their converted content.
+const INLINE_HTML: [&str; 16] = [
+    "sup", "sub", "mark", "u", "abbr", "kbd", "small", "ins", "del", "s", "strike", "cite", "q",
+    "time", "var", "samp",
+];
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    /// Where the element's start tag begins and ends, and where the element
+    /// ends, in the HTML it was read from.
+    start: usize,
+    tag_end: usize,
+    end: usize,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn has_class(&self, class: &str) -> bool {
+        self.attribute("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|name| name == class))
+    }
+}
+
+/// Converts the HTML of a post, as blogging platforms store it, to markdown.
+/// Paragraphs, headings, lists, quotes, code, links, images and emphasis
+/// become markdown; tables, embeds and the other elements markdown has
+/// nothing for are kept as HTML, which the markdown renderer passes through.
+pub fn to_markdown(html: &str) -> String {
+    let nodes = parse(html);
+    let converter = Converter { html };
+    let mut blocks = Vec::new();
+    converter.blocks(&nodes, &mut blocks);
+    blocks.join("\n\n")
+}
+
+/// The text of an HTML snippet, like an excerpt, on one line.
+pub fn to_text(html: &str) -> String {
+    collapse(&text_content(&parse(html)))
+}
+
+/// Parses HTML loosely, the way browsers forgive it: unclosed paragraphs and
+/// list items end at the next block or item, and stray end tags are ignored.
+fn parse(html: &str) -> Vec<Node> {
+    let mut root = Vec::new();
+    let mut open: Vec<Element> = Vec::new();
+    let close = |open: &mut Vec<Element>, root: &mut Vec<Node>, end: usize| {
+        let mut element = open.pop().unwrap();
+        element.end = end;
+        match open.last_mut() {
+            Some(parent) => parent.children.push(Node::Element(element)),
+            None => root.push(Node::Element(element)),
+        }
+    };
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &html[i..];
+        if !rest.starts_with('<') {
+            let end = rest.find('<').map_or(html.len(), |end| i + end);
+            let text = decode_entities(&html[i..end]);
+            match open.last_mut() {
+                Some(parent) => parent.children.push(Node::Text(text)),
+                None => root.push(Node::Text(text)),
+            }
+            i = end;
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            i += 4 + comment.find("-->").map_or(comment.len(), |end| end + 3);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            i += rest.find('>').map_or(rest.len(), |end| end + 1);
+            continue;
+        }
+        if let Some(tag) = rest.strip_prefix("</") {
+            let name = tag_name(tag);
+            let tag_len = rest.find('>').map_or(rest.len(), |end| end + 1);
+            if let Some(depth) = open.iter().rposition(|element| element.name == name) {
+                while open.len() > depth + 1 {
+                    close(&mut open, &mut root, i);
+                }
+                close(&mut open, &mut root, i + tag_len);
+            }
+            i += tag_len;
+            continue;
+        }
+
+        let name = tag_name(&rest[1..]);
+        if name.is_empty() {
+            let text = "<".to_string();
+            match open.last_mut() {
+                Some(parent) => parent.children.push(Node::Text(text)),
+                None => root.push(Node::Text(text)),
+            }
+            i += 1;
+            continue;
+        }
+        let (attributes, tag_len) = attributes(&rest[1 + name.len()..]);
+        let tag_end = i + 1 + name.len() + tag_len;
+        // A block ends the paragraph it is in, and an item the one before it.
+        let implied = match name.as_str() {
+            "li" => Some(["li"].as_slice()),
+            "dt" | "dd" => Some(["dt", "dd"].as_slice()),
+            name if BLOCKS.contains(&name) || HTML_BLOCKS.contains(&name) => Some(["p"].as_slice()),
+            _ => None,
+        };
+        if let Some(implied) = implied {
+            if open
+                .last()
+                .is_some_and(|element| implied.contains(&element.name.as_str()))
+            {
+                close(&mut open, &mut root, i);
+            }
+        }
+        let mut element = Element {
+            name: name.clone(),
+            attributes,
+            children: Vec::new(),
+            start: i,
+            tag_end,
+            end: tag_end,
+        };
+        i = tag_end;
+        if VOID_ELEMENTS.contains(&name.as_str()) || html[..tag_end].ends_with("/>") {
+            match open.last_mut() {
+                Some(parent) => parent.children.push(Node::Element(element)),
+                None => root.push(Node::Element(element)),
+            }
+            continue;
+        }
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            let closing = format!("</{}", name);
+            let text_end = html[i..]
+                .to_ascii_lowercase()
+                .find(&closing)
+                .map_or(html.len(), |end| i + end);
+            element
+                .children
+                .push(Node::Text(html[i..text_end].to_string()));
+            i = html[text_end..]
+                .find('>')
+                .map_or(html.len(), |end| text_end + end + 1);
+            element.end = i;
+            match open.last_mut() {
+                Some(parent) => parent.children.push(Node::Element(element)),
+                None => root.push(Node::Element(element)),
+            }
+            continue;
+        }
+        open.push(element);
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut root, html.len());
+    }
+    root
+}
+
+/// The lowercased name a tag starts with.
+fn tag_name(tag: &str) -> String {
+    tag.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == ':')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// The attributes of a start tag after its name, with the length of the rest
+/// of the tag, up to and including its `>`.
+fn attributes(tag: &str) -> (Vec<(String, String)>, usize) {
+    let mut attributes = Vec::new();
+    let bytes = tag.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'>' => return (attributes, i + 1),
+            b if b.is_ascii_whitespace() || b == b'/' => i += 1,
+            _ => {
+                let name_end = tag[i..]
+                    .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+                    .map_or(tag.len(), |end| i + end);
+                let name = tag[i..name_end].to_ascii_lowercase();
+                i = name_end;
+                let after_name = tag[i..].trim_start();
+                let mut value = String::new();
+                if let Some(rest) = after_name.strip_prefix('=') {
+                    let rest = rest.trim_start();
+                    let value_start = tag.len() - rest.len();
+                    let (raw, len) = match rest.chars().next() {
+                        Some(quote @ ('"' | '\'')) => match rest[1..].find(quote) {
+                            Some(end) => (&rest[1..1 + end], end + 2),
+                            None => (&rest[1..], rest.len()),
+                        },
+                        _ => {
+                            let end = rest
+                                .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                                .unwrap_or(rest.len());
+                            (&rest[..end], end)
+                        }
+                    };
+                    value = decode_entities(raw);
+                    i = value_start + len;
+                }
+                attributes.push((name, value));
+            }
+        }
+    }
+    (attributes, tag.len())
+}
+
+/// Text with its character references replaced by the characters.
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest[1..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+            .map(|end| end + 1)
+            .filter(|end| rest[*end..].starts_with(';'));
+        let decoded = end.and_then(|end| {
+            let name = &rest[1..end];
+            let c = match name.strip_prefix('#') {
+                Some(number) => match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => number.parse().ok().and_then(char::from_u32),
+                },
+                None => named_entity(name),
+            };
+            c.map(|c| (c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                output.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// The character of the named references posts commonly use.
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "times" => '×',
+        "deg" => '°',
+        "middot" => '·',
+        "bull" => '•',
+        "euro" => '€',
+        _ => return None,
+    })
+}
+
+struct Converter<'a> {
+    html: &'a str,
+}
+
+impl Converter<'_> {
+    /// Appends the markdown blocks of `nodes`. Runs of text and inline
+    /// elements between blocks are paragraphs.
+    fn blocks(&self, nodes: &[Node], blocks: &mut Vec<String>) {
+        let mut inline: Vec<&Node> = Vec::new();
+        for node in nodes {
+            match node {
+                Node::Element(element) if self.is_block(element) => {
+                    self.paragraph(&inline, blocks);
+                    inline.clear();
+                    self.block(element, blocks);
+                }
+                node => inline.push(node),
+            }
+        }
+        self.paragraph(&inline, blocks);
+    }
+
+    fn is_block(&self, element: &Element) -> bool {
+        let name = element.name.as_str();
+        BLOCKS.contains(&name)
+            || HTML_BLOCKS.contains(&name)
+            || WRAPPERS.contains(&name)
+            || (name == "img" && element.has_class("aligncenter"))
+    }
+
+    fn block(&self, element: &Element, blocks: &mut Vec<String>) {
+        let name = element.name.as_str();
+        match name {
+            "p" | "figcaption" => {
+                let nodes: Vec<&Node> = element.children.iter().collect();
+                let before = blocks.len();
+                self.paragraph(&nodes, blocks);
+                if name == "figcaption" {
+                    if let Some(caption) = blocks.get_mut(before) {
+                        *caption = format!("*{}*", caption);
+                    }
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                let text = self.inline(&element.children).replace("\\\n", " ");
+                let text = collapse(&text);
+                if !text.is_empty() {
+                    blocks.push(format!("{} {}", "#".repeat(level), text));
+                }
+            }
+            "ul" | "ol" => blocks.push(self.list(element)),
+            "li" => {
+                let item = self.list_item(element, "- ");
+                blocks.push(item);
+            }
+            "blockquote" => {
+                let mut quoted = Vec::new();
+                self.blocks(&element.children, &mut quoted);
+                let quote: Vec<String> = quoted
+                    .join("\n\n")
+                    .lines()
+                    .map(|line| match line.is_empty() {
+                        true => ">".to_string(),
+                        false => format!("> {}", line),
+                    })
+                    .collect();
+                if !quote.is_empty() {
+                    blocks.push(quote.join("\n"));
+                }
+            }
+            "pre" => {
+                let code = text_content(&element.children);
+                let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+                let lines: Vec<&str> = code.lines().collect();
+                let mut block = String::new();
+                code_block(&language(element), &lines, &mut block);
+                blocks.push(block.trim_end().to_string());
+            }
+            "hr" => blocks.push("---".to_string()),
+            "img" => blocks.push(self.image(element)),
+            "figure" | "div" if element.has_class("kg-card") && !is_ghost_media(element) => {
+                blocks.push(self.raw(element))
+            }
+            name if HTML_BLOCKS.contains(&name) => blocks.push(self.raw(element)),
+            _ => self.blocks(&element.children, blocks),
+        }
+    }
+
+    /// A paragraph of inline nodes, unless they hold nothing but whitespace.
+    fn paragraph(&self, nodes: &[&Node], blocks: &mut Vec<String>) {
+        let mut text = String::new();
+        for node in nodes {
+            self.inline_node(node, &mut text);
+        }
+        let lines: Vec<String> = text
+            .split("\\\n")
+            .map(collapse)
+            .filter(|line| !line.is_empty())
+            .map(|line| escape_line_start(&line))
+            .collect();
+        if !lines.is_empty() {
+            blocks.push(lines.join("\\\n"));
+        }
+    }
+
+    fn list(&self, list: &Element) -> String {
+        let ordered = list.name == "ol";
+        let mut number: usize = list
+            .attribute("start")
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(1);
+        let mut items = Vec::new();
+        for node in &list.children {
+            match node {
+                Node::Element(item) if item.name == "li" => {
+                    let marker = match ordered {
+                        true => format!("{}. ", number),
+                        false => "- ".to_string(),
+                    };
+                    number += 1;
+                    items.push(self.list_item(item, &marker));
+                }
+                Node::Element(other) => {
+                    let mut blocks = Vec::new();
+                    self.block(other, &mut blocks);
+                    items.extend(blocks);
+                }
+                Node::Text(_) => {}
+            }
+        }
+        // Items of several blocks need blank lines between them all.
+        let loose = items.iter().any(|item| item.contains("\n\n"));
+        items.join(if loose { "\n\n" } else { "\n" })
+    }
+
+    /// A list item, with the lines after its first indented under the marker.
+    fn list_item(&self, item: &Element, marker: &str) -> String {
+        let mut blocks = Vec::new();
+        self.blocks(&item.children, &mut blocks);
+        let indent = " ".repeat(marker.len());
+        let mut output = marker.to_string();
+        for (n, line) in blocks.join("\n\n").lines().enumerate() {
+            if n > 0 {
+                output.push('\n');
+                if !line.is_empty() {
+                    output.push_str(&indent);
+                }
+            }
+            output.push_str(line);
+        }
+        output.trim_end().to_string()
+    }
+
+    fn inline(&self, nodes: &[Node]) -> String {
+        let mut output = String::new();
+        for node in nodes {
+            self.inline_node(node, &mut output);
+        }
+        output
+    }
+
+    fn inline_node(&self, node: &Node, output: &mut String) {
+        let element = match node {
+            Node::Text(text) => {
+                // Whitespace collapses as it does in a browser.
+                let mut collapsed = String::with_capacity(text.len());
+                let mut space = false;
+                for c in text.chars() {
+                    match c.is_whitespace() && c != '\u{a0}' {
+                        true => space = true,
+                        false => {
+                            if space {
+                                collapsed.push(' ');
+                                space = false;
+                            }
+                            collapsed.push(c);
+                        }
+                    }
+                }
+                if space {
+                    collapsed.push(' ');
+                }
+                escape_markdown(&collapsed, output);
+                return;
+            }
+            Node::Element(element) => element,
+        };
+        match element.name.as_str() {
+            "strong" | "b" => self.emphasis("**", element, output),
+            "em" | "i" => self.emphasis("*", element, output),
+            "code" | "tt" => output.push_str(&code_span(&text_content(&element.children))),
+            "br" => output.push_str("\\\n"),
+            "img" => output.push_str(&self.image(element)),
+            "a" => {
+                let text = collapse(&self.inline(&element.children).replace("\\\n", " "));
+                match element.attribute("href").filter(|href| !href.is_empty()) {
+                    Some(href) if !text.is_empty() => {
+                        output.push_str(&format!("[{}]({}", text, destination(href)));
+                        if let Some(title) = element.attribute("title") {
+                            output.push_str(&format!(" \"{}\"", title.replace('"', "\\\"")));
+                        }
+                        output.push(')');
+                    }
+                    _ => output.push_str(&text),
+                }
+            }
+            name if INLINE_HTML.contains(&name) => {
+                output.push_str(&self.html[element.start..element.tag_end]);
+                output.push_str(&self.inline(&element.children));
+                output.push_str(&format!("</{}>", name));
+            }
+            "script" | "style" | "input" | "button" | "noscript" => {}
+            _ => output.push_str(&self.inline(&element.children)),
+        }
+    }
+
+    /// `**text**` or `*text*`, with the spaces around the text outside the
+    /// markers, where markdown needs them.
+    fn emphasis(&self, marker: &str, element: &Element, output: &mut String) {
+        let text = self.inline(&element.children);
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            output.push_str(&text);
+            return;
+        }
+        if text.starts_with(char::is_whitespace) {
+            output.push(' ');
+        }
+        output.push_str(&format!("{}{}{}", marker, trimmed, marker));
+        if text.ends_with(char::is_whitespace) {
+            output.push(' ');
+        }
+    }
+
+    fn image(&self, image: &Element) -> String {
+        let Some(src) = image.attribute("src").filter(|src| !src.is_empty()) else {
+            return String::new();
+        };
+        let mut alt = String::new();
+        escape_markdown(image.attribute("alt").unwrap_or_default(), &mut alt);
+        let mut markdown = format!("![{}]({}", alt, destination(src));
+        if let Some(title) = image.attribute("title").filter(|title| !title.is_empty()) {
+            markdown.push_str(&format!(" \"{}\"", title.replace('"', "\\\"")));
+        }
+        markdown.push(')');
+        markdown
+    }
+
+    /// An element's HTML as it was written, without the blank lines that
+    /// would end an HTML block in markdown.
+    fn raw(&self, element: &Element) -> String {
+        self.html[element.start..element.end.max(element.tag_end)]
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Ghost's image and code cards, which convert like any figure or code block.
+fn is_ghost_media(element: &Element) -> bool {
+    ["kg-image-card", "kg-code-card"]
+        .iter()
+        .any(|class| element.has_class(class))
+}
+
+/// The language of a code block, from `class="language-rust"` on it or the
+/// `<code>` inside it.
+fn language(pre: &Element) -> String {
+    let code = pre.children.iter().find_map(|node| match node {
+        Node::Element(code) if code.name == "code" => Some(code),
+        _ => None,
+    });
+    [Some(pre), code]
+        .into_iter()
+        .flatten()
+        .filter_map(|element| element.attribute("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The text of nodes without their markup.
+fn text_content(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(content) => text.push_str(content),
+            Node::Element(element) if element.name == "br" => text.push('\n'),
+            Node::Element(element) => text.push_str(&text_content(&element.children)),
+        }
+    }
+    text
+}
+
+/// A link destination, in angle brackets when it holds spaces or parentheses.
+fn destination(url: &str) -> String {
+    match url.contains([' ', '(', ')']) {
+        true => format!("<{}>", url.replace('<', "%3C").replace('>', "%3E")),
+        false => url.to_string(),
+    }
+}
+
+/// `text` on one line, with runs of spaces made one.
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A line of text escaped where markdown would start a heading, quote, list,
+/// table or thematic break.
+fn escape_line_start(line: &str) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if line.starts_with(['#', '>', '-', '+', '=', '|']) {
+        format!("\\{}", line)
+    } else if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        format!("{}\\{}", &line[..digits], &line[digits..])
+    } else {
+        line.to_string()
+    }
+}
@@ -0,0 +1,94 @@
+use crate::config::Config;
+use crate::languages::site_languages;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Directory at the source root holding a `<lang>.yaml` of UI strings per language.
+pub const I18N_DIR: &str = "i18n";
+
+/// The text of the labels and headings templates and listings add around
+/// posts, by key.
+pub type Strings = BTreeMap<String, String>;
+
+/// The built-in English strings. `{name}`-style placeholders are filled in
+/// where a string is used.
+const DEFAULT_STRINGS: [(&str, &str); 30] = [
+    ("posts", "Posts"),
+    ("archive", "Archive"),
+    ("search", "Search"),
+    ("tags", "Tags"),
+    ("back", "Back"),
+    ("next", "Next"),
+    ("newer", "Newer"),
+    ("older", "Older"),
+    ("page_of", "Page {current} of {total}"),
+    ("reading_time", "{minutes} min read"),
+    ("word_count", "{words} words"),
+    ("updated", "Last updated {date}"),
+    ("draft", "Draft"),
+    ("series_part", "Part {part} of {count} in {series}"),
+    ("related_posts", "Related posts"),
+    ("all_posts_by_date", "All posts by date"),
+    ("tagged", "Posts tagged \"{name}\""),
+    ("period", "Posts from {name}"),
+    ("posts_by", "Posts by {name}"),
+    ("by", "by"),
+    ("search_placeholder", "Search posts"),
+    ("search_result", "1 result"),
+    ("search_results", "{count} results"),
+    ("not_found", "Page not found"),
+    (
+        "not_found_text",
+        "There's nothing at this address. It may have moved, or never existed.",
+    ),
+    ("back_to_posts", "Back to the posts"),
+    ("theme_toggle", "Switch between light and dark mode"),
+    ("feed", "Feed"),
+    ("copy_code", "Copy"),
+    ("copied_code", "Copied"),
+];
+
+pub fn default_strings() -> Strings {
+    DEFAULT_STRINGS
+        .iter()
+        .map(|(key, text)| (key.to_string(), text.to_string()))
+        .collect()
+}
+
+/// The strings of every language of the site: the built-in ones, replaced by
+/// those in `<source>/i18n/<lang>.yaml`, then by `strings` under the
+/// language's entry in the config.
+pub fn load_strings(
+    source_dir: &Path,
+    config: &Config,
+) -> Result<BTreeMap<String, Strings>, Box<dyn Error>> {
+    let mut strings = BTreeMap::new();
+    for lang in site_languages(config) {
+        let mut language_strings = default_strings();
+        let path = source_dir.join(I18N_DIR).join(format!("{}.yaml", lang));
+        if path.is_file() {
+            let content = fs::read_to_string(&path)?;
+            let file_strings: Strings = serde_yaml::from_str(&content)
+                .map_err(|e| format!("Invalid strings file {:?}: {}", path, e))?;
+            crate::verbose!("Strings loaded: {:?}", path);
+            language_strings.extend(file_strings);
+        }
+        if let Some(language) = config.languages.get(lang) {
+            language_strings.extend(language.strings.clone());
+        }
+        strings.insert(lang.to_string(), language_strings);
+    }
+    Ok(strings)
+}
+
+/// The string `key` in `lang`.
+pub fn string(config: &Config, lang: &str, key: &str) -> String {
+    config
+        .strings
+        .get(lang)
+        .and_then(|strings| strings.get(key))
+        .cloned()
+        .unwrap_or_else(|| default_strings().remove(key).unwrap_or_default())
+}
@@ -0,0 +1,270 @@
+use crate::config::ImagesConfig;
+use crate::error::BuildError;
+use crate::markdown::RenderError;
+use crate::output::write_atomic;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use lol_html::html_content::Element;
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Image formats that are measured and resized. Others, such as SVG, are left alone.
+const RESIZED_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Adds `loading="lazy"` and `decoding="async"` to the `<img>` tags of a
+/// rendered post, and `width`, `height`, `srcset` and `sizes` to its PNG and
+/// JPEG images, writing the resized variants `srcset` lists next to the copy
+/// of each original. `locate` maps an image's `src` to its source file
+/// and the path it is copied to; images it can't find are left unchanged.
+/// Attributes already set in the markdown are kept.
+pub fn responsive_images(
+    html_content: &str,
+    config: &ImagesConfig,
+    locate: impl Fn(&str) -> Option<(PathBuf, PathBuf)>,
+) -> Result<String, RenderError> {
+    let settings = RewriteStrSettings::new()
+        .append_element_content_handler(element!("img[src]", |el| Ok(rewrite_image(
+            el, config, &locate
+        )?)));
+
+    Ok(rewrite_str(html_content, settings)?)
+}
+
+/// `src` of every image without alt text. Markdown images always get an `alt`
+/// attribute, so an empty one counts as missing too.
+pub fn images_without_alt(html_content: &str) -> Vec<String> {
+    let mut missing = Vec::new();
+    let settings =
+        RewriteStrSettings::new().append_element_content_handler(element!("img", |el| {
+            if el
+                .get_attribute("alt")
+                .is_none_or(|alt| alt.trim().is_empty())
+            {
+                missing.push(el.get_attribute("src").unwrap_or_default());
+            }
+            Ok(())
+        }));
+    // Only the handler above could fail, and it doesn't.
+    let _ = rewrite_str(html_content, settings);
+    missing
+}
+
+/// Whether a resized variant that the `srcset` of a rendered post lists is
+/// missing from the output directory, or older than its original, so HTML
+/// taken from the cache must be rendered again to write it. `locate` is the
+/// function passed to `responsive_images`.
+pub fn variants_stale(
+    html_content: &str,
+    locate: impl Fn(&str) -> Option<(PathBuf, PathBuf)>,
+) -> bool {
+    let mut stale = false;
+    let settings = RewriteStrSettings::new().append_element_content_handler(element!(
+        "img[src][srcset]",
+        |el| {
+            let (Some(src), Some(srcset)) = (el.get_attribute("src"), el.get_attribute("srcset"))
+            else {
+                return Ok(());
+            };
+            let Some((source_path, output_path)) = locate(&src) else {
+                return Ok(());
+            };
+            let original = HashSet::from([output_path.clone()]);
+            stale |= srcset
+                .split(',')
+                .filter_map(|candidate| candidate.split_whitespace().next())
+                .filter_map(|url| url.rsplit('/').next())
+                .map(|name| output_path.with_file_name(name))
+                .filter(|path| is_image_variant(path, &original))
+                .any(|path| is_stale(&path, &source_path));
+            Ok(())
+        }
+    ));
+    // Only the handler above could fail, and it doesn't.
+    let _ = rewrite_str(html_content, settings);
+    stale
+}
+
+fn rewrite_image(
+    el: &mut Element,
+    config: &ImagesConfig,
+    locate: &impl Fn(&str) -> Option<(PathBuf, PathBuf)>,
+) -> Result<(), BuildError> {
+    // Images below the fold shouldn't hold up the rest of the page.
+    if !el.has_attribute("loading") {
+        set_attribute(el, "loading", "lazy");
+    }
+    if !el.has_attribute("decoding") {
+        set_attribute(el, "decoding", "async");
+    }
+
+    let Some(src) = el.get_attribute("src") else {
+        return Ok(());
+    };
+    if !is_resized(&src) {
+        return Ok(());
+    }
+    let Some((source_path, output_path)) = locate(&src) else {
+        return Ok(());
+    };
+    let image_error = |source| BuildError::Image {
+        path: source_path.clone(),
+        source,
+    };
+
+    let (width, height) = image::image_dimensions(&source_path).map_err(image_error)?;
+    if !el.has_attribute("width") && !el.has_attribute("height") {
+        set_attribute(el, "width", &width.to_string());
+        set_attribute(el, "height", &height.to_string());
+    }
+    if el.has_attribute("srcset") {
+        return Ok(());
+    }
+
+    let widths: Vec<u32> = config
+        .widths
+        .iter()
+        .copied()
+        .filter(|&w| w < width)
+        .collect();
+    if widths.is_empty() {
+        return Ok(());
+    }
+    let mut original = None;
+    let mut srcset = Vec::new();
+    for &variant_width in &widths {
+        let variant_path = output_path.with_file_name(variant_name(&output_path, variant_width));
+        if is_stale(&variant_path, &source_path) {
+            if original.is_none() {
+                original = Some(image::open(&source_path).map_err(image_error)?);
+            }
+            // Resizing keeps the aspect ratio, so only the width constrains it.
+            let variant =
+                original
+                    .as_ref()
+                    .unwrap()
+                    .resize(variant_width, height, FilterType::Lanczos3);
+            write_variant(&variant, &variant_path)?;
+        }
+        srcset.push(format!(
+            "{} {}w",
+            variant_url(&src, &variant_path),
+            variant_width
+        ));
+    }
+    srcset.push(format!("{} {}w", src, width));
+
+    set_attribute(el, "srcset", &srcset.join(", "));
+    if !el.has_attribute("sizes") {
+        set_attribute(el, "sizes", &config.sizes);
+    }
+    Ok(())
+}
+
+fn write_variant(variant: &DynamicImage, variant_path: &Path) -> Result<(), BuildError> {
+    if let Some(parent) = variant_path.parent() {
+        fs::create_dir_all(parent).map_err(|source| BuildError::Write {
+            path: variant_path.to_path_buf(),
+            source,
+        })?;
+    }
+    let image_error = |source| BuildError::Image {
+        path: variant_path.to_path_buf(),
+        source,
+    };
+    let format = ImageFormat::from_path(variant_path).map_err(image_error)?;
+    let mut encoded = Cursor::new(Vec::new());
+    variant
+        .write_to(&mut encoded, format)
+        .map_err(image_error)?;
+    write_atomic(variant_path, encoded.into_inner()).map_err(|source| BuildError::Write {
+        path: variant_path.to_path_buf(),
+        source,
+    })?;
+    crate::verbose!("Image variant generated: {:?}", variant_path);
+    Ok(())
+}
+
+/// Whether `src` is a local image that gets resized.
+fn is_resized(src: &str) -> bool {
+    if src.contains(':') || src.starts_with("//") {
+        return false;
+    }
+    Path::new(src).extension().is_some_and(|extension| {
+        RESIZED_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+    })
+}
+
+/// `diagram.png` resized to 480 pixels is `diagram-480w.png`. The extension
+/// keeps its case, `Photo-480w.JPG`, so `is_image_variant` finds the original.
+fn variant_name(path: &Path, width: u32) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    format!("{}-{}w.{}", stem, width, extension)
+}
+
+/// Whether `path` is a resized copy, named by `variant_name`, of one of the
+/// `images` next to it.
+pub fn is_image_variant(path: &Path, images: &HashSet<PathBuf>) -> bool {
+    let (Some(stem), Some(extension)) = (
+        path.file_stem().and_then(OsStr::to_str),
+        path.extension().and_then(OsStr::to_str),
+    ) else {
+        return false;
+    };
+    let Some((original_stem, width)) = stem.rsplit_once('-') else {
+        return false;
+    };
+    let is_width = width
+        .strip_suffix('w')
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()));
+    is_width && images.contains(&path.with_file_name(format!("{}.{}", original_stem, extension)))
+}
+
+/// `src` with its file name replaced by the variant's.
+fn variant_url(src: &str, variant_path: &Path) -> String {
+    let name = variant_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    match src.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, name),
+        None => name.to_string(),
+    }
+}
+
+fn is_stale(variant_path: &Path, source_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(variant_path), modified(source_path)) {
+        (Ok(variant), Ok(source)) => variant < source,
+        _ => true,
+    }
+}
+
+/// Setting a plain attribute name can't fail.
+fn set_attribute(el: &mut Element, name: &str, value: &str) {
+    el.set_attribute(name, value).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_keep_the_extension_case() {
+        for original in ["img/diagram.png", "img/Photo.JPG"] {
+            let original = Path::new(original);
+            let variant = original.with_file_name(variant_name(original, 480));
+            let images = HashSet::from([original.to_path_buf()]);
+            assert!(is_image_variant(&variant, &images), "{:?}", variant);
+        }
+        assert_eq!(
+            variant_name(Path::new("img/Photo.JPG"), 480),
+            "Photo-480w.JPG"
+        );
+        assert!(is_resized("/img/Photo.JPG"));
+    }
+}
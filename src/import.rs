@@ -0,0 +1,1514 @@
+use crate::config::{AssetsConfig, CONFIG_FILE, TAGS};
+use crate::dry_run::relative_files;
+use crate::error::BuildError;
+use crate::exports;
+use crate::formats::{code_block, find_date};
+use crate::front_matter::{self, toml_to_json};
+use crate::html;
+use crate::links::{describe, percent_decode, EXTERNAL_TIMEOUT};
+use crate::new::POSTS_DIR;
+use crate::post::METADATA_KEYS;
+use crate::site::PAGES_DIR;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Generators whose sites `ebolg import --from` reads.
+pub const GENERATORS: [&str; 3] = ["hugo", "jekyll", "zola"];
+
+/// Blogging platforms whose export files `ebolg import --from` reads.
+pub const PLATFORMS: [&str; 2] = ["wordpress", "ghost"];
+
+/// Extensions of the images of an exported site that are downloaded.
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "ico"];
+
+/// Extensions of the markdown files the generators read, all imported as `.md`.
+const MARKDOWN_EXTENSIONS: [&str; 4] = ["md", "markdown", "mdown", "mkd"];
+
+/// Config files of each generator, in the order they look for them.
+const HUGO_CONFIGS: [&str; 8] = [
+    "hugo.toml",
+    "hugo.yaml",
+    "hugo.yml",
+    "hugo.json",
+    "config.toml",
+    "config.yaml",
+    "config.yml",
+    "config.json",
+];
+const JEKYLL_CONFIGS: [&str; 3] = ["_config.yml", "_config.yaml", "_config.toml"];
+const ZOLA_CONFIGS: [&str; 1] = ["config.toml"];
+
+/// Files at the root of a Jekyll site that aren't part of it, besides those
+/// its `exclude` lists.
+const JEKYLL_EXCLUDED: [&str; 6] = [
+    "Gemfile",
+    "Gemfile.lock",
+    "node_modules",
+    "vendor",
+    "package.json",
+    "package-lock.json",
+];
+
+/// Jekyll layouts every post or page has, which ebolg picks by itself.
+const JEKYLL_DEFAULT_LAYOUTS: [&str; 3] = ["default", "post", "page"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Generator {
+    Hugo,
+    Jekyll,
+    Zola,
+}
+
+/// The settings of the site's config that ebolg has a setting for.
+#[derive(Debug, Default)]
+struct Settings {
+    title: Option<String>,
+    base_url: Option<String>,
+    author: Option<String>,
+    permalink: Option<String>,
+    /// The permalink names posts by their title, as Hugo's `:title` does, so
+    /// posts without a `slug:` get one from their title.
+    title_slugs: bool,
+    paginate: Option<i64>,
+    /// Front-matter keys of the taxonomies besides `tags`.
+    taxonomies: Vec<String>,
+    /// Root entries of a Jekyll site its `exclude` leaves out.
+    exclude: Vec<String>,
+}
+
+/// A file of the imported site and the path, relative to the source
+/// directory, it is imported at.
+#[derive(Debug)]
+struct Planned {
+    from: PathBuf,
+    to: PathBuf,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    /// A post or page whose front matter and markup are converted.
+    Markdown {
+        /// The directory of a Hugo or Zola page bundle, whose other files are
+        /// imported next to the post under a directory of its name.
+        bundle: Option<PathBuf>,
+        /// The date a Jekyll post's file name starts with.
+        date: Option<String>,
+        /// A post from Jekyll's `_drafts`.
+        draft: bool,
+    },
+    /// A post or page of a WordPress or Ghost export, converted from its
+    /// HTML.
+    Written,
+    /// An image of the exported site, downloaded from this URL.
+    Download(String),
+    /// Any other file, copied as it is.
+    Copy,
+}
+
+/// An import of one site, planned in full before anything is written.
+struct Import<'a> {
+    generator: Generator,
+    site_dir: &'a Path,
+    /// The default static directory, where the site's static files go.
+    static_dir: PathBuf,
+    settings: Settings,
+    planned: Vec<Planned>,
+    /// The source directory path of every post and page, by the ways the
+    /// imported site's links and shortcodes name them.
+    links: BTreeMap<String, PathBuf>,
+    /// Front-matter keys the imported posts set that mean nothing to ebolg.
+    extra_keys: BTreeSet<String>,
+    /// Front-matter keys the imported posts set, to tell which taxonomies
+    /// are used.
+    used_keys: BTreeSet<String>,
+    patterns: Patterns,
+}
+
+/// The markup of each generator that is rewritten in posts.
+struct Patterns {
+    /// Hugo's `{{< ref "post.md" >}}` and `relref`.
+    hugo_ref: Regex,
+    /// Hugo's `{{< highlight go >}}` ... `{{< /highlight >}}`.
+    hugo_highlight: Regex,
+    /// A Hugo shortcode with named arguments only, `{{< figure src="a.png" >}}`.
+    hugo_shortcode: Regex,
+    /// Zola's links to other posts, `[post](@/blog/post.md)`.
+    zola_link: Regex,
+    /// A Zola shortcode, `{{ youtube(id="abc") }}`.
+    zola_shortcode: Regex,
+    /// A named argument of a Zola shortcode.
+    zola_argument: Regex,
+    /// Jekyll's `{% highlight ruby %}` ... `{% endhighlight %}`.
+    jekyll_highlight: Regex,
+    /// Jekyll's `{% post_url 2024-01-02-post %}` and `{% link about.md %}`.
+    jekyll_link: Regex,
+    /// Jekyll's `{{ site.baseurl }}` and `{{ "/about/" | relative_url }}`.
+    jekyll_url: Regex,
+    /// A markdown link or image, or an HTML `src` or `href`, pointing at a
+    /// relative path.
+    relative_link: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Patterns {
+            hugo_ref: Regex::new(r#"\{\{[<%]\s*(?:rel)?ref\s+"([^"]*)"\s*[>%]\}\}"#).unwrap(),
+            hugo_highlight: Regex::new(
+                r"(?s)\{\{<\s*highlight\s+([\w+#-]+)[^>]*>\}\}\n?(.*?)\n?\{\{<\s*/highlight\s*>\}\}",
+            )
+            .unwrap(),
+            hugo_shortcode: Regex::new(
+                r#"\{\{[<%]\s*([\w-]+)((?:\s+[\w-]+=(?:"[^"]*"|[^\s"]+))*)\s*[>%]\}\}"#,
+            )
+            .unwrap(),
+            zola_link: Regex::new(r"\]\(@/([^)#\s]*)(#[^)\s]*)?\)").unwrap(),
+            zola_shortcode: Regex::new(r"\{\{\s*([\w-]+)\(([^)]*)\)\s*\}\}").unwrap(),
+            zola_argument: Regex::new(r#"([\w-]+)\s*=\s*("(?:[^"\\]|\\.)*"|'[^']*'|[^,\s]+)"#)
+                .unwrap(),
+            jekyll_highlight: Regex::new(
+                r"(?s)\{%-?\s*highlight\s+([\w+#-]+)[^%]*%\}\n?(.*?)\n?\{%-?\s*endhighlight\s*-?%\}",
+            )
+            .unwrap(),
+            jekyll_link: Regex::new(r"\{%-?\s*(post_url|link)\s+([^\s%]+)\s*-?%\}").unwrap(),
+            jekyll_url: Regex::new(
+                r#"\{\{-?\s*(?:site\.baseurl|["']([^"']*)["']\s*\|\s*(?:relative|absolute)_url)\s*-?\}\}"#,
+            )
+            .unwrap(),
+            relative_link: Regex::new(r##"(\]\(\s*<?|(?:src|href)=")([^\s)>"#?:]+)"##).unwrap(),
+        }
+    }
+}
+
+/// Imports the site in `site_dir`, built with one of the `GENERATORS`, into
+/// `source_dir`: its posts and pages with their front matter converted to
+/// ebolg's keys and `YYYY-MM-DD` dates, their links to each other and the
+/// shortcodes that have an equivalent rewritten, the files they use and the
+/// static files, and an `ebolg.toml` holding the site's title, URL, author,
+/// permalinks and taxonomies. Nothing is written when any of the files
+/// already exists. A dry run only lists the files it would create.
+///
+/// The export file of one of the `PLATFORMS` is imported by `import_export`,
+/// with `url` the site's address when the export doesn't have it.
+pub fn import(
+    generator: &str,
+    site_dir: &Path,
+    source_dir: &Path,
+    url: Option<&str>,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let generator = match generator {
+        "hugo" => Generator::Hugo,
+        "jekyll" => Generator::Jekyll,
+        "zola" => Generator::Zola,
+        platform if PLATFORMS.contains(&platform) => {
+            return import_export(platform, site_dir, source_dir, url, dry_run)
+        }
+        other => {
+            return Err(format!(
+                "Unknown generator {:?}, expected one of: {}, {}",
+                other,
+                GENERATORS.join(", "),
+                PLATFORMS.join(", ")
+            )
+            .into())
+        }
+    };
+    if !site_dir.is_dir() {
+        return Err(format!("{} is not a directory", site_dir.display()).into());
+    }
+    let settings = read_settings(generator, site_dir)?;
+    let mut import = Import {
+        generator,
+        site_dir,
+        static_dir: AssetsConfig::default().static_dir,
+        settings,
+        planned: Vec::new(),
+        links: BTreeMap::new(),
+        extra_keys: BTreeSet::new(),
+        used_keys: BTreeSet::new(),
+        patterns: Patterns::new(),
+    };
+    match generator {
+        Generator::Hugo | Generator::Zola => import.plan_content()?,
+        Generator::Jekyll => import.plan_jekyll()?,
+    }
+    let planned = std::mem::take(&mut import.planned);
+    check(&planned, source_dir)?;
+
+    let mut files = Vec::with_capacity(planned.len());
+    for planned in &planned {
+        let contents = match &planned.kind {
+            Kind::Markdown {
+                bundle,
+                date,
+                draft,
+            } => Some(
+                import
+                    .convert(planned, bundle.as_deref(), date.as_deref(), *draft)?
+                    .into_bytes(),
+            ),
+            _ => None,
+        };
+        files.push((planned, contents));
+    }
+    let config = import
+        .settings
+        .config(&import.used_keys, &import.extra_keys);
+    write(site_dir, source_dir, files, &config, dry_run)
+}
+
+/// Imports the export file of a WordPress or Ghost site: its posts into
+/// `posts/` and its pages into `pages/`, each named by its slug, their HTML
+/// converted to markdown, and the images of the site they show downloaded
+/// into `static/` at the path they had on it, so their URLs stay the same.
+/// The `ebolg.toml` gets the site's title, URL and author, and the permalink
+/// its posts were published at.
+fn import_export(
+    platform: &str,
+    file: &Path,
+    source_dir: &Path,
+    url: Option<&str>,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let content = fs::read_to_string(file).map_err(|source| BuildError::Read {
+        path: file.to_path_buf(),
+        source,
+    })?;
+    let export = match platform {
+        "wordpress" => exports::wordpress(&content),
+        _ => exports::ghost(&content),
+    }
+    .map_err(|reason| BuildError::Convert {
+        path: file.to_path_buf(),
+        reason,
+    })?;
+    let base_url = url
+        .map(str::to_string)
+        .or(export.url.clone())
+        .map(|url| url.trim_end_matches('/').to_string());
+    let mut entries = export.entries;
+    // Ghost writes `__GHOST_URL__` for the site's address, so its links
+    // become site-relative ones.
+    for entry in &mut entries {
+        entry.html = entry.html.replace(exports::GHOST_URL, "");
+        if let Some(Value::String(image)) = entry.metadata.get_mut("image") {
+            *image = image.replace(exports::GHOST_URL, "");
+        }
+    }
+
+    // Links to the site itself become site-relative, so they are to the
+    // imported posts, which keep their URLs.
+    if let Some(host) = base_url.as_deref().and_then(|url| url.split_once("://")) {
+        for entry in &mut entries {
+            for scheme in ["http", "https"] {
+                let link = format!("href=\"{}://{}/", scheme, host.1);
+                entry.html = entry.html.replace(&link, "href=\"/");
+            }
+        }
+    }
+    let static_dir = AssetsConfig::default().static_dir;
+    let image_pattern = Regex::new(&format!(
+        r#"(?i)(?:src|href)=["']([^"'?#\s]+\.(?:{}))[?#"']"#,
+        IMAGE_EXTENSIONS.join("|")
+    ))
+    .unwrap();
+    let mut planned = Vec::new();
+    // The URLs of the images posts show, by the site-relative path they are
+    // imported as.
+    let mut images: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut unreachable = false;
+    for entry in &entries {
+        let featured = entry.metadata.get("image").and_then(Value::as_str);
+        let found = image_pattern
+            .captures_iter(&entry.html)
+            .map(|captures| captures.get(1).unwrap().as_str())
+            .chain(featured);
+        for image in found {
+            let path = match &base_url {
+                Some(base_url) => site_path(image, base_url),
+                None => image.strip_prefix('/').filter(|_| !image.starts_with("//")),
+            };
+            // Checked once decoded, so `%2e%2e` can't climb out of static/.
+            let Some(path) = path.filter(|path| {
+                !percent_decode(path)
+                    .split(['/', '\\'])
+                    .any(|part| part == "..")
+            }) else {
+                continue;
+            };
+            if base_url.is_none() {
+                unreachable = true;
+                continue;
+            }
+            images
+                .entry(format!("/{}", path))
+                .or_default()
+                .insert(image.to_string());
+        }
+    }
+    if unreachable {
+        eprintln!(
+            "Warning: {}: the site's address isn't in the export, pass --url to download its images",
+            file.display()
+        );
+    }
+    let base = base_url.as_deref().unwrap_or_default();
+    for path in images.keys() {
+        planned.push(Planned {
+            from: PathBuf::from(format!("{}{}", base, path)),
+            to: static_dir.join(percent_decode(&path[1..])),
+            kind: Kind::Download(format!("{}{}", base, path)),
+        });
+    }
+    for entry in &entries {
+        let to = match (&entry.url_path, entry.page) {
+            (Some(url_path), true) if !url_path.trim_matches('/').is_empty() => {
+                let path = url_path.trim_matches('/');
+                let path = path.strip_suffix(".html").unwrap_or(path);
+                Path::new(PAGES_DIR).join(format!("{}.md", path))
+            }
+            (_, true) => Path::new(PAGES_DIR).join(format!("{}.md", entry.slug)),
+            (_, false) => Path::new(POSTS_DIR).join(format!("{}.md", entry.slug)),
+        };
+        planned.push(Planned {
+            from: PathBuf::from(entry.url_path.as_deref().unwrap_or(&entry.slug)),
+            to,
+            kind: Kind::Written,
+        });
+    }
+    check(&planned, source_dir)?;
+
+    // Images are fetched before anything is written, so posts keep linking
+    // to those that couldn't be.
+    let agent = ureq::AgentBuilder::new().timeout(EXTERNAL_TIMEOUT).build();
+    let mut files = Vec::with_capacity(planned.len());
+    for planned in &planned {
+        let Kind::Download(url) = &planned.kind else {
+            continue;
+        };
+        if dry_run {
+            files.push((planned, None));
+            continue;
+        }
+        crate::verbose!("Downloading {}", url);
+        match download(&agent, url) {
+            Ok(image) => files.push((planned, Some(image))),
+            Err(reason) => {
+                eprintln!("Warning: could not download {}: {}", url, reason);
+                let path = &url[base.len()..];
+                images.remove(path);
+            }
+        }
+    }
+
+    let mut used_keys = BTreeSet::new();
+    let mut extra_keys = BTreeSet::new();
+    let posts = planned
+        .iter()
+        .filter(|planned| matches!(planned.kind, Kind::Written));
+    for (entry, planned) in entries.iter().zip(posts) {
+        let mut html = entry.html.clone();
+        let mut metadata = entry.metadata.clone();
+        for (path, urls) in &images {
+            for url in urls {
+                html = html.replace(url.as_str(), path);
+                if metadata.get("image").and_then(Value::as_str) == Some(url.as_str()) {
+                    metadata.insert("image".to_string(), Value::from(path.as_str()));
+                }
+            }
+        }
+        for key in metadata.keys() {
+            used_keys.insert(key.clone());
+            if !METADATA_KEYS.contains(&key.as_str()) && key != TAGS && key != "categories" {
+                extra_keys.insert(key.clone());
+            }
+        }
+        let mut contents = yaml_front_matter(metadata)?;
+        contents.push_str(&html::to_markdown(&html));
+        contents.truncate(contents.trim_end().len());
+        contents.push('\n');
+        files.push((planned, Some(contents.into_bytes())));
+    }
+
+    let settings = Settings {
+        title: export.title,
+        base_url,
+        author: export.author,
+        permalink: export_permalink(&entries),
+        taxonomies: vec!["categories".to_string()],
+        ..Settings::default()
+    };
+    let config = settings.config(&used_keys, &extra_keys);
+    write(file, source_dir, files, &config, dry_run)
+}
+
+/// The path, without its leading `/`, of an image URL on the site at
+/// `base_url`, or linked to from the site's root. Images elsewhere have none.
+fn site_path<'a>(url: &'a str, base_url: &str) -> Option<&'a str> {
+    // The site may be linked to by `http://` or `https://`, or without either.
+    let host = base_url.split_once("//").map_or(base_url, |(_, rest)| rest);
+    match url
+        .strip_prefix("//")
+        .or(url.split_once("://").map(|(_, rest)| rest))
+    {
+        Some(rest) => rest.strip_prefix(host)?.strip_prefix('/'),
+        None => url.strip_prefix('/'),
+    }
+}
+
+/// The permalink most posts of an export were published at, with their slug
+/// and the parts of their date replaced by placeholders, if they were
+/// published at one like `/2024/01/02/hello/`.
+fn export_permalink(entries: &[exports::Entry]) -> Option<String> {
+    let mut patterns: BTreeMap<String, usize> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| !entry.page) {
+        let (Some(url_path), Some(date)) = (
+            &entry.url_path,
+            entry.metadata.get("date").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let mut parts = date.splitn(3, '-');
+        let (year, month, day) = (parts.next(), parts.next(), parts.next());
+        let pattern: Vec<String> = url_path
+            .split('/')
+            .map(|part| match part {
+                part if part == entry.slug => "{slug}".to_string(),
+                part if part.strip_suffix(".html") == Some(entry.slug.as_str()) => {
+                    "{slug}.html".to_string()
+                }
+                part if Some(part) == year => "{year}".to_string(),
+                part if Some(part) == month => "{month}".to_string(),
+                part if Some(part) == day => "{day}".to_string(),
+                part => part.to_string(),
+            })
+            .collect();
+        let pattern = pattern.join("/");
+        if pattern.contains("{slug}") {
+            *patterns.entry(pattern).or_default() += 1;
+        }
+    }
+    let (pattern, count) = patterns.iter().max_by_key(|(_, count)| **count)?;
+    let total: usize = patterns.values().sum();
+    if *count < total {
+        eprintln!(
+            "Warning: {} of {} posts were published at {}, the permalink of the others will change",
+            count, total, pattern
+        );
+    }
+    Some(pattern.clone())
+}
+
+/// The body of the response to a GET of `url`.
+fn download(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>, String> {
+    let response = agent.get(url).call().map_err(describe)?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+/// Fails when a file would be imported outside the source directory, when
+/// two would be imported at the same path, or when one would overwrite a file
+/// of the source directory.
+fn check(planned: &[Planned], source_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mut destinations: BTreeMap<&Path, &Path> = BTreeMap::new();
+    for planned in planned {
+        if !planned
+            .to
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(format!(
+                "{} would be imported as {}, outside the source directory",
+                planned.from.display(),
+                planned.to.display()
+            )
+            .into());
+        }
+        if let Some(other) = destinations.insert(&planned.to, &planned.from) {
+            return Err(format!(
+                "{} and {} would both be imported as {}",
+                other.display(),
+                planned.from.display(),
+                planned.to.display()
+            )
+            .into());
+        }
+        let to = source_dir.join(&planned.to);
+        if to.exists() {
+            return Err(format!("{} already exists", to.display()).into());
+        }
+    }
+    Ok(())
+}
+
+/// Writes the imported files into `source_dir`, each with its contents or
+/// copied when it has none, and the `ebolg.toml`, unless the source directory
+/// has one. A dry run only lists them.
+fn write(
+    from: &Path,
+    source_dir: &Path,
+    files: Vec<(&Planned, Option<Vec<u8>>)>,
+    config: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config_path = source_dir.join(CONFIG_FILE);
+    let (mut posts, mut pages) = (0, 0);
+    for (planned, contents) in files {
+        let to = source_dir.join(&planned.to);
+        match planned.kind {
+            Kind::Markdown { .. } | Kind::Written if planned.to.starts_with(PAGES_DIR) => {
+                pages += 1
+            }
+            Kind::Markdown { .. } | Kind::Written => posts += 1,
+            _ => {}
+        }
+        if dry_run {
+            println!("Would create {}", to.display());
+            continue;
+        }
+        fs::create_dir_all(to.parent().unwrap())?;
+        match contents {
+            Some(contents) => fs::write(&to, contents)?,
+            None => fs::copy(&planned.from, &to).map(|_| ())?,
+        }
+    }
+    if config_path.exists() {
+        println!(
+            "Leaving {} as it is; the imported site's settings are:\n\n{}",
+            config_path.display(),
+            config
+        );
+    } else if dry_run {
+        println!("Would create {}", config_path.display());
+    } else {
+        fs::create_dir_all(source_dir)?;
+        fs::write(&config_path, config)?;
+    }
+
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    println!(
+        "{} {} posts and {} pages from {} into {}",
+        verb,
+        posts,
+        pages,
+        from.display(),
+        source_dir.display()
+    );
+    Ok(())
+}
+
+impl Settings {
+    /// The `ebolg.toml` of the imported site, with the taxonomies among the
+    /// `used_keys` of its posts and the `extra_keys` ebolg doesn't know.
+    fn config(&self, used_keys: &BTreeSet<String>, extra_keys: &BTreeSet<String>) -> String {
+        let settings = self;
+        let mut config = toml::Table::new();
+        for (key, value) in [
+            ("title", &settings.title),
+            ("base_url", &settings.base_url),
+            ("author", &settings.author),
+        ] {
+            if let Some(value) = value {
+                config.insert(key.to_string(), toml::Value::from(value.as_str()));
+            }
+        }
+        if let Some(permalink) = &settings.permalink {
+            config.insert(
+                "permalink".to_string(),
+                toml::Value::from(permalink.as_str()),
+            );
+        }
+        if let Some(paginate) = settings.paginate.filter(|paginate| *paginate > 0) {
+            config.insert("paginate".to_string(), toml::Value::from(paginate));
+        }
+        let taxonomies: toml::Table = settings
+            .taxonomies
+            .iter()
+            .filter(|taxonomy| used_keys.contains(*taxonomy))
+            .map(|taxonomy| (taxonomy.clone(), toml::Value::Table(toml::Table::new())))
+            .collect();
+        if !taxonomies.is_empty() {
+            config.insert("taxonomies".to_string(), toml::Value::Table(taxonomies));
+        }
+        if !extra_keys.is_empty() {
+            let mut front_matter = toml::Table::new();
+            front_matter.insert(
+                "extra".to_string(),
+                toml::Value::Array(
+                    extra_keys
+                        .iter()
+                        .map(|key| toml::Value::from(key.as_str()))
+                        .collect(),
+                ),
+            );
+            config.insert("front_matter".to_string(), toml::Value::Table(front_matter));
+        }
+        config.to_string()
+    }
+}
+
+/// Reads the settings of the site's config file.
+fn read_settings(generator: Generator, site_dir: &Path) -> Result<Settings, Box<dyn Error>> {
+    let candidates: &[&str] = match generator {
+        Generator::Hugo => &HUGO_CONFIGS,
+        Generator::Jekyll => &JEKYLL_CONFIGS,
+        Generator::Zola => &ZOLA_CONFIGS,
+    };
+    let Some(path) = candidates
+        .iter()
+        .map(|name| site_dir.join(name))
+        .find(|path| path.is_file())
+    else {
+        return Err(format!(
+            "No config file found in {}, expected one of: {}",
+            site_dir.display(),
+            candidates.join(", ")
+        )
+        .into());
+    };
+    let text = fs::read_to_string(&path)?;
+    let config: Value = match path.extension().and_then(OsStr::to_str) {
+        Some("toml") => toml_to_json(toml::Value::Table(text.parse().map_err(|source| {
+            BuildError::Config {
+                path: path.clone(),
+                source,
+            }
+        })?)),
+        Some("json") => serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid config in {}: {}", path.display(), e))?,
+        _ => serde_yaml::from_str(&text)
+            .map_err(|e| format!("Invalid config in {}: {}", path.display(), e))?,
+    };
+    // Hugo's keys are case-insensitive, `baseURL` and `baseurl` alike.
+    let config = match generator {
+        Generator::Hugo => lowercase_keys(config),
+        _ => config,
+    };
+    let string = |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_string);
+    // An author is a name or an object with one.
+    let author = |value: Option<&Value>| {
+        string(value).or_else(|| string(value.and_then(|author| author.get("name"))))
+    };
+
+    let mut settings = Settings {
+        title: string(config.get("title")),
+        ..Settings::default()
+    };
+    match generator {
+        Generator::Hugo => {
+            settings.base_url = string(config.get("baseurl"));
+            settings.author =
+                author(config.get("author")).or_else(|| author(config.pointer("/params/author")));
+            settings.paginate = config
+                .get("paginate")
+                .or(config.pointer("/pagination/pagersize"))
+                .and_then(Value::as_i64);
+            let ugly = config.get("uglyurls").and_then(Value::as_bool) == Some(true);
+            let (permalink, title_slugs) = hugo_permalink(&path, &config, ugly);
+            settings.permalink = Some(permalink);
+            settings.title_slugs = title_slugs;
+            settings.taxonomies = match config.get("taxonomies").and_then(Value::as_object) {
+                Some(taxonomies) => taxonomies
+                    .values()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect(),
+                None => vec!["categories".to_string()],
+            };
+        }
+        Generator::Jekyll => {
+            let url = string(config.get("url")).unwrap_or_default();
+            let base_url = string(config.get("baseurl")).unwrap_or_default();
+            settings.base_url = Some(format!("{}{}", url.trim_end_matches('/'), base_url))
+                .filter(|base_url| !base_url.is_empty());
+            settings.author = author(config.get("author"));
+            settings.paginate = config.get("paginate").and_then(Value::as_i64);
+            let pattern = string(config.get("permalink")).unwrap_or("date".to_string());
+            settings.permalink = jekyll_permalink(&path, &pattern);
+            settings.taxonomies = vec!["categories".to_string()];
+            settings.exclude = config
+                .get("exclude")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .map(|entry| entry.trim_matches('/').to_string())
+                .collect();
+        }
+        Generator::Zola => {
+            settings.base_url = string(config.get("base_url"));
+            settings.author =
+                string(config.get("author")).or_else(|| author(config.pointer("/extra/author")));
+            // Zola publishes every page under its section's path.
+            settings.permalink = Some("/{dir}/{slug}/".to_string());
+            settings.taxonomies = config
+                .get("taxonomies")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|taxonomy| taxonomy.get("name")?.as_str())
+                .map(str::to_string)
+                .collect();
+        }
+    }
+    settings.taxonomies.retain(|taxonomy| taxonomy != TAGS);
+    Ok(settings)
+}
+
+/// `value` with the keys of its objects lowercased, all the way down.
+fn lowercase_keys(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key.to_lowercase(), lowercase_keys(value)))
+                .collect(),
+        ),
+        Value::Array(values) => values.into_iter().map(lowercase_keys).collect(),
+        value => value,
+    }
+}
+
+/// The permalink of a Hugo site's posts, from `[permalinks]`, and whether it
+/// names them by their title. Hugo sets one per section, so the one for
+/// `posts` is used, or else the first.
+fn hugo_permalink(path: &Path, config: &Value, ugly: bool) -> (String, bool) {
+    let default = match ugly {
+        true => "/{dir}/{slug}.html",
+        false => "/{dir}/{slug}/",
+    };
+    let Some(permalinks) = config.get("permalinks").and_then(Value::as_object) else {
+        return (default.to_string(), false);
+    };
+    // Hugo 0.112 nests them under the kind of page.
+    let permalinks = match permalinks.get("page").and_then(Value::as_object) {
+        Some(pages) => pages,
+        None => permalinks,
+    };
+    let pattern = ["posts", "post", "blog"]
+        .iter()
+        .find_map(|section| permalinks.get(*section))
+        .or(permalinks.values().next())
+        .and_then(Value::as_str);
+    let Some(pattern) = pattern else {
+        return (default.to_string(), false);
+    };
+    if permalinks.len() > 1 {
+        eprintln!(
+            "Warning: {}: ebolg has one permalink for every post, so {:?} is used for all sections",
+            path.display(),
+            pattern
+        );
+    }
+    let mut title_slugs = false;
+    let converted = convert_permalink(pattern, |placeholder| match placeholder {
+        "year" => Some("{year}"),
+        "month" => Some("{month}"),
+        "day" => Some("{day}"),
+        "section" | "sections" => Some("{dir}"),
+        "title" | "slug" => {
+            title_slugs = true;
+            Some("{slug}")
+        }
+        "filename" | "contentbasename" | "slugorfilename" | "slugorcontentbasename" => {
+            Some("{slug}")
+        }
+        _ => None,
+    });
+    match converted {
+        Ok(permalink) => (
+            match ugly || permalink.ends_with('/') || permalink.ends_with(".html") {
+                true => permalink,
+                false => format!("{}/", permalink),
+            },
+            title_slugs,
+        ),
+        Err(placeholder) => {
+            warn_permalink(path, pattern, &placeholder);
+            (default.to_string(), false)
+        }
+    }
+}
+
+/// The permalink of a Jekyll site's posts, from its `permalink`, which may
+/// be one of Jekyll's named styles. Posts are imported into `posts/`, whose
+/// name isn't part of Jekyll's URLs, and without their categories, which
+/// ebolg doesn't put in them.
+fn jekyll_permalink(path: &Path, pattern: &str) -> Option<String> {
+    let pattern = match pattern {
+        "date" => "/:categories/:year/:month/:day/:title:output_ext",
+        "pretty" => "/:categories/:year/:month/:day/:title/",
+        "ordinal" => "/:categories/:year/:y_day/:title:output_ext",
+        "weekdate" => "/:categories/:year/W:week/:short_day/:title:output_ext",
+        "none" => "/:categories/:title:output_ext",
+        pattern => pattern,
+    };
+    let converted = convert_permalink(pattern, |placeholder| match placeholder {
+        "year" => Some("{year}"),
+        "month" => Some("{month}"),
+        "day" => Some("{day}"),
+        "title" | "slug" => Some("{slug}"),
+        "categories" => Some(""),
+        "output_ext" => Some(".html"),
+        _ => None,
+    });
+    match converted {
+        Ok(permalink) if permalink.ends_with('/') || permalink.ends_with(".html") => {
+            Some(permalink)
+        }
+        Ok(permalink) => Some(format!("{}.html", permalink)),
+        Err(placeholder) => {
+            warn_permalink(path, pattern, &placeholder);
+            None
+        }
+    }
+}
+
+fn warn_permalink(path: &Path, pattern: &str, placeholder: &str) {
+    eprintln!(
+        "Warning: {}: permalink {:?} uses :{}, which ebolg has no placeholder for, so posts keep the default permalink",
+        path.display(),
+        pattern,
+        placeholder
+    );
+}
+
+/// A `:placeholder` pattern with each placeholder replaced by what `convert`
+/// gives, or the first placeholder it gives nothing for.
+fn convert_permalink<'a>(
+    pattern: &'a str,
+    mut convert: impl FnMut(&str) -> Option<&'a str>,
+) -> Result<String, String> {
+    let mut permalink = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find(':') {
+        permalink.push_str(&rest[..start]);
+        let name_len = rest[start + 1..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len() - start - 1);
+        let name = &rest[start + 1..start + 1 + name_len];
+        permalink.push_str(convert(name).ok_or_else(|| name.to_string())?);
+        rest = &rest[start + 1 + name_len..];
+    }
+    permalink.push_str(rest);
+    // Placeholders left empty, like Jekyll's `:categories`, leave doubled
+    // slashes.
+    while permalink.contains("//") {
+        permalink = permalink.replace("//", "/");
+    }
+    Ok(permalink)
+}
+
+impl Import<'_> {
+    /// Plans a Hugo or Zola site: the markdown files under `content/`, with
+    /// the page bundles holding an `index.md` flattened into a post of the
+    /// bundle's name and a directory of its files, and `static/`.
+    fn plan_content(&mut self) -> Result<(), Box<dyn Error>> {
+        let content_dir = self.site_dir.join("content");
+        if !content_dir.is_dir() {
+            return Err(format!("{} has no content directory", self.site_dir.display()).into());
+        }
+        let files = relative_files(&content_dir)?;
+        let bundles: Vec<&Path> = files
+            .iter()
+            .filter(|file| is_bundle_index(file))
+            .filter_map(|file| file.parent())
+            .filter(|bundle| !bundle.as_os_str().is_empty())
+            .collect();
+
+        for file in &files {
+            let from = content_dir.join(file);
+            if is_markdown(file) && file.file_stem() == Some(OsStr::new("_index")) {
+                eprintln!(
+                    "Skipping {}: ebolg generates its listing pages itself",
+                    from.display()
+                );
+                continue;
+            }
+            let bundle = bundles
+                .iter()
+                .filter(|bundle| file.starts_with(bundle))
+                .max_by_key(|bundle| bundle.components().count());
+            let (to, kind) = match bundle {
+                Some(bundle) if file.parent() == Some(*bundle) && is_bundle_index(file) => {
+                    let mut to = page_path(bundle.to_path_buf()).into_os_string();
+                    to.push(".md");
+                    let to = PathBuf::from(to);
+                    self.links.insert(slashed(bundle), to.clone());
+                    self.add_links(file, &to);
+                    let kind = Kind::Markdown {
+                        bundle: Some(content_dir.join(bundle)),
+                        date: None,
+                        draft: false,
+                    };
+                    (to, kind)
+                }
+                Some(bundle) => {
+                    let to =
+                        page_path(bundle.to_path_buf()).join(file.strip_prefix(bundle).unwrap());
+                    (to, Kind::Copy)
+                }
+                None if is_markdown(file) => {
+                    let to = page_path(file.with_extension("md"));
+                    self.add_links(file, &to);
+                    let kind = Kind::Markdown {
+                        bundle: None,
+                        date: None,
+                        draft: false,
+                    };
+                    (to, kind)
+                }
+                None => (page_path(file.to_path_buf()), Kind::Copy),
+            };
+            self.planned.push(Planned { from, to, kind });
+        }
+        self.plan_static()
+    }
+
+    /// Plans a Jekyll site: the posts in `_posts`, named `YYYY-MM-DD-title.md`,
+    /// and the drafts in `_drafts` go into `posts/`, markdown pages with front
+    /// matter into `pages/`, and the other files not in an `_` directory into
+    /// the static directory.
+    fn plan_jekyll(&mut self) -> Result<(), Box<dyn Error>> {
+        for (dir, draft) in [("_posts", false), ("_drafts", true)] {
+            let dir = self.site_dir.join(dir);
+            if !dir.is_dir() {
+                continue;
+            }
+            for file in relative_files(&dir)? {
+                let from = dir.join(&file);
+                if !is_markdown(&file) {
+                    eprintln!(
+                        "Skipping {}: only markdown posts are imported",
+                        from.display()
+                    );
+                    continue;
+                }
+                let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+                let (date, name) = match find_date(&stem) {
+                    Some(date) if stem.starts_with(date) => (
+                        Some(date.to_string()),
+                        stem[date.len()..].trim_start_matches('-'),
+                    ),
+                    _ if draft => (None, stem.as_ref()),
+                    _ => {
+                        eprintln!(
+                            "Skipping {}: Jekyll only publishes posts named YYYY-MM-DD-title",
+                            from.display()
+                        );
+                        continue;
+                    }
+                };
+                let mut to = Path::new(POSTS_DIR).join(file.parent().unwrap_or(Path::new("")));
+                to.push(format!("{}.md", name));
+                if !draft {
+                    self.links
+                        .insert(slashed(&file.with_extension("")), to.clone());
+                }
+                let site_path = from.strip_prefix(self.site_dir).unwrap_or(&from);
+                self.links.insert(slashed(site_path), to.clone());
+                self.planned.push(Planned {
+                    from,
+                    to,
+                    kind: Kind::Markdown {
+                        bundle: None,
+                        date,
+                        draft,
+                    },
+                });
+            }
+        }
+
+        for file in relative_files(self.site_dir)? {
+            let root = file
+                .components()
+                .next()
+                .unwrap()
+                .as_os_str()
+                .to_string_lossy();
+            if root.starts_with(['_', '.'])
+                || JEKYLL_EXCLUDED.contains(&root.as_ref())
+                || self.settings.exclude.iter().any(|entry| *entry == root)
+            {
+                continue;
+            }
+            let from = self.site_dir.join(&file);
+            // Files with front matter are pages Jekyll renders; the others
+            // are copied as they are.
+            let has_front_matter = fs::read(&from)?.starts_with(b"---");
+            if !has_front_matter {
+                self.planned.push(Planned {
+                    from,
+                    to: self.static_dir.join(&file),
+                    kind: Kind::Copy,
+                });
+            } else if !is_markdown(&file) {
+                eprintln!(
+                    "Skipping {}: Liquid templates aren't imported",
+                    from.display()
+                );
+            } else if file.with_extension("") == Path::new("index") {
+                eprintln!(
+                    "Skipping {}: ebolg generates the index page itself",
+                    from.display()
+                );
+            } else {
+                let to = Path::new(PAGES_DIR).join(file.with_extension("md"));
+                self.add_links(&file, &to);
+                self.planned.push(Planned {
+                    from,
+                    to,
+                    kind: Kind::Markdown {
+                        bundle: None,
+                        date: None,
+                        draft: false,
+                    },
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Plans copying the files of a Hugo or Zola site's `static/` into the
+    /// static directory.
+    fn plan_static(&mut self) -> Result<(), Box<dyn Error>> {
+        let dir = self.site_dir.join("static");
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        for file in relative_files(&dir)? {
+            self.planned.push(Planned {
+                from: dir.join(&file),
+                to: self.static_dir.join(file),
+                kind: Kind::Copy,
+            });
+        }
+        Ok(())
+    }
+
+    /// Records the ways links name the post at `file`, relative to the
+    /// content directory, with and without its extension.
+    fn add_links(&mut self, file: &Path, to: &Path) {
+        self.links.insert(slashed(file), to.to_path_buf());
+        self.links
+            .insert(slashed(&file.with_extension("")), to.to_path_buf());
+    }
+
+    /// The source directory path of the post a link or shortcode of the
+    /// imported site names, relative to its content directory or by its file
+    /// name, as Hugo allows.
+    fn resolve(&self, link: &str) -> Option<String> {
+        let (link, fragment) = match link.find('#') {
+            Some(i) => link.split_at(i),
+            None => (link, ""),
+        };
+        let link = link.trim_start_matches("./").trim_matches('/');
+        let found = self.links.get(link).or_else(|| {
+            let mut named = self
+                .links
+                .iter()
+                .filter(|(key, _)| key.rsplit('/').next() == Some(link));
+            match (named.next(), named.next()) {
+                (Some((_, to)), None) => Some(to),
+                _ => None,
+            }
+        })?;
+        Some(format!("/{}{}", slashed(found), fragment))
+    }
+
+    /// A post or page in ebolg's format: the front matter in YAML with ebolg's
+    /// keys, then the body with the imported site's markup rewritten.
+    fn convert(
+        &mut self,
+        planned: &Planned,
+        bundle: Option<&Path>,
+        date: Option<&str>,
+        draft: bool,
+    ) -> Result<String, Box<dyn Error>> {
+        let path = planned.from.as_path();
+        let content = fs::read_to_string(path).map_err(|source| BuildError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let front_matter_error = |e: front_matter::ParseError| BuildError::FrontMatter {
+            path: path.to_path_buf(),
+            line: e.line,
+            column: e.column,
+            message: e.message,
+        };
+        let (front_matter, body) =
+            match front_matter::split(&content).map_err(front_matter_error)? {
+                Some(front_matter) => (
+                    front_matter
+                        .parse::<Map<String, Value>>()
+                        .map_err(front_matter_error)?,
+                    front_matter.body,
+                ),
+                None => (Map::new(), content.trim()),
+            };
+
+        let mut metadata = self.front_matter(path, front_matter);
+        if let Some(date) = date {
+            metadata.entry("date").or_insert(Value::from(date));
+        }
+        if draft {
+            metadata.insert("draft".to_string(), Value::Bool(true));
+        }
+        if self.settings.title_slugs && !planned.to.starts_with(PAGES_DIR) {
+            if let Some(title) = metadata.get("title").and_then(Value::as_str) {
+                let slug = slug::slugify(title);
+                metadata.entry("slug").or_insert(Value::from(slug));
+            }
+        }
+        // A slug naming the post as its file does already is left out.
+        let stem = planned.to.file_stem().map(OsStr::to_string_lossy);
+        if metadata.get("slug").and_then(Value::as_str) == stem.as_deref() {
+            metadata.remove("slug");
+        }
+        for key in metadata.keys() {
+            self.used_keys.insert(key.clone());
+        }
+
+        let mut output = String::with_capacity(content.len());
+        output.push_str(&yaml_front_matter(metadata)?);
+        let imported_dir = format!("/{}", slashed(&planned.to.with_extension("")));
+        let bundle = bundle.map(|bundle| (bundle, imported_dir.as_str()));
+        output.push_str(&self.body(path, body, bundle));
+        output.push('\n');
+        Ok(output)
+    }
+
+    /// The front matter of an imported post with ebolg's keys and dates.
+    fn front_matter(
+        &mut self,
+        path: &Path,
+        front_matter: Map<String, Value>,
+    ) -> Map<String, Value> {
+        let generator = self.generator;
+        let mut metadata = Map::new();
+        // Values set when the post doesn't have the key itself.
+        let mut fallbacks = Vec::new();
+        for (key, value) in front_matter {
+            let key = match generator {
+                Generator::Hugo => key.to_lowercase(),
+                _ => key,
+            };
+            match (generator, key.as_str()) {
+                (_, "date") => {
+                    let date = date(path, &key, value);
+                    metadata.insert(key, date);
+                }
+                (Generator::Hugo, "publishdate" | "pubdate" | "published") => {
+                    fallbacks.push(("date".to_string(), date(path, &key, value)))
+                }
+                (Generator::Hugo, "lastmod")
+                | (Generator::Zola, "updated")
+                | (Generator::Jekyll, "last_modified_at" | "updated") => {
+                    metadata.insert("updated".to_string(), date(path, &key, value));
+                }
+                (_, "draft") => {
+                    let draft = value == true || value == "true";
+                    metadata.insert(key, Value::Bool(draft));
+                }
+                (Generator::Jekyll, "published") => {
+                    if value == Value::Bool(false) {
+                        metadata.insert("draft".to_string(), Value::Bool(true));
+                    }
+                }
+                (Generator::Hugo, "summary") | (Generator::Jekyll, "excerpt") => {
+                    fallbacks.push(("description".to_string(), value))
+                }
+                (Generator::Hugo, "url")
+                | (Generator::Jekyll, "permalink")
+                | (Generator::Zola, "path") => {
+                    let Some(url) = value.as_str() else {
+                        continue;
+                    };
+                    let url = url.trim_matches('/');
+                    let slug = url.rsplit('/').next().unwrap_or(url);
+                    let slug = slug.strip_suffix(".html").unwrap_or(slug);
+                    if slug != url {
+                        eprintln!(
+                            "Warning: {}: {} {:?} became the slug {:?}; the rest of the path follows `permalink`",
+                            path.display(),
+                            key,
+                            url,
+                            slug
+                        );
+                    }
+                    fallbacks.push(("slug".to_string(), Value::from(slug)));
+                }
+                (Generator::Hugo | Generator::Zola, "aliases")
+                | (Generator::Jekyll, "redirect_from") => {
+                    eprintln!(
+                        "Warning: {}: dropped {}, ebolg doesn't publish redirects",
+                        path.display(),
+                        key
+                    );
+                }
+                (Generator::Jekyll, "tags" | "categories" | "category") => {
+                    let terms = match value {
+                        Value::String(terms) => terms.split_whitespace().map(Value::from).collect(),
+                        value => value,
+                    };
+                    let key = match key.as_str() {
+                        "category" => "categories".to_string(),
+                        _ => key,
+                    };
+                    metadata.insert(key, terms);
+                }
+                (Generator::Jekyll, "layout")
+                    if value
+                        .as_str()
+                        .is_some_and(|layout| JEKYLL_DEFAULT_LAYOUTS.contains(&layout)) => {}
+                (Generator::Zola, "template") => {
+                    let layout = value.as_str().unwrap_or_default();
+                    let layout = layout.strip_suffix(".html").unwrap_or(layout);
+                    metadata.insert("layout".to_string(), Value::from(layout));
+                }
+                // Zola keeps terms and the site's own keys in tables, which
+                // ebolg reads at the top.
+                (Generator::Zola, "taxonomies" | "extra") if value.is_object() => {
+                    if let Value::Object(table) = value {
+                        fallbacks.extend(table);
+                    }
+                }
+                _ => {
+                    metadata.insert(key, value);
+                }
+            }
+        }
+        for (key, value) in fallbacks {
+            metadata.entry(key).or_insert(value);
+        }
+        for key in metadata.keys() {
+            if !METADATA_KEYS.contains(&key.as_str())
+                && key != TAGS
+                && !self.settings.taxonomies.contains(key)
+            {
+                self.extra_keys.insert(key.clone());
+            }
+        }
+        metadata
+    }
+
+    /// The body of an imported post with the generator's links to other
+    /// posts, code highlighting and shortcodes rewritten, and links to the
+    /// files of its page bundle pointed into the directory they are imported
+    /// to. Fenced code is left alone.
+    fn body(&self, path: &Path, body: &str, bundle: Option<(&Path, &str)>) -> String {
+        let mut output = String::with_capacity(body.len());
+        let mut text = String::new();
+        let mut fence: Option<String> = None;
+        for line in body.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            let marker: String = trimmed
+                .chars()
+                .take_while(|c| *c == '`' || *c == '~')
+                .collect();
+            match &fence {
+                Some(open) => {
+                    output.push_str(line);
+                    if trimmed.trim_end() == open.as_str()
+                        || (marker.starts_with(open.as_str()) && trimmed.trim_end() == marker)
+                    {
+                        fence = None;
+                    }
+                }
+                None if marker.len() >= 3
+                    && marker.chars().all(|c| c == marker.chars().next().unwrap()) =>
+                {
+                    output.push_str(&self.text(path, &text, bundle));
+                    text.clear();
+                    output.push_str(line);
+                    fence = Some(marker);
+                }
+                None => text.push_str(line),
+            }
+        }
+        output.push_str(&self.text(path, &text, bundle));
+        output.trim_end().to_string()
+    }
+
+    /// Markdown outside code blocks with the generator's markup rewritten.
+    fn text(&self, path: &Path, text: &str, bundle: Option<(&Path, &str)>) -> String {
+        let patterns = &self.patterns;
+        let link = |target: &str| match self.resolve(target) {
+            Some(resolved) => resolved,
+            None => {
+                eprintln!(
+                    "Warning: {}: no post {:?} to link to, left as it is",
+                    path.display(),
+                    target
+                );
+                target.to_string()
+            }
+        };
+        let highlight = |captures: &Captures| {
+            let mut code = String::new();
+            let lines: Vec<&str> = captures[2].lines().collect();
+            code_block(&captures[1], &lines, &mut code);
+            code.trim_end().to_string()
+        };
+        let mut text = match self.generator {
+            Generator::Hugo => {
+                let text = patterns
+                    .hugo_ref
+                    .replace_all(text, |captures: &Captures| link(&captures[1]));
+                let text = patterns.hugo_highlight.replace_all(&text, highlight);
+                patterns
+                    .hugo_shortcode
+                    .replace_all(&text, |captures: &Captures| {
+                        format!("{{{{ {}{} }}}}", &captures[1], &captures[2])
+                    })
+                    .into_owned()
+            }
+            Generator::Zola => {
+                let text = patterns.zola_link.replace_all(text, |captures: &Captures| {
+                    let fragment = captures.get(2).map_or("", |fragment| fragment.as_str());
+                    format!("]({})", link(&format!("{}{}", &captures[1], fragment)))
+                });
+                patterns
+                    .zola_shortcode
+                    .replace_all(&text, |captures: &Captures| {
+                        let mut shortcode = format!("{{{{ {}", &captures[1]);
+                        for argument in patterns.zola_argument.captures_iter(&captures[2]) {
+                            let value = &argument[2];
+                            let value = match value.strip_prefix('\'') {
+                                Some(value) => format!("\"{}\"", value.trim_end_matches('\'')),
+                                None => value.to_string(),
+                            };
+                            shortcode.push_str(&format!(" {}={}", &argument[1], value));
+                        }
+                        shortcode.push_str(" }}");
+                        shortcode
+                    })
+                    .into_owned()
+            }
+            Generator::Jekyll => {
+                let text = patterns.jekyll_highlight.replace_all(text, highlight);
+                let text = patterns
+                    .jekyll_link
+                    .replace_all(&text, |captures: &Captures| link(&captures[2]));
+                let text = patterns
+                    .jekyll_url
+                    .replace_all(&text, |captures: &Captures| {
+                        captures.get(1).map_or("", |url| url.as_str()).to_string()
+                    });
+                text.replace("{% raw %}", "").replace("{% endraw %}", "")
+            }
+        };
+
+        if let Some((bundle, imported_dir)) = bundle {
+            text = patterns
+                .relative_link
+                .replace_all(&text, |captures: &Captures| {
+                    let target = &captures[2];
+                    match bundle.join(target).is_file() {
+                        true => format!("{}{}/{}", &captures[1], imported_dir, target),
+                        false => captures[0].to_string(),
+                    }
+                })
+                .into_owned();
+        }
+
+        let leftover = match self.generator {
+            Generator::Hugo => ["{{<", "{{%"].iter().any(|open| text.contains(open)),
+            Generator::Zola => text.contains("{%"),
+            Generator::Jekyll => {
+                text.contains("{%") || text.contains("{{ site.") || text.contains("{{ page.")
+            }
+        };
+        if leftover {
+            eprintln!(
+                "Warning: {}: has shortcodes or template tags ebolg can't convert, left as they are",
+                path.display()
+            );
+        }
+        text
+    }
+}
+
+/// The front matter of an imported post in YAML, with ebolg's own keys first
+/// in the order posts usually set them, followed by a blank line. Empty when it
+/// has no keys.
+fn yaml_front_matter(mut metadata: Map<String, Value>) -> Result<String, serde_yaml::Error> {
+    if metadata.is_empty() {
+        return Ok(String::new());
+    }
+    let mut ordered = serde_yaml::Mapping::new();
+    let keys = METADATA_KEYS
+        .iter()
+        .chain([&TAGS])
+        .map(|key| key.to_string());
+    for key in keys.chain(metadata.keys().cloned().collect::<Vec<_>>()) {
+        if let Some(value) = metadata.remove(&key) {
+            ordered.insert(serde_yaml::Value::String(key), serde_yaml::to_value(value)?);
+        }
+    }
+    let yaml = serde_yaml::to_string(&ordered)?;
+    Ok(format!(
+        "---\n{}\n---\n\n",
+        yaml.trim_start_matches("---").trim()
+    ))
+}
+
+/// A date of the imported site's front matter, with the time and time zone
+/// some generators write after it left out.
+fn date(path: &Path, key: &str, value: Value) -> Value {
+    let Some(text) = value.as_str() else {
+        return value;
+    };
+    match find_date(text) {
+        Some(date) => Value::from(date),
+        None => {
+            eprintln!(
+                "Warning: {}: {} {:?} isn't a date, left as it is",
+                path.display(),
+                key,
+                text
+            );
+            value
+        }
+    }
+}
+
+/// Whether `path` is the `index.md` of a Hugo or Zola page bundle, if it is in
+/// a directory.
+fn is_bundle_index(path: &Path) -> bool {
+    is_markdown(path) && path.file_stem() == Some(OsStr::new("index"))
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|extension| MARKDOWN_EXTENSIONS.contains(&extension))
+}
+
+/// Where a post or page of a content directory goes: pages at its root into
+/// `pages/`, posts in a section into the directory of the same name.
+fn page_path(path: PathBuf) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => path,
+        _ => Path::new(PAGES_DIR).join(path),
+    }
+}
+
+/// A relative path with `/` between its components, as links write it.
+fn slashed(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
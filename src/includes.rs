@@ -0,0 +1,342 @@
+use crate::error::BuildError;
+use crate::math::{code_span_end, fenced_code_end};
+use crate::remote;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory at the source root for markdown that is only included into
+/// posts. It isn't built on its own.
+pub const SNIPPETS_DIR: &str = "snippets";
+
+/// Replaces every `{{ include "snippets/disclaimer.md" }}` outside code in the
+/// post at `path` with the contents of that file, resolved against
+/// `source_dir`. Included files can include others; a file including itself,
+/// directly or not, is an error. `{{ code "examples/demo.rs" lines=10..30 }}`
+/// is replaced with a fenced code block of the file, or of those lines of it.
+/// Either can name an `https://` URL instead, fetched with `remote::fetch`.
+/// Also returns the canonical paths of the local files read, so `watch` can
+/// render the post again when one of them changes.
+pub fn expand_includes(
+    markdown_content: &str,
+    source_dir: &Path,
+    path: &Path,
+) -> Result<(String, Vec<PathBuf>), BuildError> {
+    let mut stack = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+    let mut included = Vec::new();
+    let expanded = expand(
+        markdown_content,
+        source_dir,
+        path,
+        &mut stack,
+        &mut included,
+    )?;
+    Ok((expanded, included))
+}
+
+/// `stack` holds the files being included, outermost first, and `included`
+/// collects every local file read.
+fn expand(
+    markdown_content: &str,
+    source_dir: &Path,
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut Vec<PathBuf>,
+) -> Result<String, BuildError> {
+    if !markdown_content.contains("{{") {
+        return Ok(markdown_content.to_string());
+    }
+
+    let mut output = String::with_capacity(markdown_content.len());
+    let mut i = 0;
+    let mut line_start = true;
+    while i < markdown_content.len() {
+        let rest = &markdown_content[i..];
+        if line_start {
+            if let Some(end) = fenced_code_end(rest) {
+                output.push_str(&rest[..end]);
+                i += end;
+                continue;
+            }
+        }
+
+        let next = rest.chars().next().unwrap();
+        line_start = next == '\n';
+        if next == '`' {
+            let end = code_span_end(rest);
+            output.push_str(&rest[..end]);
+            i += end;
+            continue;
+        }
+        if let Some((directive, len)) = parse(rest) {
+            let expanded = match directive {
+                Directive::Include(include) => {
+                    read_include(include, source_dir, path, stack, included)?
+                }
+                Directive::Code(file, options) => {
+                    // A block in a list item is indented like the directive.
+                    let line = output.rsplit('\n').next().unwrap_or("");
+                    let indent = match line.trim().is_empty() {
+                        true => line.to_string(),
+                        false => String::new(),
+                    };
+                    read_code(file, &options, &indent, source_dir, path, included)?
+                }
+            };
+            output.push_str(&expanded);
+            i += len;
+            continue;
+        }
+        output.push(next);
+        i += next.len_utf8();
+    }
+
+    Ok(output)
+}
+
+/// A directive inside `{{ }}`.
+enum Directive<'a> {
+    /// `include "path"`, replaced with the file's markdown.
+    Include(&'a str),
+    /// `code "path"`, replaced with a code block of the file, and its
+    /// `name=value` options.
+    Code(&'a str, Vec<(&'a str, &'a str)>),
+}
+
+/// The directive at the start of `text` and its length.
+fn parse(text: &str) -> Option<(Directive<'_>, usize)> {
+    let rest = text.strip_prefix("{{")?.trim_start_matches(' ');
+    let (name, rest) = ["include", "code"]
+        .into_iter()
+        .find_map(|name| Some((name, rest.strip_prefix(name)?)))?;
+    let quoted = rest.trim_start_matches(' ');
+    if quoted.len() == rest.len() {
+        return None;
+    }
+    let (file, mut after) = quoted.strip_prefix('"')?.split_once('"')?;
+    if file.is_empty() || file.contains('\n') {
+        return None;
+    }
+
+    let mut options = Vec::new();
+    loop {
+        after = after.trim_start_matches(' ');
+        if let Some(end) = after.strip_prefix("}}") {
+            after = end;
+            break;
+        }
+        if name == "include" {
+            return None;
+        }
+        let (option, value) = after.split_once('=')?;
+        let (value, rest) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"')?,
+            None => value.split_at(value.find([' ', '}']).unwrap_or(value.len())),
+        };
+        if option.is_empty() || option.contains(char::is_whitespace) || value.contains('\n') {
+            return None;
+        }
+        options.push((option, value));
+        after = rest;
+    }
+
+    let directive = match name {
+        "include" => Directive::Include(file),
+        _ => Directive::Code(file, options),
+    };
+    Some((directive, text.len() - after.len()))
+}
+
+fn read_include(
+    include: &str,
+    source_dir: &Path,
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut Vec<PathBuf>,
+) -> Result<String, BuildError> {
+    let include_error = |reason: String| BuildError::Include {
+        path: path.to_path_buf(),
+        include: include.to_string(),
+        reason,
+    };
+
+    let include_path = source_dir.join(include);
+    let canonical = match remote::is_remote(include) {
+        true => PathBuf::from(include),
+        false => include_path
+            .canonicalize()
+            .map_err(|_| include_error("no such file".to_string()))?,
+    };
+    if !remote::is_remote(include) && !is_inside(&canonical, source_dir) {
+        return Err(include_error(
+            "the file is outside the source directory".to_string(),
+        ));
+    }
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain([&canonical])
+            .map(|file| {
+                file.strip_prefix(source_dir.canonicalize().unwrap_or_default())
+                    .unwrap_or(file)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        return Err(include_error(format!(
+            "include cycle {}",
+            chain.join(" -> ")
+        )));
+    }
+    let content = match remote::is_remote(include) {
+        true => remote::fetch(include, source_dir).map_err(include_error)?,
+        false => fs::read_to_string(&include_path).map_err(|source| BuildError::Read {
+            path: include_path.clone(),
+            source,
+        })?,
+    };
+    crate::verbose!("Including {:?} in {:?}", canonical, path);
+    if !remote::is_remote(include) {
+        included.push(canonical.clone());
+    }
+
+    stack.push(canonical);
+    let expanded = expand(content.trim_end(), source_dir, path, stack, included)?;
+    stack.pop();
+    Ok(expanded)
+}
+
+/// Whether the canonical path `path` is in `source_dir`, so an include like
+/// `/etc/passwd` or a code block of `../../.ssh/config` can't publish files
+/// from elsewhere.
+fn is_inside(path: &Path, source_dir: &Path) -> bool {
+    source_dir
+        .canonicalize()
+        .is_ok_and(|source_dir| path.starts_with(source_dir))
+}
+
+/// A fenced code block of the file `file`, or of the lines its `lines` option
+/// selects, in the language its `lang` option or extension names and with its
+/// `title` option as caption. Lines after the first are prefixed with `indent`.
+fn read_code(
+    file: &str,
+    options: &[(&str, &str)],
+    indent: &str,
+    source_dir: &Path,
+    path: &Path,
+    included: &mut Vec<PathBuf>,
+) -> Result<String, BuildError> {
+    let code_error = |reason: String| BuildError::Include {
+        path: path.to_path_buf(),
+        include: file.to_string(),
+        reason,
+    };
+
+    let code_path = match remote::is_remote(file) {
+        true => PathBuf::from(file),
+        false => source_dir.join(file),
+    };
+    if !remote::is_remote(file) {
+        let canonical = code_path
+            .canonicalize()
+            .map_err(|_| code_error("no such file".to_string()))?;
+        if !is_inside(&canonical, source_dir) {
+            return Err(code_error(
+                "the file is outside the source directory".to_string(),
+            ));
+        }
+        included.push(canonical);
+    }
+    let content = match remote::is_remote(file) {
+        true => remote::fetch(file, source_dir).map_err(code_error)?,
+        false => fs::read_to_string(&code_path).map_err(|source| match source.kind() {
+            std::io::ErrorKind::NotFound => code_error("no such file".to_string()),
+            _ => BuildError::Read {
+                path: code_path.clone(),
+                source,
+            },
+        })?,
+    };
+    let mut lang = match remote::is_remote(file) {
+        true => remote::extension(file).unwrap_or_default().to_string(),
+        false => Path::new(file)
+            .extension()
+            .map_or(String::new(), |extension| {
+                extension.to_string_lossy().into_owned()
+            }),
+    };
+    let mut title = None;
+    let mut selected: Vec<&str> = content.lines().collect();
+    for &(option, value) in options {
+        match option {
+            "lang" => lang = value.to_string(),
+            "title" => title = Some(value),
+            "lines" => {
+                let Some((start, end)) = parse_lines(value) else {
+                    return Err(code_error(format!(
+                        "invalid line range {:?}, expected one like 10..30",
+                        value
+                    )));
+                };
+                let count = selected.len();
+                if start > count {
+                    return Err(code_error(format!(
+                        "lines {} are past the end of the file, which has {}",
+                        value, count
+                    )));
+                }
+                let end = end.unwrap_or(count).min(count);
+                if start > end {
+                    return Err(code_error(format!("invalid line range {:?}", value)));
+                }
+                selected = selected[start - 1..end].to_vec();
+            }
+            _ => {
+                return Err(code_error(format!(
+                    "unknown option {:?}, expected one of: lines, lang, title",
+                    option
+                )))
+            }
+        }
+    }
+    crate::verbose!("Including {:?} as code in {:?}", code_path, path);
+
+    // Longer than any run of backticks in the code, so none of them closes it.
+    let ticks = selected
+        .iter()
+        .flat_map(|line| line.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(ticks.max(2) + 1);
+    let mut block = format!("{}{}", fence, lang);
+    if let Some(title) = title {
+        block.push_str(&format!(" title=\"{}\"", title));
+    }
+    for line in selected {
+        block.push('\n');
+        block.push_str(indent);
+        block.push_str(line);
+    }
+    block.push('\n');
+    block.push_str(indent);
+    block.push_str(&fence);
+    Ok(block)
+}
+
+/// The first and last line, counted from 1, of a range like `10..30`, `10..`,
+/// `..30` or `12`. An open end is `None`.
+fn parse_lines(range: &str) -> Option<(usize, Option<usize>)> {
+    let (start, end) = match range.split_once("..") {
+        Some((start, end)) => (start, end),
+        None => (range, range),
+    };
+    let start = match start {
+        "" => 1,
+        start => start.parse().ok().filter(|start| *start > 0)?,
+    };
+    let end = match end {
+        "" => None,
+        end => Some(end.parse().ok()?),
+    };
+    Some((start, end))
+}
@@ -0,0 +1,100 @@
+use crate::config::Config;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A version of a post or page in another language, for the language switcher.
+#[derive(Debug, Clone, Serialize)]
+pub struct Translation {
+    pub lang: String,
+    /// The language's configured name, or its code.
+    pub name: String,
+    pub url: String,
+}
+
+impl Translation {
+    pub fn new(config: &Config, lang: &str, url: &str) -> Self {
+        Translation {
+            lang: lang.to_string(),
+            name: language_name(config, lang),
+            url: url.to_string(),
+        }
+    }
+}
+
+/// The language of the file at `path`: the one whose code ends its file stem,
+/// like `fr` for `hello.fr.md`, or else the default language.
+pub fn file_language<'a>(config: &'a Config, path: &Path) -> &'a str {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let suffix = stem.rsplit_once('.').map(|(_, suffix)| suffix);
+    match suffix.and_then(|suffix| config.languages.get_key_value(suffix)) {
+        Some((lang, _)) => lang,
+        None => &config.default_language,
+    }
+}
+
+/// The file stem of `path` without the suffix naming `lang`, so `hello.fr.md`
+/// is linked and titled like `hello.md`.
+pub fn base_stem(path: &Path, lang: &str) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match stem.strip_suffix(&format!(".{}", lang)) {
+        Some(base) if !base.is_empty() => base.to_string(),
+        _ => stem.to_string(),
+    }
+}
+
+/// `path` as written in the default language, which every translation of a
+/// post shares. It always ends in `.md`, so translations may be written in
+/// other formats than the original.
+pub fn base_path(path: &Path, lang: &str) -> PathBuf {
+    path.with_file_name(format!("{}.md", base_stem(path, lang)))
+}
+
+/// Site-relative directory holding the pages in `lang`, starting and ending
+/// with `/`: the root for the default language, `/<lang>/` for the others.
+pub fn language_dir(config: &Config, lang: &str) -> String {
+    if lang == config.default_language {
+        String::from("/")
+    } else {
+        format!("/{}/", lang)
+    }
+}
+
+/// Every language of the site, the default one first and the others by code.
+pub fn site_languages(config: &Config) -> Vec<&str> {
+    let others = config
+        .languages
+        .keys()
+        .map(String::as_str)
+        .filter(|lang| *lang != config.default_language);
+    std::iter::once(config.default_language.as_str())
+        .chain(others)
+        .collect()
+}
+
+/// Position of `lang` in `site_languages`, which posts are grouped by.
+pub fn language_rank(config: &Config, lang: &str) -> usize {
+    if lang == config.default_language {
+        return 0;
+    }
+    config
+        .languages
+        .keys()
+        .filter(|other| **other != config.default_language)
+        .position(|other| other == lang)
+        .map_or(usize::MAX, |i| i + 1)
+}
+
+pub fn language_name(config: &Config, lang: &str) -> String {
+    match config.languages.get(lang) {
+        Some(language) if !language.name.is_empty() => language.name.clone(),
+        _ => lang.to_string(),
+    }
+}
+
+/// Site title in `lang`, falling back to the configured `title`.
+pub fn language_title<'a>(config: &'a Config, lang: &str) -> &'a str {
+    match config.languages.get(lang) {
+        Some(language) if !language.title.is_empty() => &language.title,
+        _ => &config.title,
+    }
+}
@@ -0,0 +1,64 @@
+//! Generate tailwind styled html from markdown files.
+//!
+//! [`Site`] loads a source directory and builds it into an output directory;
+//! [`build_file`] renders a single post on its own.
+
+mod archive;
+#[cfg(feature = "asciidoc")]
+mod asciidoc;
+mod authors;
+mod cache;
+pub mod clean;
+#[cfg(feature = "cms")]
+mod cms;
+mod compress;
+pub mod config;
+mod containers;
+mod data;
+mod definitions;
+pub mod deploy;
+mod dry_run;
+mod emoji;
+pub mod error;
+mod exports;
+mod feed;
+mod filter;
+mod fingerprint;
+mod formats;
+mod front_matter;
+mod git;
+mod html;
+mod i18n;
+mod images;
+pub mod import;
+mod includes;
+mod languages;
+mod links;
+mod markdown;
+mod math;
+pub mod new;
+mod notebook;
+mod org;
+mod output;
+mod paginate;
+pub mod post;
+mod remote;
+mod rst;
+mod sanitize;
+mod search;
+pub mod serve;
+mod shortcodes;
+pub mod site;
+mod sitemap;
+mod tags;
+mod tailwind;
+mod templates;
+mod toc;
+mod typography;
+pub mod verbose;
+pub mod watch;
+
+pub use config::Config;
+pub use error::BuildError;
+pub use post::Post;
+pub use site::{build_file, check, render_post, BuildOptions, Related, Site};
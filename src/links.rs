@@ -0,0 +1,308 @@
+use crate::config::ExternalLinksConfig;
+use crate::error::BuildError;
+use crate::formats::SOURCE_FORMATS;
+use crate::markdown::append_class;
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// How long to wait for an external site before reporting its link as broken.
+pub const EXTERNAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A link found in a generated page.
+struct Link {
+    href: String,
+    /// The page it was found in.
+    page: PathBuf,
+}
+
+/// Checks every `href` and `src` in the HTML files of `output_dir`. Relative
+/// and site-relative links must lead to a file in the output directory; with
+/// `check_external`, links to other sites must answer with a success status.
+/// Links under `base_url` count as site-relative.
+///
+/// `sources` maps the pages rendered from posts to their markdown file, so a
+/// broken link is reported at its line in the post when it is written there
+/// as-is, and at its line in the page otherwise.
+pub fn check_links(
+    output_dir: &Path,
+    html_files: &[PathBuf],
+    sources: &HashMap<PathBuf, PathBuf>,
+    base_url: &str,
+    check_external: bool,
+) -> Vec<BuildError> {
+    let links: Vec<Link> = html_files
+        .par_iter()
+        .flat_map_iter(|page| {
+            let html_content = fs::read_to_string(page).unwrap_or_default();
+            page_links(&html_content)
+                .into_iter()
+                .map(|href| Link {
+                    href,
+                    page: page.clone(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut broken: Vec<(&Link, String)> = links
+        .par_iter()
+        .filter_map(|link| {
+            let path = site_path(&link.href, base_url)?;
+            let target = resolve(output_dir, &link.page, path);
+            (!target.is_file()).then(|| (link, String::from("no such file")))
+        })
+        .collect();
+
+    if check_external {
+        let mut external: BTreeMap<&str, Vec<&Link>> = BTreeMap::new();
+        for link in &links {
+            if is_external(&link.href) && site_path(&link.href, base_url).is_none() {
+                external.entry(&link.href).or_default().push(link);
+            }
+        }
+        let agent = ureq::AgentBuilder::new().timeout(EXTERNAL_TIMEOUT).build();
+        let failed: Vec<(&Vec<&Link>, String)> = external
+            .par_iter()
+            .filter_map(|(url, links)| check_url(&agent, url).err().map(|reason| (links, reason)))
+            .collect();
+        for (links, reason) in failed {
+            broken.extend(links.iter().map(|link| (*link, reason.clone())));
+        }
+    }
+
+    // A broken link in a template is on every page, so it is reported once.
+    let mut errors = Vec::new();
+    let mut template_links: BTreeMap<&str, (BuildError, usize)> = BTreeMap::new();
+    for (link, reason) in broken {
+        let (path, line) = locate(link, sources);
+        let in_page = path == link.page;
+        let error = BuildError::BrokenLink {
+            path,
+            line,
+            href: link.href.clone(),
+            reason,
+        };
+        if in_page {
+            template_links.entry(&link.href).or_insert((error, 0)).1 += 1;
+        } else {
+            errors.push(error);
+        }
+    }
+    for (mut error, pages) in template_links.into_values() {
+        if let BuildError::BrokenLink { reason, .. } = &mut error {
+            if pages > 1 {
+                reason.push_str(&format!(" (on {} pages)", pages));
+            }
+        }
+        errors.push(error);
+    }
+    errors
+}
+
+/// Points links to the source files of other posts, like
+/// `../notes/other-post.md#setup` or `setup.org`, at the pages rendered from
+/// them. `urls` maps the `normalize`d source path of every post to its URL.
+/// Links are resolved from the directory of `post_path`, or from `source_dir`
+/// when they start with `/`; links to files that aren't posts are left alone.
+pub fn rewrite_post_links(
+    html_content: &str,
+    post_path: &Path,
+    source_dir: &Path,
+    urls: &HashMap<PathBuf, String>,
+) -> String {
+    let is_source_link = |path: &str| {
+        let extension = path.rsplit_once('.').map_or("", |(_, extension)| extension);
+        SOURCE_FORMATS.contains(&extension.to_ascii_lowercase().as_str())
+    };
+    if !SOURCE_FORMATS
+        .iter()
+        .any(|format| html_content.contains(&format!(".{}", format)))
+    {
+        return html_content.to_string();
+    }
+    let post_dir = post_path.parent().unwrap_or(source_dir);
+    let settings =
+        RewriteStrSettings::new().append_element_content_handler(element!("a[href]", |el| {
+            let href = el.get_attribute("href").unwrap_or_default();
+            let Some(path) = site_path(&href, "") else {
+                return Ok(());
+            };
+            if !is_source_link(path) {
+                return Ok(());
+            }
+            let decoded = percent_decode(path);
+            let target = match decoded.strip_prefix('/') {
+                Some(relative) => source_dir.join(relative),
+                None => post_dir.join(&decoded),
+            };
+            if let Some(url) = urls.get(&normalize(&target)) {
+                let fragment = href.find('#').map_or("", |i| &href[i..]);
+                el.set_attribute("href", &format!("{}{}", url, fragment))?;
+            }
+            Ok(())
+        }));
+    // The handler only fails to set an attribute named `href`, which it can't.
+    rewrite_str(html_content, settings).unwrap_or_else(|_| html_content.to_string())
+}
+
+/// Marks up the links to other sites in a post as `[external_links]` asks.
+/// Links under `base_url` lead within the site, however they are written.
+pub fn decorate_external_links(
+    html_content: &str,
+    base_url: &str,
+    config: &ExternalLinksConfig,
+) -> String {
+    if !config.new_tab && config.class.is_empty() {
+        return html_content.to_string();
+    }
+    let settings =
+        RewriteStrSettings::new().append_element_content_handler(element!("a[href]", |el| {
+            let href = el.get_attribute("href").unwrap_or_default();
+            if !is_external(&href) || site_path(&href, base_url).is_some() {
+                return Ok(());
+            }
+            if config.new_tab && !el.has_attribute("target") {
+                el.set_attribute("target", "_blank")?;
+                let mut rel = el.get_attribute("rel").unwrap_or_default();
+                for value in ["noopener", "noreferrer"] {
+                    if !rel.split_whitespace().any(|existing| existing == value) {
+                        rel = format!("{} {}", rel, value).trim_start().to_string();
+                    }
+                }
+                el.set_attribute("rel", &rel)?;
+            }
+            if !config.class.is_empty() {
+                append_class(el, &config.class)?;
+            }
+            Ok(())
+        }));
+    // The handler only fails to set attributes with valid names, which it can't.
+    rewrite_str(html_content, settings).unwrap_or_else(|_| html_content.to_string())
+}
+
+/// Resolves `.` and `..` in a path without touching the file system, so the
+/// same file is found however a link spells it.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Values of every `href` and `src` attribute in a page.
+fn page_links(html_content: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let settings =
+        RewriteStrSettings::new().append_element_content_handler(element!("[href], [src]", |el| {
+            links.extend(el.get_attribute("href"));
+            links.extend(el.get_attribute("src"));
+            Ok(())
+        }));
+    // Only the handler above could fail, and it doesn't.
+    let _ = rewrite_str(html_content, settings);
+    links
+}
+
+fn is_external(href: &str) -> bool {
+    href.starts_with("http://") || href.starts_with("https://") || href.starts_with("//")
+}
+
+/// The path a link points to within this site, without its query or fragment,
+/// or `None` for links elsewhere, like other sites, `mailto:` or `#section`.
+pub fn site_path<'a>(href: &'a str, base_url: &str) -> Option<&'a str> {
+    let base_url = base_url.trim_end_matches('/');
+    let href = match href.strip_prefix(base_url) {
+        Some("") if !base_url.is_empty() => "/",
+        Some(path) if !base_url.is_empty() && path.starts_with('/') => path,
+        _ => href,
+    };
+    if href.starts_with('#') || href.starts_with("//") || href.contains(':') {
+        return None;
+    }
+    let end = href.find(['?', '#']).unwrap_or(href.len());
+    Some(&href[..end]).filter(|path| !path.is_empty())
+}
+
+/// The file a site path from `page` leads to. Paths to a directory lead to
+/// its `index.html`.
+fn resolve(output_dir: &Path, page: &Path, path: &str) -> PathBuf {
+    let decoded = percent_decode(path);
+    let mut target = match decoded.strip_prefix('/') {
+        Some(relative) => output_dir.join(relative),
+        None => page.parent().unwrap_or(output_dir).join(&decoded),
+    };
+    if decoded.ends_with('/') || target.is_dir() {
+        target.push("index.html");
+    }
+    target
+}
+
+pub fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Asks for the headers of an external page, falling back to a full request
+/// for servers that don't answer `HEAD`.
+fn check_url(agent: &ureq::Agent, url: &str) -> Result<(), String> {
+    let url = match url.strip_prefix("//") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    match agent.head(&url).call() {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(405, _)) => agent.get(&url).call().map(|_| ()).map_err(describe),
+        Err(e) => Err(describe(e)),
+    }
+}
+
+pub fn describe(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(code, response) => {
+            format!("{} {}", code, response.status_text())
+        }
+        ureq::Error::Transport(transport) => transport.to_string(),
+    }
+}
+
+/// File and line to report a broken link at: the post it was written in, if
+/// it appears there, or else the generated page.
+fn locate(link: &Link, sources: &HashMap<PathBuf, PathBuf>) -> (PathBuf, usize) {
+    let candidates = sources.get(&link.page).into_iter().chain([&link.page]);
+    for path in candidates {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        if let Some(offset) = content.find(&link.href) {
+            return (path.clone(), content[..offset].matches('\n').count() + 1);
+        }
+    }
+    (link.page.clone(), 1)
+}
@@ -1,183 +1,310 @@
-use pulldown_cmark::{html, Options, Parser};
-use serde::Deserialize;
-use serde_yaml::{self};
-use std::env;
+use chrono::{DateTime, NaiveDate};
+use clap::{Args, Parser, Subcommand};
+use ebolg::clean::clean;
+use ebolg::config::CONFIG_FILE;
+use ebolg::deploy::{deploy, deploy_target};
+use ebolg::import::import;
+use ebolg::new::new_post;
+use ebolg::serve::{serve, DEFAULT_PORT};
+use ebolg::watch::watch;
+use ebolg::{build_file, check, verbose, BuildOptions, Config, Site};
+use std::error::Error;
 use std::path::{Path, PathBuf};
-use std::{error::Error, fs};
+use std::process::ExitCode;
 
-#[derive(Debug, Deserialize, Clone)]
-struct Metadata {
-    title: String,
+/// Generate tailwind styled html from markdown files.
+///
+/// `ebolg <SOURCE> [OUTPUT DIRECTORY]` is shorthand for `ebolg build`.
+#[derive(Parser)]
+#[command(
+    version,
+    arg_required_else_help = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Markdown file or directory to build
+    source: Option<PathBuf>,
+
+    /// Directory to write the site to
+    #[arg(value_name = "OUTPUT DIRECTORY")]
+    output_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    global: GlobalArgs,
 }
 
-fn read_post_metadata(file_path: &Path) -> Result<(Metadata, String), Box<dyn Error>> {
-    let content = fs::read_to_string(file_path)?;
+#[derive(Args)]
+struct GlobalArgs {
+    /// Directory to write the site to [default: `output_dir` from ebolg.toml]
+    #[arg(short, long, global = true, value_name = "DIR")]
+    output: Option<PathBuf>,
 
-    let (yaml_str, content_str) = extract_yaml_and_content(&content)?;
+    /// Config file to use [default: <SOURCE>/ebolg.toml]
+    #[arg(short, long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
 
-    let metadata: Metadata = serde_yaml::from_str(&yaml_str)?;
-    println!("\n-------------------");
-    println!("Metadata: {:?}", metadata);
-    println!("Content snippet: {}", &content[..content.len().min(100)]);
+    /// Print every file as it is processed
+    #[arg(short, long, global = true)]
+    verbose: bool,
 
-    Ok((metadata, content_str))
-}
+    /// Include posts marked `draft: true`
+    #[arg(long, global = true)]
+    drafts: bool,
 
-fn extract_yaml_and_content(content: &str) -> Result<(String, String), Box<dyn Error>> {
-    // Splitting the content based on the starting and ending triple-dashed lines of YAML front matter
-    let parts: Vec<&str> = content.splitn(3, "---").collect();
-    if parts.len() < 3 {
-        return Err("Failed to split content".into());
-    }
-    let yaml_str = parts[1].trim(); // The YAML content should be the second part
-    let content_str = parts[2].trim(); // The remaining content starts after the second triple-dashed line
+    /// Include posts dated after today
+    #[arg(long, global = true)]
+    future: bool,
 
-    Ok((yaml_str.to_string(), content_str.to_string()))
-}
+    /// Build every post that can be built, then list the ones that failed
+    #[arg(short, long, global = true)]
+    keep_going: bool,
 
-fn add_tailwind_classes(html_content: &str) -> String {
-    html_content
-        .replace("<h1>", r#"<h1 class="text-3xl font-bold">"#)
-        .replace("<h2>", r#"<h2 class="text-2xl font-bold mb-2">"#)
-        .replace("<p>", r#"<p class="text-gray-400 mb-4">"#)
-        .replace(
-            "<pre>",
-            r#"<pre class="bg-gray-700 text-green-300 p-4 rounded mb-4 overflow-x-auto">"#,
-        )
-        .replace("<code>", r#"<code class="inline-block">"#)
-        .replace(
-            "<code class=\"inline-block\">",
-            r#"<code class="inline-block bg-gray-700 text-green-300 p-1 rounded">"#,
-        )
-}
+    /// Treat warnings, such as images without alt text or unknown front-matter keys, as errors
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Also check that links to other sites still work
+    #[arg(long, global = true)]
+    check_external: bool,
+
+    /// List the files a build, clean or import would create, update or delete,
+    /// without touching the output directory
+    #[arg(short = 'n', long, global = true)]
+    dry_run: bool,
+
+    /// Delete files in the output directory that the build didn't produce, such
+    /// as pages of renamed or deleted posts
+    #[arg(long, global = true)]
+    prune: bool,
 
-fn generate_html_header(
-    title: &str,
-    prev_post: Option<&Metadata>,
-    next_post: Option<&Metadata>,
-) -> String {
-    let prev_button = prev_post.map_or(String::new(), |_| String::from(r#"<button class="bg-green-500 hover:bg-green-600 text-white font-bold py-2 px-4 rounded"><span>&larr; Back</span></button>"#));
-    let next_button = next_post.map_or(String::new(), |_| String::from(r#"<button class="bg-green-500 hover:bg-green-600 text-white font-bold py-2 px-4 rounded"><span>Next &rarr;</span></button>"#));
-
-    format!(
-        r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{title}</title>
-    <link rel="stylesheet" href="/style/tailwind.css">   
-</head>
-<body class="bg-gray-800 text-white">
-    <div class="container mx-auto px-4 py-8">
-        <div class="flex justify-between items-center mb-6">
-            {prev_button}
-            <h1 class="text-3xl font-bold">{title}</h1>
-            {next_button}
-        </div>
-        <article>
-"#,
-        title = title,
-        prev_button = prev_button,
-        next_button = next_button
-    )
+    /// Date posts without a `date:` as this many seconds since the Unix epoch,
+    /// instead of by their modification time
+    #[arg(long, global = true, env = "SOURCE_DATE_EPOCH", value_name = "SECONDS")]
+    source_date_epoch: Option<i64>,
+
+    /// Leave out the analytics script from ebolg.toml, as watch and serve do
+    #[arg(long, global = true)]
+    no_analytics: bool,
 }
 
-fn generate_html_footer() -> &'static str {
-    r#"</article>
-    </div>
+#[derive(Subcommand)]
+enum Command {
+    /// Build the site once
+    Build {
+        /// Markdown file or directory to build
+        #[arg(default_value = ".")]
+        source: PathBuf,
+    },
+    /// Build the site and rebuild whenever a source file changes
+    Watch {
+        /// Source directory to watch
+        #[arg(default_value = ".")]
+        source: PathBuf,
+    },
+    /// Serve the site locally, rebuilding and reloading pages on change
+    Serve {
+        /// Source directory to watch
+        #[arg(default_value = ".")]
+        source: PathBuf,
+
+        /// Port to listen on
+        #[arg(short, long, default_value_t = DEFAULT_PORT)]
+        port: u16,
+    },
+    /// Create a new post
+    New {
+        /// Slug (or title) of the post, used for its file name and title
+        slug: String,
+
+        /// Source directory to create the post in
+        #[arg(short, long, default_value = ".")]
+        source: PathBuf,
+    },
+    /// Check every post's front matter, dates, terms and permalinks without
+    /// writing the site, e.g. before a commit
+    Check {
+        /// Source directory to check
+        #[arg(default_value = ".")]
+        source: PathBuf,
+    },
+    /// Build the site and publish it to the host set under [deploy] in ebolg.toml
+    Deploy {
+        /// Source directory to build and deploy
+        #[arg(default_value = ".")]
+        source: PathBuf,
+
+        /// Host to deploy to, instead of `target` from ebolg.toml: rsync, s3,
+        /// github-pages or netlify
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+    /// Import the posts, pages and settings of a site built with another generator,
+    /// or of a WordPress or Ghost export
+    Import {
+        /// Directory of the site to import, or the export file
+        site: PathBuf,
+
+        /// What the site is built with: hugo, jekyll, zola, wordpress or ghost
+        #[arg(long, value_name = "GENERATOR")]
+        from: String,
 
-</body>
+        /// Source directory to import the site into
+        #[arg(short, long, default_value = ".")]
+        source: PathBuf,
 
-</html>"#
+        /// Address of the exported site, which Ghost exports don't record, to
+        /// download its images from
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Delete the output directory
+    Clean {
+        /// Source directory whose output should be removed
+        #[arg(default_value = ".")]
+        source: PathBuf,
+    },
 }
 
-fn convert_markdown_to_html(
-    html_path: &Path,
-    metadata: &Metadata,
-    markdown_content: &str,
-    prev_post: Option<&Metadata>,
-    next_post: Option<&Metadata>,
-) -> Result<(), Box<dyn Error>> {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    let parser = Parser::new_ext(markdown_content, options);
-
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
-
-    let styled_html_content = add_tailwind_classes(&html_output);
-
-    let header = generate_html_header(&metadata.title, prev_post, next_post);
-    let footer = generate_html_footer();
-    let complete_html = format!("{}{}{}", header, styled_html_content, footer);
-    println!("HTML content length: {}", complete_html.len());
-    println!("HTML file generated: {:?}", html_path);
-
-    if let Err(e) = fs::write(html_path, complete_html) {
-        eprintln!("Failed to write HTML to file: {}", e);
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
     }
-
-    Ok(())
 }
 
-fn process_directory(dir_path: &Path, output_dir: &Path) -> Result<(), Box<dyn Error>> {
-    fs::create_dir_all(output_dir)?;
-
-    for entry in fs::read_dir(dir_path)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            let sub_output_dir = output_dir.join(entry.file_name());
-            process_directory(&path, &sub_output_dir)?;
-        } else {
-            match path.extension().and_then(std::ffi::OsStr::to_str) {
-                Some("md") => {
-                    let (metadata, content) = read_post_metadata(&path)?;
-                    let file_stem = path.file_stem().unwrap().to_str().unwrap();
-                    let html_file_name = PathBuf::from(format!("{}.html", file_stem));
-                    let html_path = output_dir.join(html_file_name);
-
-                    convert_markdown_to_html(&html_path, &metadata, &content, None, None)?;
-                }
-                Some("css") => {
-                    let target_path = output_dir.join(path.file_name().unwrap());
-                    fs::copy(&path, &target_path)?;
-                }
-                _ => {} // Ignore other file types
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    verbose::set_verbose(cli.global.verbose);
+
+    let command = match cli.command {
+        Some(command) => command,
+        None => match cli.source {
+            Some(source) => Command::Build { source },
+            None => {
+                eprintln!("Nothing to build. Run `ebolg --help` for usage.");
+                return Ok(());
             }
+        },
+    };
+
+    let source_path = match &command {
+        Command::New { slug, source } => {
+            let post_path = new_post(source, slug)?;
+            println!("Created {:?}", post_path);
+            return Ok(());
+        }
+        Command::Import {
+            site,
+            from,
+            source,
+            url,
+        } => {
+            return import(from, site, source, url.as_deref(), cli.global.dry_run);
         }
+        Command::Build { source }
+        | Command::Watch { source }
+        | Command::Serve { source, .. }
+        | Command::Check { source }
+        | Command::Deploy { source, .. }
+        | Command::Clean { source } => source.as_path(),
+    };
+    if !source_path.exists() {
+        return Err(format!("{} does not exist", source_path.display()).into());
     }
 
-    Ok(())
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        eprintln!("Usage: {} <SOURCE> <OUTPUT DIRECTORY>", args[0]);
-        return Ok(());
+    let site_dir = if source_path.is_dir() {
+        source_path
+    } else {
+        source_path.parent().unwrap_or(Path::new("."))
+    };
+    let config_path = match &cli.global.config {
+        Some(config_path) if !config_path.is_file() => {
+            return Err(format!("Config file {} does not exist", config_path.display()).into());
+        }
+        Some(config_path) => config_path.clone(),
+        None => site_dir.join(CONFIG_FILE),
+    };
+    let config = Config::load(&config_path)?;
+    if let Command::Check { .. } = command {
+        if !source_path.is_dir() {
+            return Err("check expects a source directory".into());
+        }
+        let options = BuildOptions {
+            source_date: source_date(cli.global.source_date_epoch)?,
+            ..BuildOptions::default()
+        };
+        return check(source_path, config, options);
     }
 
-    let source_path = Path::new(&args[1]);
-    let output_dir = Path::new(&args[2]);
+    let output_dir = match (&cli.global.output, &cli.output_dir, &config.output_dir) {
+        (Some(output_dir), _, _) | (None, Some(output_dir), _) => output_dir.clone(),
+        (None, None, Some(output_dir)) => site_dir.join(output_dir),
+        (None, None, None) => {
+            return Err("No output directory given and none set in ebolg.toml".into());
+        }
+    };
+    let output_dir = output_dir.as_path();
 
-    fs::create_dir_all(&output_dir)?;
+    let options = BuildOptions {
+        drafts: cli.global.drafts,
+        future: cli.global.future,
+        keep_going: cli.global.keep_going,
+        strict: cli.global.strict,
+        check_external: cli.global.check_external,
+        dry_run: cli.global.dry_run,
+        prune: cli.global.prune,
+        source_date: source_date(cli.global.source_date_epoch)?,
+        analytics: matches!(command, Command::Build { .. } | Command::Deploy { .. })
+            && !cli.global.no_analytics,
+        pull_cms: !cli.global.dry_run,
+    };
 
-    if source_path.is_dir() {
-        process_directory(source_path, &output_dir)?;
-    } else if source_path.is_file() {
-        if source_path.extension().and_then(std::ffi::OsStr::to_str) == Some("md") {
-            let file_name = source_path.file_name().unwrap().to_str().unwrap();
-            let target_file = output_dir.join(file_name).with_extension("html");
-            let (metadata, content) = read_post_metadata(&source_path)?;
-            convert_markdown_to_html(&target_file, &metadata, &content, None, None)?;
+    match &command {
+        Command::Clean { .. } => clean(site_dir, output_dir, options.dry_run)?,
+        // Misuse fails, so scripts and CI jobs don't report success.
+        Command::Watch { .. } | Command::Serve { .. } if options.dry_run => {
+            return Err("--dry-run only applies to build, deploy and clean".into());
+        }
+        Command::Watch { .. } | Command::Serve { .. } if !source_path.is_dir() => {
+            return Err("watch and serve expect a source directory".into());
+        }
+        Command::Deploy { .. } if !source_path.is_dir() => {
+            return Err("deploy expects a source directory".into());
+        }
+        Command::Watch { .. } => watch(source_path, output_dir, &config_path, options, || {})?,
+        Command::Serve { port, .. } => {
+            serve(source_path, output_dir, &config_path, *port, options)?
+        }
+        Command::Build { .. } if source_path.is_dir() => {
+            Site::load(source_path, output_dir, config, options)?.build()?;
+        }
+        Command::Build { .. } => build_file(source_path, output_dir, &config, options)?,
+        Command::Deploy { target, .. } => {
+            let deploy_config = config.clone();
+            let target = deploy_target(&deploy_config.deploy, target.as_deref())?;
+            let dry_run = options.dry_run;
+            Site::load(source_path, output_dir, config, options)?.build()?;
+            deploy(&deploy_config, target, source_path, output_dir, dry_run)?;
+        }
+        Command::New { .. } | Command::Import { .. } | Command::Check { .. } => {
+            unreachable!("handled above")
         }
-    } else {
-        eprintln!("The path specified does not exist or is not a file/directory.");
-        return Ok(());
     }
 
     Ok(())
 }
+
+/// The date of `--source-date-epoch`, if given.
+fn source_date(seconds: Option<i64>) -> Result<Option<NaiveDate>, Box<dyn Error>> {
+    let Some(seconds) = seconds else {
+        return Ok(None);
+    };
+    let datetime = DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| format!("SOURCE_DATE_EPOCH {} is out of range", seconds))?;
+    Ok(Some(datetime.date_naive()))
+}
@@ -0,0 +1,801 @@
+use crate::config::{Config, MarkdownConfig};
+use crate::containers;
+use crate::definitions::definition_lists;
+use crate::emoji::{EmojiReplacer, EMOJI_STYLES};
+use crate::math;
+use crate::sanitize::Sanitizer;
+use crate::typography::Typographer;
+use lol_html::html_content::Element;
+use lol_html::{rewrite_str, ElementContentHandlers, HandlerResult, RewriteStrSettings, Selector};
+use pulldown_cmark::escape::escape_html;
+use pulldown_cmark::{html, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ops::RangeInclusive;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{
+    append_highlighted_html_for_styled_line, highlighted_html_for_string,
+    start_highlighted_html_snippet, IncludeBackground,
+};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// Errors from highlighting or rewriting a rendered post. They may be
+/// returned from any of the threads rendering posts in parallel.
+pub type RenderError = Box<dyn Error + Send + Sync>;
+
+/// Renders markdown to styled HTML. Syntax definitions, the code theme and the
+/// class selectors are loaded once and shared by every post.
+pub struct MarkdownRenderer {
+    syntax_set: SyntaxSet,
+    code_theme: Theme,
+    options: Options,
+    classes: Vec<(Selector, String)>,
+    /// The raw HTML allowed by `[sanitize]`, which every post goes through
+    /// when it is enabled and untrusted ones always do.
+    sanitizer: Sanitizer,
+    /// `sanitize.enabled`.
+    sanitize: bool,
+    /// Set when `[typography]` is enabled.
+    typographer: Option<Typographer>,
+    /// Set when `[emoji]` is enabled.
+    emoji: Option<EmojiReplacer>,
+    /// `code.line_numbers`.
+    line_numbers: bool,
+}
+
+impl MarkdownRenderer {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let code_theme = config.theme.code_theme.as_str();
+        let mut themes = ThemeSet::load_defaults().themes;
+        let available: Vec<String> = themes.keys().cloned().collect();
+        let code_theme = themes.remove(code_theme).ok_or_else(|| {
+            format!(
+                "Unknown code theme {:?}, expected one of: {}",
+                code_theme,
+                available.join(", ")
+            )
+        })?;
+
+        let mut classes = Vec::new();
+        for (selector, class) in &config.classes {
+            if class.is_empty() {
+                continue;
+            }
+            let parsed = selector
+                .parse()
+                .map_err(|e| format!("Invalid selector {:?} in [classes]: {}", selector, e))?;
+            classes.push((parsed, class.clone()));
+        }
+
+        if !EMOJI_STYLES.contains(&config.emoji.style.as_str()) {
+            return Err(format!(
+                "Unknown emoji style {:?}, expected one of: {}",
+                config.emoji.style,
+                EMOJI_STYLES.join(", ")
+            )
+            .into());
+        }
+
+        let mut options = markdown_options(&config.markdown);
+        if config.typography.enabled {
+            options.insert(Options::ENABLE_SMART_PUNCTUATION);
+        }
+        Ok(MarkdownRenderer {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            code_theme,
+            options,
+            classes,
+            sanitizer: Sanitizer::new(&config.sanitize),
+            sanitize: config.sanitize.enabled,
+            typographer: config
+                .typography
+                .enabled
+                .then(|| Typographer::new(&config.typography)),
+            emoji: config
+                .emoji
+                .enabled
+                .then(|| EmojiReplacer::new(&config.emoji)),
+            line_numbers: config.code.line_numbers,
+        })
+    }
+
+    /// Renders markdown to HTML, its raw HTML sanitized when `[sanitize]` is
+    /// enabled. With `math`, `$...$` and `$$...$$` spans are passed through
+    /// untouched for KaTeX to render in the browser, and `:::note` ... `:::`
+    /// containers become callout boxes. Raw HTML, math spans inside it
+    /// included, is sanitized before the renderer adds its own markup, and
+    /// typography is applied before the math spans in text are put back, so
+    /// the sanitizer keeps the renderer's markup and typography leaves math be.
+    pub fn render(&self, markdown_content: &str, math: bool) -> Result<String, RenderError> {
+        self.render_with(markdown_content, math, self.sanitize)
+    }
+
+    /// Renders like `render`, sanitizing the raw HTML whether or not
+    /// `[sanitize]` is enabled, for posts whose authors can't be trusted with
+    /// the pages, like those pulled from a CMS.
+    pub fn render_untrusted(
+        &self,
+        markdown_content: &str,
+        math: bool,
+    ) -> Result<String, RenderError> {
+        self.render_with(markdown_content, math, true)
+    }
+
+    fn render_with(
+        &self,
+        markdown_content: &str,
+        math: bool,
+        sanitize: bool,
+    ) -> Result<String, RenderError> {
+        let (markdown_content, containers) = containers::protect(markdown_content);
+        let (markdown_content, spans) = if math {
+            math::protect(&markdown_content)
+        } else {
+            (markdown_content, Vec::new())
+        };
+        let parser = Parser::new_ext(&markdown_content, self.options);
+        let parser = math::restore_html(parser, &spans);
+        let events = match sanitize {
+            true => self.sanitizer.sanitize(parser)?,
+            false => parser.collect(),
+        };
+        let events = containers::render(events.into_iter(), &containers);
+        let events = paragraph_attributes(definition_lists(events.into_iter()));
+        let events = match &self.typographer {
+            Some(typographer) => typographer.apply(events.into_iter()),
+            None => events,
+        };
+        let events = link_footnotes(anchor_headings(math::restore(events.into_iter(), &spans)));
+        // Headings have their ids by now, made from the shortcodes as written,
+        // like the table of contents'.
+        let events = match &self.emoji {
+            Some(emoji) => emoji.replace(events.into_iter()),
+            None => events,
+        };
+        let events = self.highlight_code_blocks(mermaid_blocks(events))?;
+
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, wrap_tables(events));
+
+        self.add_classes(&html_output)
+    }
+
+    /// Adds the configured classes to every element matching their selector.
+    /// This runs on the parsed HTML, so markup that only appears as text inside
+    /// code samples is left alone.
+    fn add_classes(&self, html_content: &str) -> Result<String, RenderError> {
+        let mut settings = RewriteStrSettings::new();
+        for (selector, class) in &self.classes {
+            let handler = ElementContentHandlers::default()
+                .element(|el: &mut Element| append_class(el, class));
+            settings = settings.append_element_content_handler((Cow::Borrowed(selector), handler));
+        }
+
+        Ok(rewrite_str(html_content, settings)?)
+    }
+
+    /// Replaces fenced code blocks whose info string names a known language with
+    /// syntect's highlighted HTML, and writes those with numbered or
+    /// highlighted lines or a title by hand. Other code blocks pass through
+    /// untouched.
+    fn highlight_code_blocks<'a>(
+        &self,
+        events: impl Iterator<Item = Event<'a>>,
+    ) -> Result<Vec<Event<'a>>, RenderError> {
+        let mut output = Vec::new();
+        let mut code_block: Option<CodeBlock> = None;
+
+        for event in events {
+            if let Some(block) = &mut code_block {
+                match event {
+                    Event::Text(text) => block.code.push_str(&text),
+                    Event::End(Tag::CodeBlock(_)) => {
+                        let mut html = match block.syntax {
+                            Some(syntax) => self.highlight(&block.code, syntax, &block.lines)?,
+                            None => plain_code_block(&block.code, &block.lang, &block.lines),
+                        };
+                        if let Some(title) = &block.title {
+                            html = format!(
+                                "<figure class=\"code-block\"><figcaption class=\"code-title\">{}</figcaption>\n{}</figure>\n",
+                                escape_attribute(title),
+                                html
+                            );
+                        }
+                        output.push(Event::Html(html.into()));
+                        code_block = None;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = &event {
+                let CodeInfo {
+                    lang,
+                    title,
+                    highlighted,
+                } = parse_info(info);
+                let lines = CodeLines {
+                    numbers: self.line_numbers,
+                    highlighted,
+                };
+                let syntax = self.find_syntax(lang);
+                if syntax.is_some() || !lines.is_plain() || title.is_some() {
+                    code_block = Some(CodeBlock {
+                        syntax,
+                        lang: lang.to_string(),
+                        title: title.map(str::to_string),
+                        lines,
+                        code: String::new(),
+                    });
+                    continue;
+                }
+            }
+
+            output.push(event);
+        }
+
+        Ok(output)
+    }
+
+    fn find_syntax(&self, lang: &str) -> Option<&SyntaxReference> {
+        if lang.is_empty() {
+            return None;
+        }
+        self.syntax_set.find_syntax_by_token(lang)
+    }
+
+    fn highlight(
+        &self,
+        code: &str,
+        syntax: &SyntaxReference,
+        lines: &CodeLines,
+    ) -> Result<String, RenderError> {
+        if lines.is_plain() {
+            return Ok(highlighted_html_for_string(
+                code,
+                &self.syntax_set,
+                syntax,
+                &self.code_theme,
+            )?);
+        }
+
+        let mut highlighter = HighlightLines::new(syntax, &self.code_theme);
+        let (start, background) = start_highlighted_html_snippet(&self.code_theme);
+        let mut html = start.replacen("<pre", &format!("<pre{}", lines.pre_attributes()), 1);
+        for (i, line) in LinesWithEndings::from(code).enumerate() {
+            let mut regions = highlighter.highlight_line(line, &self.syntax_set)?;
+            // The line break goes after the line's element, not inside it.
+            if let Some((_, text)) = regions.last_mut() {
+                *text = text.trim_end_matches(['\r', '\n']);
+            }
+            html.push_str(&lines.line_start(i + 1));
+            append_highlighted_html_for_styled_line(
+                &regions,
+                IncludeBackground::IfDifferent(background),
+                &mut html,
+            )?;
+            html.push_str("</span>\n");
+        }
+        html.push_str("</pre>\n");
+        Ok(html)
+    }
+}
+
+/// A fenced code block being read by `highlight_code_blocks`.
+struct CodeBlock<'s> {
+    /// Set when syntect knows the block's language.
+    syntax: Option<&'s SyntaxReference>,
+    lang: String,
+    title: Option<String>,
+    lines: CodeLines,
+    code: String,
+}
+
+/// How the lines of a code block are marked up: numbered with `code.line_numbers`,
+/// and highlighted when listed in its info string.
+struct CodeLines {
+    numbers: bool,
+    highlighted: Vec<RangeInclusive<usize>>,
+}
+
+impl CodeLines {
+    /// Whether the block's lines need no markup of their own.
+    fn is_plain(&self) -> bool {
+        !self.numbers && self.highlighted.is_empty()
+    }
+
+    fn pre_attributes(&self) -> &'static str {
+        match self.numbers {
+            true => " class=\"line-numbers\"",
+            false => "",
+        }
+    }
+
+    /// Opening tag of line `number`, counted from 1. Numbers are left to CSS,
+    /// reading `data-line`, so copying the code doesn't copy them.
+    fn line_start(&self, number: usize) -> String {
+        let class = match self.highlighted.iter().any(|range| range.contains(&number)) {
+            true => "line highlight",
+            false => "line",
+        };
+        match self.numbers {
+            true => format!("<span class=\"{}\" data-line=\"{}\">", class, number),
+            false => format!("<span class=\"{}\">", class),
+        }
+    }
+}
+
+/// A code block in a language syntect doesn't know, with its lines marked up
+/// like highlighted ones when they need it.
+fn plain_code_block(code: &str, lang: &str, lines: &CodeLines) -> String {
+    let mut html = format!("<pre{}><code", lines.pre_attributes());
+    if !lang.is_empty() {
+        html.push_str(&format!(" class=\"language-{}\"", escape_attribute(lang)));
+    }
+    html.push('>');
+    if lines.is_plain() {
+        html.push_str(&escape_attribute(code));
+        html.push_str("</code></pre>\n");
+        return html;
+    }
+    for (i, line) in code.lines().enumerate() {
+        html.push_str(&lines.line_start(i + 1));
+        html.push_str(&escape_attribute(line));
+        html.push_str("</span>\n");
+    }
+    html.push_str("</code></pre>\n");
+    html
+}
+
+/// What a fenced code block's info string, like `rust title="src/main.rs" {3-5, 8}`,
+/// says about it.
+struct CodeInfo<'a> {
+    lang: &'a str,
+    /// The file name shown above the code.
+    title: Option<&'a str>,
+    /// Lines to highlight, listed in braces.
+    highlighted: Vec<RangeInclusive<usize>>,
+}
+
+fn parse_info(info: &str) -> CodeInfo<'_> {
+    let (before, title, after) = match info.find("title=") {
+        Some(start) => {
+            let value = &info[start + "title=".len()..];
+            let (title, after) = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    value[1..].split_once(quote).unwrap_or((&value[1..], ""))
+                }
+                _ => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+            };
+            (
+                &info[..start],
+                Some(title).filter(|title| !title.is_empty()),
+                after,
+            )
+        }
+        None => (info, None, ""),
+    };
+    let ranges = match before.split_once('{') {
+        Some((_, ranges)) => ranges,
+        None => after.split_once('{').map_or("", |(_, ranges)| ranges),
+    };
+    let highlighted = ranges
+        .split('}')
+        .next()
+        .unwrap_or("")
+        .split(',')
+        .filter_map(|range| {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            Some(start.trim().parse().ok()?..=end.trim().parse().ok()?)
+        })
+        .collect();
+    let lang = before.split('{').next().unwrap_or("");
+    CodeInfo {
+        lang: lang.split_whitespace().next().unwrap_or(""),
+        title,
+        highlighted,
+    }
+}
+
+fn markdown_options(config: &MarkdownConfig) -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    options.set(Options::ENABLE_STRIKETHROUGH, config.strikethrough);
+    options.set(Options::ENABLE_TASKLISTS, config.tasklists);
+    options.set(Options::ENABLE_SMART_PUNCTUATION, config.smart_punctuation);
+    options
+}
+
+/// Options for reading titles, headings and excerpts out of a post. These run
+/// without the site config; the extensions they differ in don't change ids.
+fn default_options() -> Options {
+    markdown_options(&MarkdownConfig::default())
+}
+
+/// A heading of a post, with the anchor id its rendered element gets.
+#[derive(Debug)]
+pub struct Heading {
+    pub level: usize,
+    pub id: String,
+    pub title: String,
+}
+
+/// Every heading of a post in document order, with the same ids `render` assigns.
+pub fn headings(markdown_content: &str) -> Vec<Heading> {
+    let mut ids = HeadingIds::default();
+    let mut headings = Vec::new();
+    let mut current: Option<(HeadingLevel, Option<&str>, String)> = None;
+    for event in Parser::new_ext(markdown_content, default_options()) {
+        match (&mut current, event) {
+            (None, Event::Start(Tag::Heading(level, id, _))) => {
+                current = Some((level, id, String::new()))
+            }
+            (Some((.., title)), Event::Text(text) | Event::Code(text)) => title.push_str(&text),
+            (Some((level, id, title)), Event::End(Tag::Heading(..))) => {
+                let title = title.trim().to_string();
+                headings.push(Heading {
+                    level: *level as usize,
+                    id: ids.assign(*id, &title),
+                    title,
+                });
+                current = None;
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+/// Hands out anchor ids derived from heading text, numbering repeats
+/// (`setup`, `setup-1`, ...) so every id on a page is unique. Ids given with
+/// `{#id}` are kept as written.
+#[derive(Default)]
+struct HeadingIds {
+    seen: HashMap<String, usize>,
+}
+
+impl HeadingIds {
+    fn assign(&mut self, explicit: Option<&str>, title: &str) -> String {
+        if let Some(id) = explicit {
+            *self.seen.entry(id.to_string()).or_insert(0) += 1;
+            return id.to_string();
+        }
+        let mut base = slug::slugify(title);
+        if base.is_empty() {
+            base = String::from("section");
+        }
+        let count = self.seen.entry(base.clone()).or_insert(0);
+        let id = match *count {
+            0 => base,
+            n => format!("{}-{}", base, n),
+        };
+        *count += 1;
+        id
+    }
+}
+
+/// Writes heading tags by hand so each one carries its anchor id. Second and
+/// third level headings also get a `#` link to themselves, styled through the
+/// `a.anchor` selector in `[classes]`. Classes given with `{.class}` are kept.
+fn anchor_headings<'a>(events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+    let mut ids = HeadingIds::default();
+    let mut output = Vec::new();
+    // Start of the open heading in `output`, its attributes and its text so far.
+    let mut heading: Option<(usize, Option<&'a str>, Vec<&'a str>, String)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Heading(_, id, ref classes)) => {
+                heading = Some((output.len(), id, classes.clone(), String::new()));
+                output.push(event);
+            }
+            Event::End(Tag::Heading(level, ..)) => {
+                let linked = matches!(level, HeadingLevel::H2 | HeadingLevel::H3);
+                let mut anchor = String::new();
+                if let Some((start, id, mut classes, title)) = heading.take() {
+                    let id = escape_attribute(&ids.assign(id, title.trim()));
+                    if linked {
+                        anchor = format!(" <a href=\"#{}\" class=\"anchor\">#</a>", id);
+                        classes.insert(0, "group");
+                    }
+                    output[start] = match classes.is_empty() {
+                        true => Event::Html(format!("<{} id=\"{}\">", level, id).into()),
+                        false => Event::Html(
+                            format!(
+                                "<{} id=\"{}\" class=\"{}\">",
+                                level,
+                                id,
+                                escape_attribute(&classes.join(" "))
+                            )
+                            .into(),
+                        ),
+                    };
+                }
+                output.push(Event::Html(format!("{}</{}>\n", anchor, level).into()));
+            }
+            Event::Text(ref text) | Event::Code(ref text) => {
+                if let Some((.., title)) = &mut heading {
+                    title.push_str(text);
+                }
+                output.push(event);
+            }
+            _ => output.push(event),
+        }
+    }
+
+    output
+}
+
+/// Moves a `{#id .class}` written at the end of a paragraph onto its `<p>`, as
+/// pulldown-cmark does for headings.
+fn paragraph_attributes(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    // Start of the open paragraph in `output`.
+    let mut start: Option<usize> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::Paragraph) => start = Some(output.len()),
+            Event::End(Tag::Paragraph) => {
+                if let Some(start) = start.take() {
+                    move_attributes(&mut output, start);
+                }
+            }
+            _ => {}
+        }
+        output.push(event);
+    }
+    output
+}
+
+/// Replaces the start of the paragraph at `start` with a `<p>` carrying the
+/// attributes its last text ends with, taking them out of the text.
+fn move_attributes(output: &mut Vec<Event<'_>>, start: usize) {
+    let Some(Event::Text(text)) = output.last() else {
+        return;
+    };
+    let Some((end, html)) = paragraph_tag(text) else {
+        return;
+    };
+    let text = text[..end].trim_end().to_string();
+    // A paragraph of nothing but attributes is left as it is.
+    if text.is_empty() && output.len() == start + 2 {
+        return;
+    }
+    output.pop();
+    if !text.is_empty() {
+        output.push(Event::Text(text.into()));
+    } else if output.last() == Some(&Event::SoftBreak) {
+        output.pop();
+    }
+    output[start] = Event::Html(html.into());
+}
+
+/// The `<p>` carrying the `{#id .class}` attributes `text` ends with, and where
+/// they start in `text`.
+fn paragraph_tag(text: &str) -> Option<(usize, String)> {
+    let trimmed = text.trim_end().strip_suffix('}')?;
+    let start = trimmed.rfind('{')?;
+    let mut id = None;
+    let mut classes = Vec::new();
+    for attribute in trimmed[start + 1..].split_whitespace() {
+        let name = attribute.strip_prefix(['#', '.']).filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':'))
+        })?;
+        match attribute.starts_with('#') {
+            true => id = Some(name),
+            false => classes.push(name),
+        }
+    }
+
+    if id.is_none() && classes.is_empty() {
+        return None;
+    }
+    let mut html = String::from("<p");
+    if let Some(id) = id {
+        html.push_str(&format!(" id=\"{}\"", id));
+    }
+    if !classes.is_empty() {
+        html.push_str(&format!(" class=\"{}\"", classes.join(" ")));
+    }
+    html.push('>');
+    Some((start, html))
+}
+
+/// Writes footnote references and definitions by hand so that each definition
+/// links back to where it was referenced. Ids are prefixed with `fn-` and
+/// `fnref-` so `[^1]` can't collide with a heading's id. Footnotes are numbered
+/// in the order they first appear, as pulldown-cmark does.
+fn link_footnotes(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    let mut number = |name: &str| {
+        let next = numbers.len() + 1;
+        *numbers.entry(name.to_string()).or_insert(next)
+    };
+    let mut referenced = HashSet::new();
+
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::FootnoteReference(name) => {
+                let id = escape_attribute(&name);
+                // Only the first reference gets an id for the backlink to target.
+                let anchor = if referenced.insert(name.to_string()) {
+                    format!(" id=\"fnref-{}\"", id)
+                } else {
+                    String::new()
+                };
+                Event::Html(
+                    format!(
+                        "<sup class=\"footnote-reference\"{}><a href=\"#fn-{}\">{}</a></sup>",
+                        anchor,
+                        id,
+                        number(&name)
+                    )
+                    .into(),
+                )
+            }
+            Event::Start(Tag::FootnoteDefinition(name)) => Event::Html(
+                format!(
+                    "<div class=\"footnote-definition\" id=\"fn-{}\"><sup class=\"footnote-definition-label\">{}</sup>",
+                    escape_attribute(&name),
+                    number(&name)
+                )
+                .into(),
+            ),
+            Event::End(Tag::FootnoteDefinition(name)) => Event::Html(
+                format!(
+                    "<a href=\"#fnref-{}\" class=\"footnote-backref\">&#8617;</a></div>\n",
+                    escape_attribute(&name)
+                )
+                .into(),
+            ),
+            event => event,
+        })
+        .collect()
+}
+
+fn escape_attribute(name: &str) -> String {
+    let mut escaped = String::new();
+    // Writing to a String can't fail.
+    escape_html(&mut escaped, name).unwrap();
+    escaped
+}
+
+/// Fenced code block language whose blocks are diagrams drawn by mermaid.js.
+const MERMAID: &str = "mermaid";
+
+/// Writes ```` ```mermaid ```` blocks as `<pre class="mermaid">`, the element
+/// mermaid.js looks for, instead of as code.
+fn mermaid_blocks(events: Vec<Event<'_>>) -> impl Iterator<Item = Event<'_>> {
+    let mut in_diagram = false;
+    events.into_iter().map(move |event| match event {
+        Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info))) if is_mermaid(info) => {
+            in_diagram = true;
+            Event::Html("<pre class=\"mermaid\">".into())
+        }
+        Event::End(Tag::CodeBlock(_)) if in_diagram => {
+            in_diagram = false;
+            Event::Html("</pre>\n".into())
+        }
+        event => event,
+    })
+}
+
+/// Puts every table in a `<div class="table-wrapper">`, so wide tables can
+/// scroll sideways on small screens instead of widening the page.
+fn wrap_tables(events: Vec<Event<'_>>) -> impl Iterator<Item = Event<'_>> {
+    events.into_iter().flat_map(|event| match event {
+        Event::Start(Tag::Table(_)) => {
+            vec![Event::Html("<div class=\"table-wrapper\">\n".into()), event]
+        }
+        Event::End(Tag::Table(_)) => vec![event, Event::Html("</div>\n".into())],
+        event => vec![event],
+    })
+}
+
+fn is_mermaid(info: &str) -> bool {
+    info.split_whitespace().next() == Some(MERMAID)
+}
+
+/// Whether the post has a mermaid diagram, so its page needs the script.
+pub fn has_diagrams(markdown_content: &str) -> bool {
+    Parser::new_ext(markdown_content, default_options()).any(|event| {
+        matches!(event, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) if is_mermaid(&info))
+    })
+}
+
+/// Marks the end of a post's excerpt when placed on its own line.
+pub const MORE_MARKER: &str = "<!-- more -->";
+
+/// Plain-text excerpt of a markdown post: everything before a `<!-- more -->`
+/// marker, or the first paragraph when there is none.
+pub fn excerpt(markdown_content: &str) -> String {
+    let (markdown_content, whole) = match markdown_content.split_once(MORE_MARKER) {
+        Some((before, _)) => (before, true),
+        None => (markdown_content, false),
+    };
+
+    let markdown_content = containers::strip(markdown_content);
+    let mut text = String::new();
+    let mut in_paragraph = false;
+    for event in Parser::new_ext(&markdown_content, default_options()) {
+        match event {
+            Event::Start(Tag::Paragraph) => in_paragraph = true,
+            Event::End(Tag::Paragraph) if !whole => break,
+            Event::End(Tag::Paragraph) => {
+                in_paragraph = false;
+                text.push(' ');
+            }
+            Event::Text(fragment) | Event::Code(fragment) if in_paragraph => {
+                text.push_str(&fragment)
+            }
+            Event::SoftBreak | Event::HardBreak if in_paragraph => text.push(' '),
+            _ => {}
+        }
+    }
+
+    text.trim().to_string()
+}
+
+/// Text of a markdown post without its markup, leaving out code blocks.
+pub fn plain_text(markdown_content: &str) -> String {
+    let markdown_content = containers::strip(markdown_content);
+    let mut text = String::new();
+    let mut in_code_block = false;
+    for event in Parser::new_ext(&markdown_content, default_options()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(fragment) | Event::Code(fragment) if !in_code_block => {
+                text.push_str(&fragment)
+            }
+            // Inline markup can sit inside a word; everything else ends one.
+            Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..)) => {}
+            Event::End(_) | Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Number of words in the text of a markdown post, leaving out code blocks.
+pub fn word_count(markdown_content: &str) -> usize {
+    plain_text(markdown_content).split_whitespace().count()
+}
+
+/// Text of the first level-one heading, if the post has one.
+pub fn first_heading(markdown_content: &str) -> Option<String> {
+    let mut heading: Option<String> = None;
+    for event in Parser::new_ext(markdown_content, default_options()) {
+        match (&mut heading, event) {
+            (None, Event::Start(Tag::Heading(HeadingLevel::H1, ..))) => {
+                heading = Some(String::new())
+            }
+            (Some(text), Event::Text(fragment) | Event::Code(fragment)) => text.push_str(&fragment),
+            (Some(_), Event::End(Tag::Heading(..))) => break,
+            _ => {}
+        }
+    }
+
+    heading
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+pub fn append_class(el: &mut Element, class: &str) -> HandlerResult {
+    let class = match el.get_attribute("class") {
+        Some(existing) => format!("{} {}", existing, class),
+        None => class.to_string(),
+    };
+    el.set_attribute("class", &class)?;
+    Ok(())
+}
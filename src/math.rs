@@ -0,0 +1,186 @@
+use pulldown_cmark::Event;
+
+/// Marks the start and end of a placeholder. Both are Unicode noncharacters,
+/// which neither markdown nor real text uses.
+const OPEN: char = '\u{fdd0}';
+const CLOSE: char = '\u{fdd1}';
+
+/// Replaces every `$$...$$` and `$...$` span outside code with a placeholder so
+/// markdown can't turn `_` into emphasis or `\\` into a line break inside it.
+/// Returns the markdown to parse and the spans, delimiters included, for
+/// `restore` to put back.
+///
+/// An inline span must not start with whitespace, nor end with whitespace or
+/// before a digit, so prices like `$5 and $10` are left alone. `\$` is a
+/// literal dollar sign.
+pub fn protect(markdown_content: &str) -> (String, Vec<String>) {
+    let mut output = String::with_capacity(markdown_content.len());
+    let mut spans = Vec::new();
+    let mut i = 0;
+    let mut line_start = true;
+
+    while i < markdown_content.len() {
+        let rest = &markdown_content[i..];
+        if line_start {
+            if let Some(end) = fenced_code_end(rest) {
+                output.push_str(&rest[..end]);
+                i += end;
+                continue;
+            }
+        }
+
+        let next = rest.chars().next().unwrap();
+        line_start = next == '\n';
+        let span_len = match next {
+            '\\' => {
+                // Keeps the escaped character, which may be a `$` or a backtick.
+                let escaped = rest[1..].chars().next().map_or(0, char::len_utf8);
+                output.push_str(&rest[..1 + escaped]);
+                i += 1 + escaped;
+                continue;
+            }
+            '`' => {
+                let end = code_span_end(rest);
+                output.push_str(&rest[..end]);
+                i += end;
+                continue;
+            }
+            '$' if rest.starts_with("$$") => rest[2..].find("$$").map(|end| end + 4),
+            '$' => inline_math_end(rest),
+            _ => None,
+        };
+
+        match span_len {
+            Some(len) => {
+                output.push(OPEN);
+                output.push_str(&spans.len().to_string());
+                output.push(CLOSE);
+                spans.push(rest[..len].to_string());
+                i += len;
+            }
+            None => {
+                output.push(next);
+                i += next.len_utf8();
+            }
+        }
+    }
+
+    (output, spans)
+}
+
+/// Puts the spans taken out by `protect` back into the text they were in.
+/// Text is escaped when it is written out, and KaTeX reads the page's text,
+/// so the math reaches it unchanged. Spans in raw HTML are put back by
+/// `restore_html` instead.
+pub fn restore<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    spans: &'a [String],
+) -> impl Iterator<Item = Event<'a>> {
+    events.map(move |event| match event {
+        Event::Text(text) if text.contains(OPEN) => Event::Text(fill(&text, spans).into()),
+        event => event,
+    })
+}
+
+/// Puts the spans taken out by `protect` back into the raw HTML they were in.
+/// That HTML is written out as it is, so this must run before it is
+/// sanitized, or markup inside `$...$` would get past the sanitizer.
+pub fn restore_html<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    spans: &'a [String],
+) -> impl Iterator<Item = Event<'a>> {
+    events.map(move |event| match event {
+        Event::Html(html) if html.contains(OPEN) => Event::Html(fill(&html, spans).into()),
+        event => event,
+    })
+}
+
+fn fill(text: &str, spans: &[String]) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(OPEN) {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len_utf8()..];
+        let Some(end) = after.find(CLOSE) else {
+            break;
+        };
+        match after[..end]
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| spans.get(i))
+        {
+            Some(span) => output.push_str(span),
+            None => output.push_str(&rest[start..start + OPEN.len_utf8() + end + CLOSE.len_utf8()]),
+        }
+        rest = &after[end + CLOSE.len_utf8()..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Length of a fenced code block starting at `text`, up to and including its
+/// closing fence, or `None` if `text` doesn't open one.
+pub fn fenced_code_end(text: &str) -> Option<usize> {
+    let first_line = text.split_inclusive('\n').next()?;
+    let indented = first_line.trim_start_matches(' ');
+    if first_line.len() - indented.len() > 3 {
+        return None;
+    }
+    let fence_char = indented.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let fence_len = indented.len() - indented.trim_start_matches(fence_char).len();
+    if fence_len < 3 {
+        return None;
+    }
+
+    let mut end = first_line.len();
+    for line in text[end..].split_inclusive('\n') {
+        end += line.len();
+        let closing = line.trim();
+        if closing.len() >= fence_len && closing.chars().all(|c| c == fence_char) {
+            break;
+        }
+    }
+    Some(end)
+}
+
+/// Length of the code span opened by the backticks at the start of `text`, or
+/// of just the backticks if the span is never closed.
+pub fn code_span_end(text: &str) -> usize {
+    let ticks = text.len() - text.trim_start_matches('`').len();
+    let mut search = ticks;
+    while let Some(found) = text[search..].find('`') {
+        let start = search + found;
+        let run = text[start..].len() - text[start..].trim_start_matches('`').len();
+        if run == ticks {
+            return start + run;
+        }
+        search = start + run;
+    }
+    ticks
+}
+
+/// Length of the `$...$` span at the start of `text`, if it is one. Inline math
+/// doesn't continue past a blank line or into a code span.
+fn inline_math_end(text: &str) -> Option<usize> {
+    let body = &text[1..];
+    if body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let paragraph_end = body.find("\n\n").unwrap_or(body.len());
+    let paragraph = &body[..body[..paragraph_end].find('`').unwrap_or(paragraph_end)];
+
+    let mut search = 0;
+    while let Some(found) = paragraph[search..].find('$') {
+        let close = search + found;
+        let before = paragraph[..close].chars().next_back();
+        let after = paragraph[close + 1..].chars().next();
+        let closes = close > 0
+            && !before.is_some_and(|c| c.is_whitespace() || c == '\\')
+            && !after.is_some_and(|c| c.is_ascii_digit());
+        if closes {
+            return Some(close + 2);
+        }
+        search = close + 1;
+    }
+    None
+}
@@ -0,0 +1,33 @@
+use crate::post::humanize;
+use chrono::Local;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory, relative to the source root, that new posts are created in.
+pub const POSTS_DIR: &str = "posts";
+
+/// Creates `posts/<slug>.md` under `source_dir` with front matter filled in
+/// from the slug and today's date, refusing to overwrite an existing file.
+/// `name` may be a slug or a title; it is slugified for the file name.
+pub fn new_post(source_dir: &Path, name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let slug = slug::slugify(name);
+    if slug.is_empty() {
+        return Err(format!("Cannot derive a file name from {:?}", name).into());
+    }
+
+    let post_path = source_dir.join(POSTS_DIR).join(format!("{}.md", slug));
+    if post_path.exists() {
+        return Err(format!("{} already exists", post_path.display()).into());
+    }
+
+    let front_matter = format!(
+        "---\ntitle: \"{}\"\ndate: {}\ntags: []\n---\n\n",
+        humanize(&slug),
+        Local::now().date_naive().format("%Y-%m-%d")
+    );
+    fs::create_dir_all(post_path.parent().unwrap())?;
+    fs::write(&post_path, front_matter)?;
+
+    Ok(post_path)
+}
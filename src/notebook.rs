@@ -0,0 +1,419 @@
+use crate::formats::{code_block, escape_markdown, set_metadata};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Output formats of a cell shown in order of preference, when it offers the
+/// same result in several of them.
+const MIME_TYPES: [&str; 8] = [
+    "text/html",
+    "image/svg+xml",
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "text/markdown",
+    "text/latex",
+    "text/plain",
+];
+
+/// Cell tags leaving a whole cell, its code or its outputs out of the post,
+/// as Jupyter Book reads them.
+const REMOVE_CELL: &str = "remove-cell";
+const REMOVE_INPUT: &str = "remove-input";
+const REMOVE_OUTPUT: &str = "remove-output";
+
+#[derive(Deserialize)]
+struct Notebook {
+    nbformat: u32,
+    #[serde(default)]
+    metadata: Map<String, Value>,
+    #[serde(default)]
+    cells: Vec<Cell>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: Text,
+    #[serde(default)]
+    metadata: CellMetadata,
+    #[serde(default)]
+    outputs: Vec<Output>,
+    /// Images pasted into a markdown cell, by file name, each in one or more
+    /// formats.
+    #[serde(default)]
+    attachments: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+#[derive(Default, Deserialize)]
+struct CellMetadata {
+    #[serde(default)]
+    tags: Vec<String>,
+    /// The format of a raw cell's text, also written `raw_mimetype`.
+    #[serde(default, alias = "raw_mimetype")]
+    format: String,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+    Stream {
+        name: String,
+        text: Text,
+    },
+    DisplayData {
+        data: Map<String, Value>,
+    },
+    ExecuteResult {
+        data: Map<String, Value>,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+        traceback: Vec<String>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Text notebooks store as one string or as a list of lines.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Text {
+    String(String),
+    Lines(Vec<String>),
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Text::String(String::new())
+    }
+}
+
+impl Text {
+    fn joined(&self) -> String {
+        match self {
+            Text::String(text) => text.clone(),
+            Text::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Converts a Jupyter notebook. Markdown cells are kept as they are, and code
+/// cells become code blocks in the notebook's language followed by what they
+/// output, both inside a `notebook-cell` block. Images are embedded in the
+/// page as `data:` URLs.
+///
+/// A raw cell opening the notebook with front matter is used as it is;
+/// otherwise the notebook's `title`, `authors`, `date`, `tags` and other
+/// metadata named like front matter keys make up the front matter.
+pub fn to_markdown(_: &Path, content: &str) -> Result<(Map<String, Value>, String), String> {
+    let notebook: Notebook =
+        serde_json::from_str(content).map_err(|e| format!("invalid notebook: {}", e))?;
+    if notebook.nbformat < 4 {
+        return Err(format!(
+            "notebook format {} isn't supported, save it with Jupyter 4 or later",
+            notebook.nbformat
+        ));
+    }
+    let language = ["kernelspec", "language_info"]
+        .iter()
+        .find_map(|key| {
+            let info = notebook.metadata.get(*key)?;
+            info.get("language").or(info.get("name"))?.as_str()
+        })
+        .unwrap_or("python")
+        .to_ascii_lowercase();
+
+    let mut output = String::with_capacity(content.len());
+    let mut cells = notebook.cells.iter().peekable();
+    let front_matter = cells.next_if(|cell| {
+        let source = cell.source.joined();
+        cell.cell_type == "raw" && (source.starts_with("---") || source.starts_with("+++"))
+    });
+    let metadata = match front_matter {
+        Some(cell) => {
+            output.push_str(cell.source.joined().trim_end());
+            output.push('\n');
+            Map::new()
+        }
+        None => metadata(&notebook.metadata),
+    };
+
+    for cell in cells {
+        if cell.metadata.tags.iter().any(|tag| tag == REMOVE_CELL) {
+            continue;
+        }
+        let source = cell.source.joined();
+        match cell.cell_type.as_str() {
+            "markdown" => output.push_str(&with_attachments(source.trim(), &cell.attachments)),
+            "code" => code_cell(cell, source.trim_end(), &language, &mut output),
+            "raw" if ["text/html", "text/markdown"].contains(&cell.metadata.format.as_str()) => {
+                output.push_str(source.trim())
+            }
+            _ => continue,
+        }
+        output.push_str("\n\n");
+    }
+    Ok((metadata, output))
+}
+
+/// The front matter set by the notebook's metadata. Authors are objects with
+/// a `name`.
+fn metadata(notebook: &Map<String, Value>) -> Map<String, Value> {
+    let mut metadata = Map::new();
+    for (key, value) in notebook {
+        let values: Vec<String> = match value {
+            Value::String(value) => vec![value.clone()],
+            Value::Bool(value) => vec![value.to_string()],
+            Value::Array(values) => values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::String(value) => Some(value.clone()),
+                    value => Some(value.get("name")?.as_str()?.to_string()),
+                })
+                .collect(),
+            _ => continue,
+        };
+        match (key.as_str(), values.as_slice()) {
+            ("authors", [name]) => set_metadata(&mut metadata, "author", name),
+            (key, values) => set_metadata(&mut metadata, key, &values.join(",")),
+        }
+    }
+    metadata
+}
+
+/// A markdown cell with links to its attachments, `attachment:plot.png`,
+/// replaced by the images themselves.
+fn with_attachments(
+    source: &str,
+    attachments: &BTreeMap<String, BTreeMap<String, Value>>,
+) -> String {
+    let mut source = source.to_string();
+    for (name, data) in attachments {
+        // SVG images are stored as text, the others in base64.
+        let image = data.iter().find(|(mime_type, _)| {
+            mime_type.starts_with("image/") && *mime_type != "image/svg+xml"
+        });
+        if let Some((mime_type, data)) = image {
+            let url = data_url(mime_type, &output_text(data));
+            source = source.replace(&format!("attachment:{}", name), &url);
+        }
+    }
+    source
+}
+
+/// A code cell: its code unless it is tagged `remove-input`, then each of its
+/// outputs unless it is tagged `remove-output`.
+fn code_cell(cell: &Cell, source: &str, language: &str, output: &mut String) {
+    let tagged = |tag| cell.metadata.tags.iter().any(|t| t == tag);
+    let show_input = !source.is_empty() && !tagged(REMOVE_INPUT);
+    let show_outputs = !cell.outputs.is_empty() && !tagged(REMOVE_OUTPUT);
+    if !show_input && !show_outputs {
+        return;
+    }
+    output.push_str("<div class=\"notebook-cell\">\n\n");
+    if show_input {
+        code_block(language, &source.lines().collect::<Vec<_>>(), output);
+        output.push('\n');
+    }
+    if show_outputs {
+        let mut outputs = cell.outputs.iter().peekable();
+        while let Some(result) = outputs.next() {
+            match result {
+                Output::Stream { name, text } => {
+                    // Jupyter shows a stream written in several parts as one.
+                    let mut text = text.joined();
+                    while let Some(Output::Stream { text: more, .. }) = outputs
+                        .next_if(|next| matches!(next, Output::Stream { name: n, .. } if n == name))
+                    {
+                        text.push_str(&more.joined());
+                    }
+                    let class = match name.as_str() {
+                        "stderr" => "notebook-output notebook-stderr",
+                        _ => "notebook-output",
+                    };
+                    preformatted(class, &terminal_text(&text), output);
+                }
+                Output::DisplayData { data } | Output::ExecuteResult { data } => {
+                    rich_output(data, output)
+                }
+                Output::Error {
+                    ename,
+                    evalue,
+                    traceback,
+                } => {
+                    let text = match traceback.is_empty() {
+                        true => format!("{}: {}", ename, evalue),
+                        false => traceback.join("\n"),
+                    };
+                    preformatted(
+                        "notebook-output notebook-error",
+                        &terminal_text(&text),
+                        output,
+                    );
+                }
+                Output::Unknown => {}
+            }
+        }
+    }
+    output.push_str("</div>");
+}
+
+/// A display or a result, in the format it offers that is first of the
+/// `MIME_TYPES`.
+fn rich_output(data: &Map<String, Value>, output: &mut String) {
+    let Some((mime_type, value)) = MIME_TYPES
+        .iter()
+        .find_map(|mime_type| Some((*mime_type, data.get(*mime_type)?)))
+    else {
+        return;
+    };
+    let text = output_text(value);
+    match mime_type {
+        "text/html" | "image/svg+xml" => {
+            // An SVG document's XML declaration and doctype can't be put in
+            // a page.
+            let html = match mime_type {
+                "image/svg+xml" => text.find("<svg").map_or(text.as_str(), |i| &text[i..]),
+                _ => &text,
+            };
+            output.push_str("<div class=\"notebook-output\">\n");
+            for line in html.trim().lines() {
+                // A blank line would end the HTML block.
+                match line.trim().is_empty() {
+                    true => output.push_str("&#32;"),
+                    false => output.push_str(line),
+                }
+                output.push('\n');
+            }
+            output.push_str("</div>\n\n");
+        }
+        "text/markdown" | "text/latex" => {
+            output.push_str("<div class=\"notebook-output\">\n\n");
+            output.push_str(text.trim());
+            output.push_str("\n\n</div>\n\n");
+        }
+        "text/plain" => preformatted("notebook-output", &text, output),
+        image => {
+            // The plain text version of a plot, like `<Figure size 640x480
+            // with 1 Axes>`, is the best description there is of it.
+            let alt = data
+                .get("text/plain")
+                .map(output_text)
+                .and_then(|alt| alt.lines().next().map(str::to_string))
+                .unwrap_or_else(|| "Output".to_string());
+            output.push_str("<div class=\"notebook-output\">\n\n![");
+            escape_markdown(alt.trim(), output);
+            output.push_str(&format!("]({})\n\n</div>\n\n", data_url(image, &text)));
+        }
+    }
+}
+
+/// Text a cell printed, as a code block inside an output block of `class`.
+fn preformatted(class: &str, text: &str, output: &mut String) {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    if lines.is_empty() {
+        return;
+    }
+    output.push_str(&format!("<div class=\"{}\">\n\n", class));
+    code_block("", &lines, output);
+    output.push_str("\n</div>\n\n");
+}
+
+/// Text written to a terminal as it looks there: without the escape codes
+/// coloring it, like those in tracebacks, and with each line only showing
+/// what was written after its last carriage return, like progress bars.
+fn terminal_text(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' => {
+                if chars.next() == Some('[') {
+                    for c in chars.by_ref() {
+                        if c.is_ascii_alphabetic() {
+                            break;
+                        }
+                    }
+                }
+            }
+            c => plain.push(c),
+        }
+    }
+    plain
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .map(|line| line.rsplit('\r').next().unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The text of one format of an output, given as a string or as lines.
+fn output_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        value => value.to_string(),
+    }
+}
+
+/// A `data:` URL of an image notebooks store in base64, which may be split
+/// across lines.
+fn data_url(mime_type: &str, base64: &str) -> String {
+    let data: String = base64.split_whitespace().collect();
+    format!("data:{};base64,{}", mime_type, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(notebook: &str) -> Result<(Map<String, Value>, String), String> {
+        to_markdown(Path::new("notebook.ipynb"), notebook)
+    }
+
+    #[test]
+    fn converts_metadata_and_cells() {
+        let (metadata, markdown) = convert(
+            r##"{"nbformat": 4, "nbformat_minor": 5,
+              "metadata": {"title": "Hello", "tags": ["a", "b"], "kernelspec": {"language": "python"}},
+              "cells": [
+                {"cell_type": "markdown", "metadata": {},
+                 "source": ["# Heading\n", "\n", "- one\n", "- two\n", "\n", "| a | b |\n", "| - | - |\n", "| 1 | 2 |\n"]},
+                {"cell_type": "code", "metadata": {}, "execution_count": 1, "source": "print('hi')",
+                 "outputs": [{"output_type": "stream", "name": "stdout", "text": ["hi\n"]}]}
+              ]}"##,
+        )
+        .unwrap();
+        assert_eq!(metadata["title"], "Hello");
+        assert_eq!(metadata["tags"], serde_json::json!(["a", "b"]));
+        assert_eq!(
+            markdown,
+            "# Heading\n\n- one\n- two\n\n| a | b |\n| - | - |\n| 1 | 2 |\n\n\
+             <div class=\"notebook-cell\">\n\n```python\nprint('hi')\n```\n\n\
+             <div class=\"notebook-output\">\n\n```\nhi\n```\n\n</div>\n\n</div>\n\n"
+        );
+    }
+
+    #[test]
+    fn keeps_front_matter_and_callouts_of_cells() {
+        let (metadata, markdown) = convert(
+            r##"{"nbformat": 4, "nbformat_minor": 5, "metadata": {}, "cells": [
+              {"cell_type": "raw", "metadata": {}, "source": "---\ntitle: Raw\n---\n"},
+              {"cell_type": "markdown", "metadata": {}, "source": ":::note\nCareful\n:::"}
+            ]}"##,
+        )
+        .unwrap();
+        assert!(metadata.is_empty());
+        assert_eq!(markdown, "---\ntitle: Raw\n---\n:::note\nCareful\n:::\n\n");
+    }
+
+    #[test]
+    fn refuses_old_formats() {
+        let error = convert(r#"{"nbformat": 3, "metadata": {}, "cells": []}"#).unwrap_err();
+        assert!(error.contains("format 3"), "{}", error);
+    }
+}
@@ -0,0 +1,446 @@
+use crate::formats::{code_block, code_span, container_colons, escape_markdown, set_metadata};
+use serde_json::{Map, Value};
+
+/// Org special blocks shown as `:::` callouts, and the kind they become.
+const CALLOUTS: [(&str, &str); 8] = [
+    ("note", "note"),
+    ("info", "info"),
+    ("tip", "tip"),
+    ("hint", "tip"),
+    ("important", "important"),
+    ("warning", "warning"),
+    ("caution", "warning"),
+    ("danger", "danger"),
+];
+
+/// Heading keywords left out of the converted heading.
+const TODO_KEYWORDS: [&str; 2] = ["TODO", "DONE"];
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "avif"];
+
+/// Converts an org-mode document to markdown. `#+TITLE:`, `#+DATE:`,
+/// `#+FILETAGS:` and the other keywords naming front matter keys are
+/// returned as its front matter.
+///
+/// Headings, emphasis, links, lists, tables, source, example and quote
+/// blocks are converted; special blocks like `#+BEGIN_NOTE` become `:::`
+/// callouts. Drawers, planning lines and comments are left out.
+pub fn to_markdown(content: &str) -> (Map<String, Value>, String) {
+    let mut metadata = Map::new();
+    let lines: Vec<&str> = content.lines().collect();
+    let markdown = convert(&lines, &mut metadata);
+    (metadata, markdown)
+}
+
+fn convert(lines: &[&str], metadata: &mut Map<String, Value>) -> String {
+    let mut output = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        let indent = &line[..line.len() - line.trim_start().len()];
+        i += 1;
+
+        if let Some(rest) = strip_prefix_ignore_case(trimmed, "#+begin_") {
+            let name = rest
+                .split_whitespace()
+                .next()
+                .unwrap_or("")
+                .to_ascii_lowercase();
+            let arguments = rest[name.len()..].trim();
+            let end = format!("#+end_{}", name);
+            let length = lines[i..]
+                .iter()
+                .position(|line| line.trim().eq_ignore_ascii_case(&end))
+                .unwrap_or(lines.len() - i);
+            let inner = &lines[i..i + length];
+            i = (i + length + 1).min(lines.len());
+            block(&name, arguments, inner, metadata, &mut output);
+            continue;
+        }
+        if let Some(keyword) = trimmed.strip_prefix("#+") {
+            if let Some((key, value)) = keyword.split_once(':') {
+                set_metadata(metadata, key, value);
+            }
+            continue;
+        }
+        if trimmed == "#" || trimmed.starts_with("# ") {
+            continue;
+        }
+        if is_drawer(trimmed) {
+            while i < lines.len() && !lines[i].trim().eq_ignore_ascii_case(":end:") {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        if ["SCHEDULED:", "DEADLINE:", "CLOSED:"]
+            .iter()
+            .any(|planning| trimmed.starts_with(planning))
+        {
+            continue;
+        }
+
+        if let Some(heading) = heading(line) {
+            output.push_str(&heading);
+        } else if trimmed == ":" || trimmed.starts_with(": ") {
+            let mut example = vec![trimmed.strip_prefix(':').unwrap_or("").trim_start()];
+            while let Some(next) = lines.get(i).map(|line| line.trim()) {
+                let Some(text) = next
+                    .strip_prefix(':')
+                    .filter(|text| text.is_empty() || text.starts_with(' '))
+                else {
+                    break;
+                };
+                example.push(text.strip_prefix(' ').unwrap_or(text));
+                i += 1;
+            }
+            code_block("", &example, &mut output);
+            continue;
+        } else if trimmed.len() >= 5 && trimmed.chars().all(|c| c == '-') {
+            output.push_str("---");
+        } else if trimmed.starts_with('|') {
+            let start = i - 1;
+            while lines
+                .get(i)
+                .is_some_and(|line| line.trim().starts_with('|'))
+            {
+                i += 1;
+            }
+            table(&lines[start..i], &mut output);
+            continue;
+        } else if let Some(item) = list_item(trimmed) {
+            output.push_str(indent);
+            output.push_str(&item);
+        } else {
+            output.push_str(indent);
+            output.push_str(&inline(trimmed));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Writes the `#+BEGIN_<name>` block holding `inner`.
+fn block(
+    name: &str,
+    arguments: &str,
+    inner: &[&str],
+    metadata: &mut Map<String, Value>,
+    output: &mut String,
+) {
+    match name {
+        "src" => {
+            let lang = arguments.split_whitespace().next().unwrap_or("");
+            code_block(lang, &unescape(inner), output);
+        }
+        "example" => code_block("", &unescape(inner), output),
+        "export" if arguments.eq_ignore_ascii_case("html") => {
+            for line in inner {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+        "export" | "comment" => {}
+        "quote" | "verse" => {
+            for line in convert(inner, metadata).lines() {
+                output.push_str(format!("> {}", line).trim_end());
+                output.push('\n');
+            }
+        }
+        _ => {
+            let markdown = convert(inner, metadata);
+            match CALLOUTS.iter().find(|(block, _)| *block == name) {
+                Some((_, kind)) => {
+                    let fence = ":".repeat(container_colons(&markdown));
+                    output.push_str(&format!("{}{}\n{}{}\n", fence, kind, markdown, fence));
+                }
+                None => output.push_str(&markdown),
+            }
+        }
+    }
+}
+
+/// Lines of a source block without the commas org escapes `*` and `#+` at
+/// their start with.
+fn unescape(lines: &[&str]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            let rest = &line[indent..];
+            match rest.strip_prefix(',') {
+                Some(text) if text.starts_with('*') || text.starts_with("#+") => {
+                    format!("{}{}", &line[..indent], text)
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn is_drawer(line: &str) -> bool {
+    line.len() > 2
+        && line.starts_with(':')
+        && line.ends_with(':')
+        && line[1..line.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// A `* Heading` line as a markdown heading one level deeper, since the
+/// title is the page's only first-level heading. TODO keywords, priorities
+/// and tags are left out.
+fn heading(line: &str) -> Option<String> {
+    let stars = line.len() - line.trim_start_matches('*').len();
+    let title = line[stars..].strip_prefix(' ')?.trim();
+    if stars == 0 {
+        return None;
+    }
+    let mut words: Vec<&str> = title.split_whitespace().collect();
+    if words
+        .first()
+        .is_some_and(|word| TODO_KEYWORDS.contains(word))
+    {
+        words.remove(0);
+    }
+    if words
+        .first()
+        .is_some_and(|word| word.starts_with("[#") && word.ends_with(']'))
+    {
+        words.remove(0);
+    }
+    if words
+        .last()
+        .is_some_and(|word| word.len() > 1 && word.starts_with(':') && word.ends_with(':'))
+    {
+        words.pop();
+    }
+    Some(format!(
+        "{} {}",
+        "#".repeat((stars + 1).min(6)),
+        inline(&words.join(" "))
+    ))
+}
+
+/// A list item line with its bullet in markdown's form, or `None` if the
+/// line isn't one. Description items, `- term :: text`, get their term in
+/// bold.
+fn list_item(line: &str) -> Option<String> {
+    let (marker, rest) = if let Some(rest) = ["- ", "+ ", "* "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))
+    {
+        ("- ".to_string(), rest)
+    } else {
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let rest = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))
+            .filter(|_| digits > 0)?;
+        (format!("{}. ", &line[..digits]), rest)
+    };
+    let (checkbox, rest) = match rest.get(..4) {
+        Some("[ ] ") | Some("[-] ") => ("[ ] ", &rest[4..]),
+        Some("[X] ") | Some("[x] ") => ("[x] ", &rest[4..]),
+        _ => ("", rest),
+    };
+    let text = match rest.split_once(" :: ") {
+        Some((term, description)) => format!("**{}**: {}", inline(term), inline(description)),
+        None => inline(rest),
+    };
+    Some(format!("{}{}{}", marker, checkbox, text))
+}
+
+/// An org table as a markdown one. Its first row is the header, and the
+/// horizontal rules between rows are dropped.
+fn table(lines: &[&str], output: &mut String) {
+    let rows: Vec<Vec<String>> = lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.starts_with("|-"))
+        .map(|line| {
+            line.trim_matches('|')
+                .split('|')
+                .map(|cell| inline(cell.trim()).replace('|', "\\|"))
+                .collect()
+        })
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for (i, row) in rows.iter().enumerate() {
+        let mut cells = row.clone();
+        cells.resize(columns, String::new());
+        output.push_str(&format!("| {} |\n", cells.join(" | ")));
+        if i == 0 {
+            output.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+        }
+    }
+}
+
+/// Org inline markup converted to markdown's: `*bold*`, `/italic/`,
+/// `_underline_`, `+strike-through+`, `=verbatim=` and `~code~`, links and
+/// bare URLs. Math in `$...$` and `\(...\)` is kept for KaTeX.
+fn inline(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let previous = text[..i].chars().next_back();
+        if let Some((link, len)) = link(rest) {
+            output.push_str(&link);
+            i += len;
+            continue;
+        }
+        if (rest.starts_with("https://") || rest.starts_with("http://"))
+            && previous.is_none_or(|c| c.is_whitespace() || c == '(')
+        {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == ')' || c == ']')
+                .unwrap_or(rest.len());
+            let url = rest[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            output.push_str(&format!("<{}>", url));
+            i += url.len();
+            continue;
+        }
+        if let Some(math) = rest.strip_prefix("\\(") {
+            if let Some(end) = math.find("\\)") {
+                output.push_str(&format!("${}$", &math[..end]));
+                i += end + 4;
+                continue;
+            }
+        }
+        if let Some(math) = rest.strip_prefix('$') {
+            if let Some(end) = math.find('$').filter(|end| *end > 0) {
+                output.push_str(&rest[..end + 2]);
+                i += end + 2;
+                continue;
+            }
+        }
+        let c = rest.chars().next().unwrap();
+        if let Some(len) = emphasis_end(rest, previous) {
+            let inner = &rest[1..len - 1];
+            match c {
+                '=' | '~' => output.push_str(&code_span(inner)),
+                '*' => output.push_str(&format!("**{}**", inline(inner))),
+                '/' => output.push_str(&format!("*{}*", inline(inner))),
+                '_' => output.push_str(&format!("<u>{}</u>", inline(inner))),
+                _ => output.push_str(&format!("~~{}~~", inline(inner))),
+            }
+            i += len;
+            continue;
+        }
+        escape_markdown(&rest[..c.len_utf8()], &mut output);
+        i += c.len_utf8();
+    }
+    output
+}
+
+/// Length of the emphasized span opening at the start of `text`, markers
+/// included, if one does: the marker follows whitespace or an opening
+/// punctuation mark, and its closing twin follows text and precedes
+/// whitespace or punctuation.
+fn emphasis_end(text: &str, previous: Option<char>) -> Option<usize> {
+    let marker = text.chars().next().filter(|c| "*/_+=~".contains(*c))?;
+    if previous.is_some_and(|c| !c.is_whitespace() && !"-({'\"".contains(c)) {
+        return None;
+    }
+    let body = &text[1..];
+    if body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    // The emphasized text can't be empty.
+    let mut search = body.chars().next().map_or(0, char::len_utf8);
+    while let Some(found) = body[search..].find(marker) {
+        let end = search + found;
+        let before = body[..end].chars().next_back();
+        let after = body[end + 1..].chars().next();
+        if before.is_some_and(|c| !c.is_whitespace())
+            && after.is_none_or(|c| c.is_whitespace() || "-.,:;!?'\")}[".contains(c))
+        {
+            return Some(end + 2);
+        }
+        search = end + 1;
+    }
+    None
+}
+
+/// The markdown link for the `[[target][description]]` or `[[target]]` org
+/// link at the start of `text`, and the link's length. Links without a
+/// description to images show the image.
+fn link(text: &str) -> Option<(String, usize)> {
+    let body = text.strip_prefix("[[")?;
+    let end = body.find("]]")?;
+    let (target, description) = match body[..end].split_once("][") {
+        Some((target, description)) => (target, Some(description)),
+        None => (&body[..end], None),
+    };
+    let target = target.strip_prefix("file:").unwrap_or(target);
+    let target = match target.strip_prefix('*') {
+        Some(heading) => format!("#{}", slug::slugify(heading)),
+        None => target.to_string(),
+    };
+    let is_image = target.rsplit_once('.').is_some_and(|(_, extension)| {
+        IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+    });
+    let markdown = match description {
+        Some(description) => format!("[{}](<{}>)", inline(description), target),
+        None if is_image => format!("![](<{}>)", target),
+        None => format!("[{}](<{}>)", inline(&target), target),
+    };
+    Some((markdown, end + 4))
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let start = text.get(..prefix.len())?;
+    start
+        .eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown(org: &str) -> String {
+        to_markdown(org).1
+    }
+
+    #[test]
+    fn converts_keywords_and_headings() {
+        let (metadata, markdown) = to_markdown(
+            "#+TITLE: Hello World\n#+DATE: <2024-03-01 Fri>\n#+FILETAGS: :rust:web:\n\n\
+             Intro /text/.\n\n* TODO [#A] Section :tag:\n:PROPERTIES:\n:ID: x\n:END:\n\
+             Body.\n** Sub\n",
+        );
+        assert_eq!(metadata["title"], "Hello World");
+        assert_eq!(metadata["date"], "2024-03-01");
+        assert_eq!(metadata["tags"], serde_json::json!(["rust", "web"]));
+        assert_eq!(markdown, "\nIntro *text*.\n\n## Section\nBody.\n### Sub\n");
+    }
+
+    #[test]
+    fn converts_lists() {
+        assert_eq!(
+            markdown("- one\n- two\n  - nested\n1. first\n2. second\n- term :: text\n"),
+            "- one\n- two\n  - nested\n1. first\n2. second\n- **term**: text\n"
+        );
+    }
+
+    #[test]
+    fn converts_tables() {
+        assert_eq!(
+            markdown("| Name | Thé |\n|------+-----|\n| café | — yes |\n"),
+            "| Name | Thé |\n| --- | --- |\n| café | — yes |\n"
+        );
+    }
+
+    #[test]
+    fn converts_special_blocks_to_callouts() {
+        assert_eq!(
+            markdown(
+                "#+BEGIN_NOTE\nTake *care*.\n#+END_NOTE\n\n#+begin_warning\nHot\n#+end_warning\n"
+            ),
+            ":::note\nTake **care**.\n:::\n\n:::warning\nHot\n:::\n"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Writes `contents` to `path` through a temporary file in the same directory,
+/// renamed over `path` once it is complete. Anything reading the output
+/// directory meanwhile, like `ebolg serve`, sees the old file or the new one,
+/// never half of one, even if the build is interrupted.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let temp_path = temp_path(path);
+    fs::write(&temp_path, contents)
+        .and_then(|()| fs::rename(&temp_path, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })
+}
+
+/// Copies `from` to `to` the way `write_atomic` writes.
+pub fn copy_atomic(from: &Path, to: &Path) -> io::Result<()> {
+    let temp_path = temp_path(to);
+    fs::copy(from, &temp_path)
+        .and_then(|_| fs::rename(&temp_path, to))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })
+}
+
+/// A hidden sibling of `path`, unique to this process.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
@@ -0,0 +1,81 @@
+use crate::error::BuildError;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory, under a listing's own directory, holding its pages after the first.
+pub const PAGE_DIR: &str = "page";
+
+/// Where a page of a listing sits among the others, for templates to link them.
+#[derive(Debug, Serialize)]
+pub struct Paginator {
+    /// 1-based number of this page.
+    pub current: usize,
+    pub total: usize,
+    pub prev_url: Option<String>,
+    pub next_url: Option<String>,
+}
+
+/// One page of a listing.
+pub struct Page<'a, P> {
+    pub posts: &'a [P],
+    pub paginator: Paginator,
+    /// Where the page is written, relative to the output directory.
+    pub path: String,
+}
+
+/// Splits `posts` into pages of `per_page`, or a single page when it is 0. The
+/// first page lives at `<dir>index.html` and the others at
+/// `<dir>page/<n>/index.html`; `dir` starts and ends with `/`. There is always
+/// at least one page, so an empty listing still gets written.
+pub fn paginate<'a, P>(posts: &'a [P], per_page: usize, dir: &str) -> Vec<Page<'a, P>> {
+    let chunks: Vec<&[P]> = if per_page == 0 || posts.is_empty() {
+        vec![posts]
+    } else {
+        posts.chunks(per_page).collect()
+    };
+    let total = chunks.len();
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, posts)| {
+            let current = i + 1;
+            Page {
+                posts,
+                paginator: Paginator {
+                    current,
+                    total,
+                    prev_url: (current > 1).then(|| page_url(dir, current - 1)),
+                    next_url: (current < total).then(|| page_url(dir, current + 1)),
+                },
+                path: page_url(dir, current),
+            }
+        })
+        .collect()
+}
+
+fn page_url(dir: &str, number: usize) -> String {
+    match number {
+        1 => format!("{}index.html", dir),
+        _ => format!("{}{}/{}/index.html", dir, PAGE_DIR, number),
+    }
+}
+
+/// Removes the pages of a listing beyond `total`, left over from a build when
+/// it was longer.
+pub fn remove_extra_pages(output_dir: &Path, dir: &str, total: usize) -> Result<(), BuildError> {
+    let pages_dir = output_dir.join(dir.trim_start_matches('/')).join(PAGE_DIR);
+    let mut number = total + 1;
+    loop {
+        let page_dir: PathBuf = pages_dir.join(number.to_string());
+        if !page_dir.is_dir() {
+            return Ok(());
+        }
+        fs::remove_dir_all(&page_dir).map_err(|source| BuildError::Write {
+            path: page_dir.clone(),
+            source,
+        })?;
+        number += 1;
+    }
+}
@@ -0,0 +1,358 @@
+use crate::authors::Author;
+use crate::config::{Config, TAGS};
+use crate::error::BuildError;
+use crate::formats;
+use crate::front_matter::{self, ParseError};
+use crate::includes::expand_includes;
+use crate::languages::{base_stem, Translation};
+use crate::markdown::{excerpt, first_heading, word_count};
+use crate::toc::{table_of_contents, TocEntry};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct Metadata {
+    /// See `Post::title` for the fallbacks when absent.
+    #[serde(default, skip_serializing)]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing, deserialize_with = "deserialize_date")]
+    pub date: Option<NaiveDate>,
+    /// See `Post::updated` for the fallbacks when absent.
+    #[serde(default, skip_serializing, deserialize_with = "deserialize_date")]
+    pub updated: Option<NaiveDate>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Replaces the file stem in the permalink.
+    #[serde(default)]
+    pub slug: Option<String>,
+    /// Summary for listings, feeds and the meta description. Derived from the
+    /// post when absent, see `Post::summary`.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Drafts are left out of the build unless `--drafts` is passed.
+    #[serde(default)]
+    pub draft: bool,
+    /// Unlisted posts are built and reachable by URL, but left out of the
+    /// listing pages, feeds, the sitemap and search.
+    #[serde(default)]
+    pub unlisted: bool,
+    /// Adds a table of contents, see `Post::toc`.
+    #[serde(default, skip_serializing)]
+    pub toc: bool,
+    /// Overrides `[markdown] math` from the config for this post.
+    #[serde(default, skip_serializing)]
+    pub math: Option<bool>,
+    /// Set to `false` to leave out the comment thread the config adds.
+    #[serde(default, skip_serializing)]
+    pub comments: Option<bool>,
+    /// Who wrote the post, a key of `[authors]` in the config or a name, see
+    /// `post_authors`. `authors:` lists several.
+    #[serde(default, skip_serializing)]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub authors: Vec<String>,
+    /// Template to render the post with, without its `.html`, see `Post::template`.
+    #[serde(default)]
+    pub layout: Option<String>,
+    /// Every other front-matter key, such as the terms of configured
+    /// taxonomies. Templates see them as fields of `post`.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// Front-matter keys with a meaning of their own, which can't name a taxonomy.
+pub const METADATA_KEYS: [&str; 17] = [
+    "title",
+    "date",
+    "updated",
+    "slug",
+    "description",
+    "draft",
+    "unlisted",
+    "toc",
+    "math",
+    "comments",
+    "author",
+    "authors",
+    "layout",
+    "summary",
+    "url",
+    "word_count",
+    "reading_time",
+];
+
+/// Top-level front-matter keys, each with the line of the file it is set on.
+pub type FrontMatterKeys = Vec<(String, usize)>;
+
+/// Reading speed behind `Post::reading_time`.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Accepts a plain `YYYY-MM-DD` date as well as the full timestamps other
+/// generators write, keeping only the date.
+fn deserialize_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveDate>, D::Error> {
+    let Some(value) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let value = value.trim();
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|datetime| datetime.date_naive()))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+                .map(|datetime| datetime.date())
+        })
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .map(|datetime| datetime.date())
+        })
+        .map(Some)
+        .map_err(|_| D::Error::custom(format!("invalid date {:?}, expected YYYY-MM-DD", value)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct Post {
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    /// The front-matter title, the post's first `# heading`, or its humanized
+    /// file name, in that order.
+    pub title: String,
+    /// The front-matter date, or else the date of the first git commit adding
+    /// the source file, or else its modification date.
+    pub date: NaiveDate,
+    /// When the post last changed: the front-matter `updated:`, else the date
+    /// of the last git commit touching the source file, else its modification
+    /// date, and never before `date`.
+    pub updated: NaiveDate,
+    /// The front-matter description, or a plain-text excerpt of the post.
+    pub summary: String,
+    /// The post's headings, nested by level, when the front matter sets
+    /// `toc: true`; empty otherwise.
+    pub toc: Vec<TocEntry>,
+    /// Words in the post's text, code blocks aside.
+    pub word_count: usize,
+    /// Minutes the post takes to read, at least one.
+    pub reading_time: usize,
+    /// Code of the language the post is written in, see `file_language`.
+    pub lang: String,
+    /// The post's versions in the site's other languages, filled in once
+    /// every post is collected.
+    pub translations: Vec<Translation>,
+    /// The authors from the front matter, or else the contributors with
+    /// `authors_from_git`, filled in with the `url`.
+    pub authors: Vec<Author>,
+    /// Names of the authors of the git commits changing the source file,
+    /// by their first commit.
+    pub contributors: Vec<String>,
+    /// The markdown body, rendered into `html` once every post is collected.
+    #[serde(skip)]
+    pub content: String,
+    /// Canonical paths of the local files the body includes.
+    #[serde(skip)]
+    pub includes: Vec<PathBuf>,
+    #[serde(skip)]
+    pub keys: FrontMatterKeys,
+    #[serde(skip)]
+    pub html: String,
+    #[serde(skip)]
+    pub source_path: PathBuf,
+    #[serde(skip)]
+    pub html_path: PathBuf,
+    /// Site-relative link to the page. Pages written as `index.html` are
+    /// linked by their directory.
+    pub url: String,
+}
+
+impl Post {
+    /// The post's terms in `taxonomy`, from a single string or a list of them.
+    pub fn terms(&self, taxonomy: &str) -> Vec<&str> {
+        if taxonomy == TAGS {
+            return self.metadata.tags.iter().map(String::as_str).collect();
+        }
+        match self.metadata.extra.get(taxonomy) {
+            Some(Value::String(term)) => vec![term.as_str()],
+            Some(Value::Array(terms)) => terms.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Name of the template the post is rendered with: its `layout:` from the
+    /// front matter, like `photo` for `photo.html`, or else `default`.
+    pub fn template(&self, default: &str) -> String {
+        let layout = self.metadata.layout.as_deref().unwrap_or(default);
+        format!("{}.html", layout)
+    }
+
+    /// Whether the post's page embeds the comment thread of the configured provider.
+    pub fn comments(&self, config: &Config) -> bool {
+        !config.comments.provider.is_empty() && self.metadata.comments.unwrap_or(true)
+    }
+
+    /// Whether the post's math is left for KaTeX to render.
+    pub fn math(&self, config: &Config) -> bool {
+        self.metadata.math.unwrap_or(config.markdown.math)
+    }
+}
+
+/// Reads a post written in `lang`, with its includes resolved against
+/// `source_dir` when it is `trusted`; those of untrusted posts, like the ones
+/// pulled from a CMS, are left as written, so their authors can't publish the
+/// source directory's files. Its `url` and `html_path` are left empty for the caller to
+/// fill in, since the permalink can depend on the front matter. Posts without
+/// a `date:` or `updated:` get `source_date` when it is set; the caller dates
+/// files committed to git by their history instead, see `FileHistory::apply`.
+pub fn load_post(
+    file_path: &Path,
+    source_dir: &Path,
+    lang: &str,
+    source_date: Option<NaiveDate>,
+    trusted: bool,
+) -> Result<Post, BuildError> {
+    let (metadata, keys, content) = read_post_metadata(file_path)?;
+    let (content, includes) = match trusted {
+        true => expand_includes(&content, source_dir, file_path)?,
+        false => (content, Vec::new()),
+    };
+    let date = resolve_post_date(file_path, &metadata, source_date)?;
+    let updated = resolve_updated_date(file_path, &metadata, source_date)?.max(date);
+    let title = match &metadata.title {
+        Some(title) => title.clone(),
+        None => first_heading(&content).unwrap_or_else(|| humanize(&base_stem(file_path, lang))),
+    };
+    let summary = match &metadata.description {
+        Some(description) => description.clone(),
+        None => excerpt(&content),
+    };
+    let toc = if metadata.toc {
+        table_of_contents(&content)
+    } else {
+        Vec::new()
+    };
+    let word_count = word_count(&content);
+
+    Ok(Post {
+        metadata,
+        title,
+        date,
+        updated,
+        summary,
+        toc,
+        word_count,
+        reading_time: word_count.div_ceil(WORDS_PER_MINUTE).max(1),
+        lang: lang.to_string(),
+        translations: Vec::new(),
+        authors: Vec::new(),
+        contributors: Vec::new(),
+        content,
+        includes,
+        keys,
+        html: String::new(),
+        source_path: file_path.to_path_buf(),
+        html_path: PathBuf::new(),
+        url: String::new(),
+    })
+}
+
+/// The post's metadata, its front-matter keys with their lines, and its body.
+fn read_post_metadata(file_path: &Path) -> Result<(Metadata, FrontMatterKeys, String), BuildError> {
+    let content = fs::read_to_string(file_path).map_err(|source| BuildError::Read {
+        path: file_path.to_path_buf(),
+        source,
+    })?;
+    let content = formats::to_markdown(file_path, content)?;
+
+    let front_matter_error = |e: ParseError| BuildError::FrontMatter {
+        path: file_path.to_path_buf(),
+        line: e.line,
+        column: e.column,
+        message: e.message,
+    };
+    let (metadata, keys, body) = match front_matter::split(&content).map_err(front_matter_error)? {
+        Some(front_matter) => {
+            let metadata = front_matter.parse().map_err(front_matter_error)?;
+            let table: serde_json::Map<String, Value> =
+                front_matter.parse().map_err(front_matter_error)?;
+            let mut keys: FrontMatterKeys = table
+                .into_iter()
+                .map(|(key, _)| {
+                    let line = front_matter.key_line(&key);
+                    (key, line)
+                })
+                .collect();
+            keys.sort_by_key(|(_, line)| *line);
+            (metadata, keys, front_matter.body)
+        }
+        None => (Metadata::default(), Vec::new(), content.trim()),
+    };
+    crate::verbose!("\n-------------------");
+    crate::verbose!("Metadata: {:?}", metadata);
+    crate::verbose!("Content snippet: {}", snippet(&content, 100));
+
+    Ok((metadata, keys, body.to_string()))
+}
+
+/// The start of `text`, at most `max_len` bytes long, cut back to the last
+/// whole character so multi-byte ones like emoji aren't split.
+fn snippet(text: &str, max_len: usize) -> &str {
+    let mut end = max_len.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// `my-first-post` becomes `My First Post`.
+pub fn humanize(stem: &str) -> String {
+    stem.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Falls back to `source_date`, then the file's modification time, for posts
+/// without a `date:` field.
+fn resolve_post_date(
+    file_path: &Path,
+    metadata: &Metadata,
+    source_date: Option<NaiveDate>,
+) -> Result<NaiveDate, BuildError> {
+    if let Some(date) = metadata.date.or(source_date) {
+        return Ok(date);
+    }
+    modified_date(file_path)
+}
+
+/// Falls back to `source_date`, then the file's modification time, for posts
+/// without an `updated:` field.
+fn resolve_updated_date(
+    file_path: &Path,
+    metadata: &Metadata,
+    source_date: Option<NaiveDate>,
+) -> Result<NaiveDate, BuildError> {
+    if let Some(date) = metadata.updated.or(source_date) {
+        return Ok(date);
+    }
+    modified_date(file_path)
+}
+
+fn modified_date(file_path: &Path) -> Result<NaiveDate, BuildError> {
+    let modified = fs::metadata(file_path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|source| BuildError::Read {
+            path: file_path.to_path_buf(),
+            source,
+        })?;
+    Ok(DateTime::<Local>::from(modified).date_naive())
+}
@@ -0,0 +1,101 @@
+use crate::cache::StableHasher;
+use crate::links::{describe, EXTERNAL_TIMEOUT};
+use crate::output::write_atomic;
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Hidden directory at the source root where fetched files are kept. Being
+/// hidden, it isn't built and its writes don't set off `watch`.
+pub const CACHE_DIR: &str = ".ebolg-remote";
+
+/// How long a fetched file is used before it is fetched again, so rebuilds in
+/// quick succession, like those of `watch`, don't fetch it again and again.
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Whether an include names a file on the web rather than in the source
+/// directory.
+pub fn is_remote(file: &str) -> bool {
+    file.starts_with("https://") || file.starts_with("http://")
+}
+
+/// The contents of the file at `url`, fetched at most once every `MAX_AGE`
+/// and kept under `CACHE_DIR` in `source_dir`. When it can't be fetched, the
+/// copy kept last time is used, with a warning.
+pub fn fetch(url: &str, source_dir: &Path) -> Result<String, String> {
+    let cache_path = cache_path(url, source_dir);
+    let age = fs::metadata(&cache_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    if age.is_some_and(|age| age < MAX_AGE) {
+        if let Ok(content) = fs::read_to_string(&cache_path) {
+            return Ok(content);
+        }
+    }
+
+    crate::verbose!("Fetching {}", url);
+    let agent = ureq::AgentBuilder::new().timeout(EXTERNAL_TIMEOUT).build();
+    let fetched = agent
+        .get(url)
+        .call()
+        .map_err(describe)
+        .and_then(|response| response.into_string().map_err(|e| e.to_string()));
+    match fetched {
+        Ok(content) => {
+            let written = fs::create_dir_all(source_dir.join(CACHE_DIR))
+                .and_then(|()| write_atomic(&cache_path, &content));
+            if let Err(e) = written {
+                eprintln!(
+                    "Warning: could not cache {} in {}: {}",
+                    url,
+                    cache_path.display(),
+                    e
+                );
+            }
+            Ok(content)
+        }
+        Err(reason) => match fs::read_to_string(&cache_path) {
+            Ok(content) => {
+                eprintln!(
+                    "Warning: could not fetch {}: {}; using the copy fetched before",
+                    url, reason
+                );
+                Ok(content)
+            }
+            Err(_) => Err(format!("could not fetch it: {}", reason)),
+        },
+    }
+}
+
+/// The extension of the file a URL points at, its query and fragment aside.
+pub fn extension(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next()?;
+    name.rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| !extension.is_empty())
+}
+
+/// Where the copy of the file at `url` is kept, named by a `StableHasher`
+/// hash of the URL, so a committed cache is found by any build of ebolg.
+fn cache_path(url: &str, source_dir: &Path) -> PathBuf {
+    let mut hasher = StableHasher::default();
+    hasher.write(url.as_bytes());
+    source_dir
+        .join(CACHE_DIR)
+        .join(format!("{:016x}", hasher.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_names_are_stable() {
+        // A committed cache is looked up by this name, whatever builds ebolg.
+        let path = cache_path("http://127.0.0.1:8765/docs/README.md", Path::new("site"));
+        assert_eq!(path, Path::new("site/.ebolg-remote/b23067dade6cd79e"));
+    }
+}
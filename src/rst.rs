@@ -0,0 +1,1171 @@
+use crate::formats::{
+    code_block, code_span, container_colons, dedent, escape_markdown, set_metadata,
+};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Admonition directives shown as `:::` callouts, with the kind they become
+/// and their title when it isn't the kind's.
+const ADMONITIONS: [(&str, &str, &str); 11] = [
+    ("note", "note", ""),
+    ("tip", "tip", ""),
+    ("hint", "tip", "Hint"),
+    ("important", "important", ""),
+    ("warning", "warning", ""),
+    ("caution", "warning", "Caution"),
+    ("attention", "warning", "Attention"),
+    ("danger", "danger", ""),
+    ("error", "danger", "Error"),
+    ("seealso", "note", "See also"),
+    ("admonition", "note", ""),
+];
+
+/// Roles whose text is shown as code, Sphinx's Python ones included.
+const CODE_ROLES: [&str; 22] = [
+    "code", "literal", "file", "samp", "command", "program", "option", "envvar", "kbd", "func",
+    "meth", "class", "mod", "attr", "data", "exc", "obj", "const", "type", "macro", "member",
+    "var",
+];
+
+/// Directives left out along with their content.
+const IGNORED_DIRECTIVES: [&str; 8] = [
+    "meta",
+    "toctree",
+    "highlight",
+    "default-role",
+    "include",
+    "literalinclude",
+    "index",
+    "sectnum",
+];
+
+const BULLETS: [char; 6] = ['-', '*', '+', '•', '‣', '⁃'];
+
+/// Converts a reStructuredText document to markdown. A title opening the
+/// document and the field list after it, `:date:`, `:tags:` and the other
+/// front matter keys, are returned as its front matter.
+///
+/// Sections, inline markup, hyperlinks and their targets, footnotes, lists,
+/// definition and field lists, literal blocks, tables and the common
+/// directives are converted; admonitions like `.. note::` become `:::`
+/// callouts. Tables with cells spanning several columns or rows are kept
+/// preformatted, and comments are left out.
+pub fn to_markdown(content: &str) -> (Map<String, Value>, String) {
+    let content = content.replace('\t', "        ");
+    let lines: Vec<&str> = content.lines().map(str::trim_end).collect();
+    let mut document = Document::default();
+    document.scan(&lines);
+
+    let mut start = 0;
+    while lines.get(start).is_some_and(|line| line.is_empty()) {
+        start += 1;
+    }
+    if let Some((style, title, len)) = section_title(&lines[start..]) {
+        set_metadata(&mut document.metadata, "title", title);
+        document.styles.push(style);
+        document.has_title = true;
+        start += len;
+    }
+    while lines.get(start).is_some_and(|line| line.is_empty()) {
+        start += 1;
+    }
+    while let Some((key, value)) = lines.get(start).and_then(|line| field(line)) {
+        let end = indented_block(&lines, start + 1, 1);
+        let mut value = value.to_string();
+        for line in &lines[start + 1..end] {
+            value.push(' ');
+            value.push_str(line.trim());
+        }
+        set_metadata(&mut document.metadata, key, &value);
+        start = end;
+    }
+
+    let markdown = document.convert(&lines[start..]);
+    (document.metadata, markdown)
+}
+
+#[derive(Default)]
+struct Document {
+    metadata: Map<String, Value>,
+    /// Names of the hyperlink targets with a URL, normalized.
+    targets: HashSet<String>,
+    /// The markdown of each `|name|` substitution.
+    substitutions: HashMap<String, String>,
+    /// Section title styles, the adornment character and whether it has an
+    /// overline, in the order they first appear.
+    styles: Vec<(char, bool)>,
+    /// Whether the document opens with a title, which takes the first style.
+    has_title: bool,
+    /// Ids of the sections internal targets, `.. _name:`, stand before, by
+    /// the targets' normalized names.
+    sections: HashMap<String, String>,
+    /// Number of `[#]_` footnote references and `.. [#]` footnotes so far.
+    auto_references: usize,
+    auto_footnotes: usize,
+}
+
+impl Document {
+    /// Collects hyperlink targets and substitution definitions, which may be
+    /// referenced before they are defined.
+    fn scan(&mut self, lines: &[&str]) {
+        for (i, line) in lines.iter().enumerate() {
+            let Some(target) = line.trim_start().strip_prefix(".. _") else {
+                continue;
+            };
+            let Some((name, url)) = target_parts(target) else {
+                continue;
+            };
+            if !url.is_empty() {
+                if !url.ends_with('_') {
+                    self.targets.insert(normalize(name));
+                }
+                continue;
+            }
+            let next = lines[i + 1..]
+                .iter()
+                .position(|line| !line.is_empty() && !line.starts_with(".. _"))
+                .map_or(lines.len(), |next| i + 1 + next);
+            if let Some((_, title, _)) = section_title(&lines[next..]) {
+                self.sections.insert(normalize(name), slug::slugify(title));
+            }
+        }
+        for (i, line) in lines.iter().enumerate() {
+            let Some(definition) = line.trim_start().strip_prefix(".. |") else {
+                continue;
+            };
+            let Some((name, directive)) = definition.split_once('|') else {
+                continue;
+            };
+            let Some((directive, argument)) = directive.trim().split_once("::") else {
+                continue;
+            };
+            let markdown = match directive {
+                "replace" => self.inline(argument.trim()),
+                "image" => {
+                    let end = indented_block(lines, i + 1, 1);
+                    let (options, _) = directive_body(&dedent(&lines[i + 1..end]));
+                    image(argument.trim(), &options, name)
+                }
+                _ => continue,
+            };
+            self.substitutions.insert(name.to_string(), markdown);
+        }
+    }
+
+    /// The markdown of the body elements in `lines`.
+    fn convert(&mut self, lines: &[&str]) -> String {
+        let mut output = String::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.is_empty() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            let trimmed = line.trim_start();
+
+            if trimmed.len() < line.len() {
+                i = indented_block(lines, i, 1);
+                for line in self.convert(&dedent(&lines[start..i])).lines() {
+                    output.push_str(format!("> {}", line).trim_end());
+                    output.push('\n');
+                }
+            } else if let Some((style, title, len)) = section_title(&lines[i..]) {
+                i += len;
+                self.section(style, title, &mut output);
+            } else if is_adornment(line)
+                && line.len() >= 4
+                && lines.get(i + 1).is_none_or(|line| line.is_empty())
+            {
+                i += 1;
+                output.push_str("---\n");
+            } else if line == ".." || line.starts_with(".. ") {
+                i = indented_block(lines, i + 1, 1);
+                self.explicit_markup(&line[2..], &lines[start + 1..i], &mut output);
+            } else if line.starts_with(">>>") {
+                while lines.get(i).is_some_and(|line| !line.is_empty()) {
+                    i += 1;
+                }
+                code_block("python", &lines[start..i], &mut output);
+            } else if line.starts_with("+-") && line.ends_with('+') {
+                while lines
+                    .get(i)
+                    .is_some_and(|line| line.starts_with(['+', '|']))
+                {
+                    i += 1;
+                }
+                self.grid_table(&lines[start..i], &mut output);
+            } else if is_simple_table_border(line)
+                && lines.get(i + 1).is_some_and(|line| !line.is_empty())
+            {
+                i = simple_table_end(lines, i);
+                self.simple_table(&lines[start..i], &mut output);
+            } else if list_marker(line).is_some() {
+                i = self.list(lines, i, &mut output);
+            } else if let Some((name, value)) = field(line) {
+                i = indented_block(lines, i + 1, 1);
+                let mut body = vec![value];
+                body.extend(dedent(&lines[start + 1..i]));
+                self.definition(name, &body, &mut output);
+            } else if line == "|" || line.starts_with("| ") {
+                while lines
+                    .get(i)
+                    .is_some_and(|line| *line == "|" || line.starts_with("| "))
+                {
+                    i += 1;
+                }
+                self.line_block(&lines[start..i], &mut output);
+            } else if lines.get(i + 1).is_some_and(|next| next.starts_with(' ')) {
+                i = indented_block(lines, i + 1, 1);
+                self.definition(line, &dedent(&lines[start + 1..i]), &mut output);
+            } else {
+                while lines
+                    .get(i)
+                    .is_some_and(|line| !line.is_empty() && !line.starts_with(' '))
+                {
+                    i += 1;
+                }
+                i = self.paragraph(lines, start, i, &mut output);
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    fn section(&mut self, style: (char, bool), title: &str, output: &mut String) {
+        let index = match self.styles.iter().position(|known| *known == style) {
+            Some(index) => index,
+            None => {
+                self.styles.push(style);
+                self.styles.len() - 1
+            }
+        };
+        // Second-level headings and deeper, since the title is the page's
+        // only first-level heading.
+        let level = match self.has_title {
+            true => index.max(1) + 1,
+            false => index + 2,
+        };
+        output.push_str(&"#".repeat(level.min(6)));
+        output.push(' ');
+        output.push_str(&self.inline(title));
+        output.push('\n');
+    }
+
+    /// Writes the paragraph of `lines[start..end]`, and the literal block
+    /// after it if it ends with `::`. Returns the line after both.
+    fn paragraph(
+        &mut self,
+        lines: &[&str],
+        start: usize,
+        end: usize,
+        output: &mut String,
+    ) -> usize {
+        let mut text: Vec<&str> = lines[start..end].to_vec();
+        let last = text.len() - 1;
+        let literal = text[last].ends_with("::");
+        if literal {
+            text[last] = match text[last].strip_suffix("::").unwrap() {
+                "" => "",
+                rest if rest.ends_with(' ') => rest.trim_end(),
+                _ => text[last].strip_suffix(':').unwrap(),
+            };
+        }
+        for line in text.iter().filter(|line| !line.is_empty()) {
+            let markdown = self.inline(line);
+            if markdown.starts_with(['#', '>']) {
+                output.push('\\');
+            }
+            output.push_str(&markdown);
+            output.push('\n');
+        }
+        if !literal {
+            return end;
+        }
+        let mut block_start = end;
+        while lines.get(block_start).is_some_and(|line| line.is_empty()) {
+            block_start += 1;
+        }
+        if !lines
+            .get(block_start)
+            .is_some_and(|line| line.starts_with(' '))
+        {
+            return end;
+        }
+        let block_end = indented_block(lines, block_start, 1);
+        output.push('\n');
+        code_block("", &dedent(&lines[block_start..block_end]), output);
+        block_end
+    }
+
+    /// Writes the comment, target, footnote, substitution definition or
+    /// directive of an explicit markup block, `text` being what follows its
+    /// `..` and `body` its indented lines.
+    fn explicit_markup(&mut self, text: &str, body: &[&str], output: &mut String) {
+        let text = text.trim();
+        if let Some(target) = text.strip_prefix('_') {
+            let Some((name, url)) = target_parts(target) else {
+                return;
+            };
+            let mut url = url.to_string();
+            for line in body {
+                url.push_str(line.trim());
+            }
+            if !url.is_empty() && !url.ends_with('_') && name != "_" {
+                output.push_str(&format!("[{}]: <{}>\n", normalize(name), url));
+            }
+            return;
+        }
+        if let Some(footnote) = text.strip_prefix('[') {
+            let Some((label, first)) = footnote.split_once(']') else {
+                return;
+            };
+            let label = self.footnote_label(label, true);
+            let mut lines = vec![first.trim()];
+            lines.extend(dedent(body));
+            let markdown = self.convert(&lines);
+            output.push_str(&format!("[^{}]: ", label));
+            for (i, line) in markdown.trim().lines().enumerate() {
+                if i > 0 && !line.is_empty() {
+                    output.push_str("    ");
+                }
+                output.push_str(line);
+                output.push('\n');
+            }
+            return;
+        }
+        if text.starts_with('|') {
+            return;
+        }
+        if let Some((name, argument)) = text.split_once("::") {
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_:.+".contains(c))
+            {
+                self.directive(name, argument.trim(), &dedent(body), output);
+            }
+        }
+    }
+
+    fn directive(&mut self, name: &str, argument: &str, body: &[&str], output: &mut String) {
+        let name = name.rsplit(':').next().unwrap_or(name);
+        let (options, content) = directive_body(body);
+        let option = |key: &str| {
+            options
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| *value)
+        };
+        match name {
+            "code" | "code-block" | "sourcecode" => {
+                let mut info = argument.to_string();
+                if let Some(caption) = option("caption") {
+                    info.push_str(&format!(" title=\"{}\"", caption.replace('"', "'")));
+                }
+                if let Some(lines) = option("emphasize-lines") {
+                    info.push_str(&format!(" {{{}}}", lines));
+                }
+                code_block(info.trim(), content, output);
+            }
+            "math" => {
+                self.metadata.entry("math").or_insert(Value::Bool(true));
+                output.push_str("$$\n");
+                for line in std::iter::once(argument).chain(content.iter().copied()) {
+                    if !line.is_empty() {
+                        output.push_str(line);
+                        output.push('\n');
+                    }
+                }
+                output.push_str("$$\n");
+            }
+            "raw" => {
+                if argument.split_whitespace().any(|format| format == "html") {
+                    for line in content {
+                        output.push_str(line);
+                        output.push('\n');
+                    }
+                }
+            }
+            "image" => {
+                output.push_str(&image(argument, &options, ""));
+                output.push('\n');
+            }
+            "figure" => {
+                output.push_str(&image(argument, &options, ""));
+                output.push_str("\n\n");
+                let caption = content
+                    .iter()
+                    .position(|line| line.is_empty())
+                    .unwrap_or(content.len());
+                if caption > 0 {
+                    let text: Vec<String> = content[..caption]
+                        .iter()
+                        .map(|line| self.inline(line))
+                        .collect();
+                    output.push_str(&format!("*{}*\n\n", text.join(" ")));
+                }
+                output.push_str(&self.convert(&content[caption..]));
+            }
+            "contents" => {
+                self.metadata.entry("toc").or_insert(Value::Bool(true));
+            }
+            "rubric" => output.push_str(&format!("**{}**\n", self.inline(argument))),
+            "topic" | "sidebar" => {
+                output.push_str(&format!("**{}**\n\n", self.inline(argument)));
+                output.push_str(&self.convert(content));
+            }
+            "epigraph" | "highlights" | "pull-quote" => {
+                for line in self.convert(content).lines() {
+                    output.push_str(format!("> {}", line).trim_end());
+                    output.push('\n');
+                }
+            }
+            name if IGNORED_DIRECTIVES.contains(&name) => {}
+            _ => match ADMONITIONS
+                .iter()
+                .find(|(directive, _, _)| *directive == name)
+            {
+                Some((directive, kind, title)) => {
+                    let (title, markdown) = match *directive {
+                        "admonition" => (self.inline(argument), self.convert(content)),
+                        _ => {
+                            // Text after the directive's name starts its content.
+                            let mut lines = vec![argument];
+                            match options.is_empty() {
+                                true => lines.extend(body),
+                                false => lines.extend(content),
+                            }
+                            (title.to_string(), self.convert(&lines))
+                        }
+                    };
+                    let fence = ":".repeat(container_colons(&markdown));
+                    let opening = format!("{}{} {}", fence, kind, title);
+                    output.push_str(&format!(
+                        "{}\n{}\n{}\n",
+                        opening.trim_end(),
+                        markdown.trim_end(),
+                        fence
+                    ));
+                }
+                None => output.push_str(&self.convert(content)),
+            },
+        }
+    }
+
+    /// Writes the list whose first item is on `lines[start]`, returning the
+    /// line after it.
+    fn list(&mut self, lines: &[&str], start: usize, output: &mut String) -> usize {
+        let (first_marker, _) = list_marker(lines[start]).unwrap();
+        let ordered = !first_marker.starts_with(BULLETS);
+        let mut number = first_marker
+            .trim_matches(['(', ')', '.'])
+            .parse::<usize>()
+            .unwrap_or(1);
+        let mut i = start;
+        loop {
+            let (_, width) = list_marker(lines[i]).unwrap();
+            let end = indented_block(lines, i + 1, width);
+            let mut body = vec![&lines[i][width..]];
+            body.extend(
+                lines[i + 1..end]
+                    .iter()
+                    .map(|line| line.get(width..).unwrap_or("")),
+            );
+            let bullet = match ordered {
+                true => format!("{}. ", number),
+                false => "- ".to_string(),
+            };
+            for (n, line) in self.convert(&body).trim_end().lines().enumerate() {
+                match n {
+                    0 => output.push_str(&bullet),
+                    _ if !line.is_empty() => output.push_str(&" ".repeat(bullet.len())),
+                    _ => {}
+                }
+                output.push_str(line);
+                output.push('\n');
+            }
+            number += 1;
+
+            let mut next = end;
+            while lines.get(next).is_some_and(|line| line.is_empty()) {
+                next += 1;
+            }
+            let same_list = lines
+                .get(next)
+                .and_then(|line| list_marker(line))
+                .is_some_and(|(marker, _)| {
+                    marker.starts_with(BULLETS) != ordered && (ordered || marker == first_marker)
+                });
+            if !same_list {
+                return end;
+            }
+            i = next;
+        }
+    }
+
+    /// Writes a definition list entry, as a term followed by a `: ` line.
+    fn definition(&mut self, term: &str, body: &[&str], output: &mut String) {
+        let term = term.split(" : ").next().unwrap_or(term);
+        output.push_str(&self.inline(term));
+        output.push_str("\n: ");
+        let markdown = self.convert(body);
+        let lines: Vec<&str> = markdown.lines().filter(|line| !line.is_empty()).collect();
+        output.push_str(&lines.join("\n"));
+        output.push('\n');
+    }
+
+    /// Writes a line block, its lines divided by hard breaks.
+    fn line_block(&mut self, lines: &[&str], output: &mut String) {
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| self.inline(line[1..].trim()))
+            .collect();
+        for paragraph in lines.split(String::is_empty) {
+            if !paragraph.is_empty() {
+                output.push_str(&paragraph.join("\\\n"));
+                output.push_str("\n\n");
+            }
+        }
+    }
+
+    /// Writes a grid table, which is kept preformatted if a cell spans
+    /// several columns or rows.
+    fn grid_table(&mut self, lines: &[&str], output: &mut String) {
+        let border = |line: &str| line.starts_with('+') && line.chars().all(|c| "+-=".contains(c));
+        // Columns are counted in characters, so cells can hold any text.
+        let columns: Vec<usize> = lines[0]
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c == '+')
+            .map(|(i, _)| i)
+            .collect();
+        let width = lines[0].chars().count();
+        let regular = lines.iter().all(|line| {
+            let separator = if border(line) { '+' } else { '|' };
+            let chars: Vec<char> = line.chars().collect();
+            chars.len() == width
+                && columns.iter().all(|&i| chars[i] == separator)
+                && chars.iter().filter(|&&c| c == separator).count() == columns.len()
+        });
+        if !regular || columns.len() < 2 || !border(lines[lines.len() - 1]) {
+            code_block("", lines, output);
+            return;
+        }
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut cells: Vec<Vec<&str>> = vec![Vec::new(); columns.len() - 1];
+        for line in &lines[1..] {
+            if border(line) {
+                rows.push(
+                    cells
+                        .iter()
+                        .map(|cell| self.cell(&cell.join(" ")))
+                        .collect(),
+                );
+                cells.iter_mut().for_each(Vec::clear);
+                continue;
+            }
+            for (cell, bounds) in cells.iter_mut().zip(columns.windows(2)) {
+                let text = char_columns(line, bounds[0] + 1, bounds[1]).trim();
+                if !text.is_empty() {
+                    cell.push(text);
+                }
+            }
+        }
+        table(&rows, output);
+    }
+
+    /// Writes a simple table, whose columns are set by the `=` runs of its
+    /// first border. Tables with column spans are kept preformatted.
+    fn simple_table(&mut self, lines: &[&str], output: &mut String) {
+        let first = lines[0];
+        let mut columns = Vec::new();
+        let mut position = 0;
+        while let Some(start) = first[position..].find('=') {
+            let start = position + start;
+            let end = first[start..]
+                .find(' ')
+                .map_or(first.len(), |end| start + end);
+            columns.push(start);
+            position = end;
+        }
+        let spans = lines[1..]
+            .iter()
+            .any(|line| !line.is_empty() && line.chars().all(|c| c == '-' || c == ' '));
+        if spans {
+            code_block("", lines, output);
+            return;
+        }
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut cells: Vec<String> = Vec::new();
+        for line in &lines[1..] {
+            if line.is_empty() || is_simple_table_border(line) {
+                if !cells.is_empty() {
+                    rows.push(cells.iter().map(|cell| self.cell(cell)).collect());
+                    cells.clear();
+                }
+                continue;
+            }
+            let text: Vec<&str> = (0..columns.len())
+                .map(|c| {
+                    let end = columns.get(c + 1).copied().unwrap_or(usize::MAX);
+                    char_columns(line, columns[c], end).trim()
+                })
+                .collect();
+            if !cells.is_empty() && text[0].is_empty() {
+                for (cell, text) in cells.iter_mut().zip(text) {
+                    if !text.is_empty() {
+                        cell.push(' ');
+                        cell.push_str(text);
+                    }
+                }
+                continue;
+            }
+            if !cells.is_empty() {
+                rows.push(cells.iter().map(|cell| self.cell(cell)).collect());
+            }
+            cells = text.iter().map(|text| text.to_string()).collect();
+        }
+        table(&rows, output);
+    }
+
+    fn cell(&mut self, text: &str) -> String {
+        self.inline(text.trim()).replace('|', "\\|")
+    }
+
+    /// The label of a footnote, `[^label]` in markdown. Auto-numbered
+    /// footnotes, `[#]` and `[*]`, are numbered in order of appearance, the
+    /// references apart from the footnotes.
+    fn footnote_label(&mut self, label: &str, definition: bool) -> String {
+        if label != "#" && label != "*" {
+            return label.trim_start_matches('#').to_string();
+        }
+        let count = match definition {
+            true => &mut self.auto_footnotes,
+            false => &mut self.auto_references,
+        };
+        *count += 1;
+        format!("auto-{}", count)
+    }
+
+    /// reStructuredText inline markup converted to markdown's, with
+    /// markdown's special characters in the rest escaped.
+    fn inline(&mut self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < text.len() {
+            let rest = &text[i..];
+            let previous = text[..i].chars().next_back();
+            let c = rest.chars().next().unwrap();
+            if c == '\\' {
+                // An escaped space is removed, other characters are kept.
+                match rest[1..].chars().next() {
+                    Some(' ') => i += 2,
+                    Some(escaped) => {
+                        escape_markdown(&escaped.to_string(), &mut output);
+                        i += 1 + escaped.len_utf8();
+                    }
+                    None => i += 1,
+                }
+                continue;
+            }
+            if opens_after(previous) {
+                if let Some((markdown, len)) = self.markup(rest) {
+                    output.push_str(&markdown);
+                    i += len;
+                    continue;
+                }
+                if ["https://", "http://", "mailto:"]
+                    .iter()
+                    .any(|scheme| rest.starts_with(scheme))
+                {
+                    let end = rest
+                        .find(|c: char| c.is_whitespace() || c == '<' || c == '>')
+                        .unwrap_or(rest.len());
+                    let url = rest[..end]
+                        .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'', '"']);
+                    output.push_str(&format!("<{}>", url));
+                    i += url.len();
+                    continue;
+                }
+            }
+            escape_markdown(&rest[..c.len_utf8()], &mut output);
+            i += c.len_utf8();
+        }
+        output
+    }
+
+    /// The markdown of the inline markup at the start of `text`, and its
+    /// length, if some starts there.
+    fn markup(&mut self, text: &str) -> Option<(String, usize)> {
+        let closes = |rest: &str| closes_before(rest.chars().next());
+        if text.starts_with("``") {
+            let end = markup_end(text, 2, "``", closes)?;
+            return Some((code_span(&text[2..end]), end + 2));
+        }
+        if text.starts_with("**") {
+            let end = markup_end(text, 2, "**", closes)?;
+            let inner = &text[2..end];
+            return Some((format!("**{}**", self.inline(inner)), end + 2));
+        }
+        if text.starts_with('*') {
+            let end = markup_end(text, 1, "*", closes)?;
+            let inner = &text[1..end];
+            return Some((format!("*{}*", self.inline(inner)), end + 1));
+        }
+        if let Some(role) = text.strip_prefix(':') {
+            let name = &role[..role.find(":`")?];
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_+.:".contains(c))
+            {
+                return None;
+            }
+            let start = name.len() + 3;
+            let end = markup_end(text, start, "`", closes)?;
+            return Some((self.role(name, &text[start..end]), end + 1));
+        }
+        if text.starts_with('`') {
+            let end = markup_end(text, 1, "`", |rest| {
+                closes_before(rest[reference_suffix(rest)..].chars().next())
+            })?;
+            let inner = &text[1..end];
+            let suffix_len = reference_suffix(&text[end + 1..]);
+            let suffix = &text[end + 1..end + 1 + suffix_len];
+            let markdown = match suffix {
+                "" => format!("*{}*", self.inline(inner)),
+                "_" | "__" => self.reference(inner),
+                role => self.role(role.trim_matches(':'), inner),
+            };
+            return Some((markdown, end + 1 + suffix_len));
+        }
+        if let Some(footnote) = text.strip_prefix('[') {
+            let end = footnote.find("]_")?;
+            let label = &footnote[..end];
+            let valid = !label.is_empty()
+                && label
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "#*-_.".contains(c))
+                && closes(&footnote[end + 2..]);
+            if !valid {
+                return None;
+            }
+            let label = self.footnote_label(label, false);
+            return Some((format!("[^{}]", label), end + 3));
+        }
+        if let Some(substitution) = text.strip_prefix('|') {
+            let end = substitution.find('|')?;
+            let markdown = self.substitutions.get(&substitution[..end])?.clone();
+            let after = &substitution[end + 1..];
+            let underscores = after.len() - after.trim_start_matches('_').len();
+            return Some((markdown, end + 2 + underscores.min(2)));
+        }
+        // A simple reference, `name_`, to a target with a URL.
+        let len = text
+            .find(|c: char| !c.is_alphanumeric() && !"-_.+".contains(c))
+            .unwrap_or(text.len());
+        let word = text[..len].trim_end_matches(['.', '-', '+']);
+        let len = word.len();
+        let name = word.strip_suffix('_')?;
+        if name.ends_with('_') || !self.targets.contains(&normalize(name)) {
+            return None;
+        }
+        Some((self.reference(name), len))
+    }
+
+    /// A hyperlink reference, `` `text <url>`_ `` or `` `name`_ ``.
+    fn reference(&mut self, text: &str) -> String {
+        let (title, target) = split_target(text);
+        match target {
+            Some(target) if target.ends_with('_') => {
+                self.named_reference(title, target.trim_end_matches('_'))
+            }
+            Some(target) => {
+                let title = if title.is_empty() { target } else { title };
+                let url: String = target.split_whitespace().collect();
+                format!("[{}](<{}>)", self.inline(title), url)
+            }
+            None => self.named_reference(title, title),
+        }
+    }
+
+    /// A link to the target `name`, or to the section it names if there is
+    /// no such target.
+    fn named_reference(&mut self, title: &str, name: &str) -> String {
+        let name = normalize(name);
+        match self.targets.contains(&name) {
+            true => format!("[{}][{}]", self.inline(title), name),
+            false => format!("[{}](#{})", self.inline(title), self.section_id(&name)),
+        }
+    }
+
+    /// The id of the section the internal target `name` stands before, or
+    /// else of the section titled `name`.
+    fn section_id(&self, name: &str) -> String {
+        let name = normalize(name);
+        match self.sections.get(&name) {
+            Some(id) => id.clone(),
+            None => slug::slugify(&name),
+        }
+    }
+
+    /// The markdown of interpreted text with the role `role`.
+    fn role(&mut self, role: &str, text: &str) -> String {
+        let role = role.rsplit(':').next().unwrap_or(role);
+        let (title, target) = split_target(text);
+        match role {
+            role if CODE_ROLES.contains(&role) => {
+                let title = title.trim_start_matches('!');
+                let title = match title.strip_prefix('~') {
+                    Some(path) => path.rsplit('.').next().unwrap_or(path),
+                    None => title,
+                };
+                code_span(title)
+            }
+            "math" => {
+                self.metadata.entry("math").or_insert(Value::Bool(true));
+                format!("${}$", text)
+            }
+            "sub" | "subscript" => format!("<sub>{}</sub>", self.inline(text)),
+            "sup" | "superscript" => format!("<sup>{}</sup>", self.inline(text)),
+            "emphasis" | "title-reference" | "title" | "t" => format!("*{}*", self.inline(text)),
+            "strong" => format!("**{}**", self.inline(text)),
+            "ref" | "numref" => {
+                let id = self.section_id(target.unwrap_or(title));
+                format!("[{}](#{})", self.inline(title), id)
+            }
+            "abbr" => {
+                let abbreviation = text.split(" (").next().unwrap_or(text);
+                self.inline(abbreviation)
+            }
+            _ => self.inline(title),
+        }
+    }
+}
+
+/// A section title at the start of `lines`, with its style, its text and
+/// the number of lines it takes up.
+fn section_title<'a>(lines: &[&'a str]) -> Option<((char, bool), &'a str, usize)> {
+    let first = *lines.first()?;
+    let second = *lines.get(1)?;
+    if is_adornment(first) {
+        let third = *lines.get(2)?;
+        let title = second.trim();
+        let adornment = first.chars().next()?;
+        let valid =
+            !title.is_empty() && third == first && first.chars().count() >= title.chars().count();
+        return valid.then_some(((adornment, true), title, 3));
+    }
+    let valid = !first.starts_with(' ')
+        && is_adornment(second)
+        && second.chars().count() >= first.chars().count().min(4);
+    valid.then(|| ((second.chars().next().unwrap(), false), first.trim(), 2))
+}
+
+/// Whether `line` is a row of one punctuation character, as under section
+/// titles and in transitions.
+fn is_adornment(line: &str) -> bool {
+    let mut chars = line.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    line.len() >= 2 && first.is_ascii_punctuation() && chars.all(|c| c == first)
+}
+
+/// The characters of `line` from column `start` up to column `end`, either of
+/// which may be past its end.
+fn char_columns(line: &str, start: usize, end: usize) -> &str {
+    let offset = |column| {
+        line.char_indices()
+            .nth(column)
+            .map_or(line.len(), |(i, _)| i)
+    };
+    &line[offset(start)..offset(end.max(start))]
+}
+
+fn is_simple_table_border(line: &str) -> bool {
+    line.starts_with("==") && line.contains(' ') && line.chars().all(|c| c == '=' || c == ' ')
+}
+
+/// The line after the simple table whose first border is `lines[start]`:
+/// the one after its second border if a blank line follows, and after the
+/// third otherwise.
+fn simple_table_end(lines: &[&str], start: usize) -> usize {
+    let mut borders = 0;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        if !is_simple_table_border(line) {
+            continue;
+        }
+        borders += 1;
+        if borders == 2 || lines.get(i + 1).is_none_or(|line| line.is_empty()) {
+            return i + 1;
+        }
+    }
+    lines.len()
+}
+
+/// The marker of the list item `line` opens, and the column its text starts
+/// at.
+fn list_marker(line: &str) -> Option<(&str, usize)> {
+    let marker_len = if line.starts_with(BULLETS) {
+        line.chars().next().unwrap().len_utf8()
+    } else {
+        let body = line.strip_prefix('(').unwrap_or(line);
+        let digits = body.len() - body.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let digits = if body.starts_with('#') { 1 } else { digits };
+        if digits == 0 {
+            return None;
+        }
+        let after = &body[digits..];
+        let closing = match line.starts_with('(') {
+            true => after.starts_with(')'),
+            false => after.starts_with(['.', ')']),
+        };
+        if !closing {
+            return None;
+        }
+        line.len() - body.len() + digits + 1
+    };
+    let text = line[marker_len..].strip_prefix(' ')?;
+    if text.trim().is_empty() {
+        return None;
+    }
+    let width = line.len() - text.trim_start().len();
+    Some((&line[..marker_len], width))
+}
+
+/// A field, `:name: body`, as its name and the body on its first line.
+fn field(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest
+        .find(": ")
+        .or_else(|| rest.ends_with(':').then(|| rest.len() - 1))?;
+    let name = &rest[..end];
+    let valid = !name.is_empty() && !name.starts_with(' ') && !name.contains('`');
+    valid.then(|| (name, rest[end + 1..].trim()))
+}
+
+/// The line after the block starting at `lines[start]` whose lines are all
+/// blank or indented by `indent` or more, trailing blank lines left out.
+fn indented_block(lines: &[&str], start: usize, indent: usize) -> usize {
+    let mut end = start;
+    let mut last = start;
+    while let Some(line) = lines.get(end) {
+        if line.is_empty() {
+            end += 1;
+            continue;
+        }
+        if line.len() - line.trim_start().len() < indent {
+            break;
+        }
+        end += 1;
+        last = end;
+    }
+    last
+}
+
+/// The options at the start of a directive's body, and the content after
+/// them.
+fn directive_body<'a, 'b>(body: &'b [&'a str]) -> (Vec<(&'a str, &'a str)>, &'b [&'a str]) {
+    let mut options = Vec::new();
+    let mut i = 0;
+    while let Some((name, value)) = body.get(i).and_then(|line| field(line)) {
+        options.push((name, value));
+        i += 1;
+    }
+    while body.get(i).is_some_and(|line| line.is_empty()) {
+        i += 1;
+    }
+    (options, &body[i..])
+}
+
+/// The markdown of an image directive, with its `:alt:` and `:target:`.
+fn image(uri: &str, options: &[(&str, &str)], default_alt: &str) -> String {
+    let option = |key: &str| {
+        options
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| *value)
+    };
+    let mut alt = String::new();
+    escape_markdown(option("alt").unwrap_or(default_alt), &mut alt);
+    let image = format!("![{}](<{}>)", alt, uri.trim());
+    match option("target") {
+        Some(target) => format!("[{}](<{}>)", image, target),
+        None => image,
+    }
+}
+
+/// A markdown table of `rows`, the first being its header.
+fn table(rows: &[Vec<String>], output: &mut String) {
+    let Some(columns) = rows.iter().map(Vec::len).max() else {
+        return;
+    };
+    for (i, row) in rows.iter().enumerate() {
+        let mut cells = row.clone();
+        cells.resize(columns, String::new());
+        output.push_str(&format!("| {} |\n", cells.join(" | ")));
+        if i == 0 {
+            output.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+        }
+    }
+}
+
+/// The name and URL of a hyperlink target, what follows its `.. _`.
+fn target_parts(target: &str) -> Option<(&str, &str)> {
+    let (name, url) = match target.strip_prefix('`') {
+        Some(quoted) => {
+            let (name, rest) = quoted.split_once('`')?;
+            (name, rest.strip_prefix(':')?)
+        }
+        None => target
+            .split_once(": ")
+            .or_else(|| target.strip_suffix(':').map(|name| (name, "")))?,
+    };
+    Some((name, url.trim()))
+}
+
+/// Text with an embedded target, `title <target>`, split in two.
+fn split_target(text: &str) -> (&str, Option<&str>) {
+    match text
+        .strip_suffix('>')
+        .and_then(|text| text.rsplit_once('<'))
+    {
+        Some((title, target)) if title.is_empty() || title.ends_with(char::is_whitespace) => {
+            (title.trim(), Some(target))
+        }
+        _ => (text, None),
+    }
+}
+
+/// A reference name as targets and references are matched: lowercase, its
+/// whitespace collapsed.
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Length of the `_`, `__` or `:role:` after interpreted text, if `text`
+/// starts with one.
+fn reference_suffix(text: &str) -> usize {
+    if text.starts_with("__") {
+        return 2;
+    }
+    if text.starts_with('_') {
+        return 1;
+    }
+    let Some(role) = text.strip_prefix(':') else {
+        return 0;
+    };
+    role.find(':')
+        .filter(|&end| {
+            end > 0
+                && role[..end]
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_+.".contains(c))
+        })
+        .map_or(0, |end| end + 2)
+}
+
+/// Whether inline markup can start after `c`.
+fn opens_after(c: Option<char>) -> bool {
+    c.is_none_or(|c| c.is_whitespace() || "'\"([{<-/:".contains(c))
+}
+
+/// Whether inline markup can end before `c`.
+fn closes_before(c: Option<char>) -> bool {
+    c.is_none_or(|c| c.is_whitespace() || "-.,:;!?\\/'\")]}>".contains(c))
+}
+
+/// The position of the end-string `end` closing the inline markup at the
+/// start of `text`, whose content starts at `start`. The content can't
+/// start or end with whitespace, and the end-string must be `followed` by
+/// what may close markup.
+fn markup_end(
+    text: &str,
+    start: usize,
+    end: &str,
+    followed: impl Fn(&str) -> bool,
+) -> Option<usize> {
+    if text[start..].starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut from = start;
+    while let Some(found) = text.get(from..)?.find(end) {
+        let position = from + found;
+        let before = text[..position].chars().next_back();
+        if position > start
+            && before.is_some_and(|c| !c.is_whitespace() && c != '\\')
+            && followed(&text[position + end.len()..])
+        {
+            return Some(position);
+        }
+        from = position + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown(rst: &str) -> String {
+        to_markdown(rst).1
+    }
+
+    #[test]
+    fn converts_title_fields_and_sections() {
+        let (metadata, markdown) = to_markdown(
+            "Hello World\n===========\n\n:date: 2024-03-01\n:tags: rust, web\n\n\
+             Intro *text*.\n\nSection\n-------\n\nBody.\n",
+        );
+        assert_eq!(metadata["title"], "Hello World");
+        assert_eq!(metadata["date"], "2024-03-01");
+        assert_eq!(metadata["tags"], serde_json::json!(["rust", "web"]));
+        assert_eq!(markdown, "Intro *text*.\n\n## Section\n\nBody.\n\n");
+    }
+
+    #[test]
+    fn converts_lists() {
+        assert_eq!(
+            markdown("- one\n- two\n\n  - nested\n\n1. first\n2. second\n"),
+            "- one\n- two\n\n  - nested\n\n1. first\n2. second\n\n"
+        );
+    }
+
+    #[test]
+    fn converts_tables_with_non_ascii_text() {
+        let table = "| Name | Thé |\n| --- | --- |\n| café | — yes |\n";
+        assert_eq!(
+            markdown(
+                "+------+-------+\n| Name | Thé   |\n+======+=======+\n\
+                 | café | — yes |\n+------+-------+\n"
+            ),
+            format!("{}\n", table)
+        );
+        assert_eq!(
+            markdown("=====  =====\nName   Thé\n=====  =====\ncafé   — yes\n=====  =====\n"),
+            format!("{}\n", table)
+        );
+    }
+
+    #[test]
+    fn keeps_tables_with_spans_preformatted() {
+        let markdown = markdown("+---+---+\n| a     |\n+---+---+\n| b | c |\n+---+---+\n");
+        assert!(markdown.starts_with("```\n+---+---+\n"), "{}", markdown);
+    }
+
+    #[test]
+    fn converts_admonitions() {
+        assert_eq!(
+            markdown(".. note::\n\n   Take *care*.\n\n.. caution:: Hot\n"),
+            ":::note\nTake *care*.\n:::\n\n:::warning Caution\nHot\n:::\n\n"
+        );
+    }
+}
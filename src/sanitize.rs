@@ -0,0 +1,236 @@
+use crate::config::SanitizeConfig;
+use crate::markdown::RenderError;
+use lol_html::html_content::Element;
+use lol_html::{doc_comments, element, rewrite_str, HandlerResult, RewriteStrSettings};
+use pulldown_cmark::Event;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+
+/// Elements whose content isn't markup, so it can't be kept once they are
+/// dropped without turning text like `&lt;script&gt;` into a live tag.
+const DROPPED_WITH_CONTENT: [&str; 11] = [
+    "script",
+    "style",
+    "template",
+    "textarea",
+    "title",
+    "iframe",
+    "noembed",
+    "noframes",
+    "noscript",
+    "plaintext",
+    "xmp",
+];
+
+/// Attributes holding a URL, checked against `SAFE_SCHEMES`.
+const URL_ATTRIBUTES: [&str; 6] = ["href", "src", "cite", "poster", "action", "formaction"];
+
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Attribute names allowed on any element, see `SanitizeConfig::attributes`.
+const ANY_ELEMENT: &str = "*";
+
+/// Strips the raw HTML in posts down to the elements and attributes allowed
+/// by `[sanitize]` in the config.
+pub struct Sanitizer {
+    tags: HashSet<String>,
+    attributes: HashMap<String, HashSet<String>>,
+    end_tag: Regex,
+}
+
+impl Sanitizer {
+    pub fn new(config: &SanitizeConfig) -> Sanitizer {
+        Sanitizer {
+            tags: config.tags.iter().map(|tag| tag.to_lowercase()).collect(),
+            attributes: config
+                .attributes
+                .iter()
+                .map(|(tag, names)| {
+                    let names = names.iter().map(|name| name.to_lowercase()).collect();
+                    (tag.to_lowercase(), names)
+                })
+                .collect(),
+            end_tag: Regex::new(r"</([a-zA-Z][a-zA-Z0-9-]*)\s*>").unwrap(),
+        }
+    }
+
+    /// Sanitizes the raw HTML among the events of a parsed post. Lines of an
+    /// HTML block arrive as events of their own, so runs of them are joined
+    /// to be read whole.
+    pub fn sanitize<'a>(
+        &self,
+        events: impl Iterator<Item = Event<'a>>,
+    ) -> Result<Vec<Event<'a>>, RenderError> {
+        let mut output = Vec::new();
+        let mut html = String::new();
+        for event in events {
+            if let Event::Html(fragment) = event {
+                html.push_str(&fragment);
+                continue;
+            }
+            if !html.is_empty() {
+                output.push(Event::Html(self.clean(&html)?.into()));
+                html.clear();
+            }
+            output.push(event);
+        }
+        if !html.is_empty() {
+            output.push(Event::Html(self.clean(&html)?.into()));
+        }
+        Ok(output)
+    }
+
+    fn clean(&self, html: &str) -> Result<String, RenderError> {
+        let settings = RewriteStrSettings::new()
+            .append_element_content_handler(element!("*", |el| self.clean_element(el)))
+            .append_document_content_handler(doc_comments!(|comment| {
+                comment.remove();
+                Ok(())
+            }));
+        let cleaned = rewrite_str(html, settings)?;
+
+        // Inline HTML comes one tag at a time, so the end tag of a dropped
+        // element is usually read without its start tag to go with it.
+        let cleaned = self.end_tag.replace_all(&cleaned, |captures: &Captures| {
+            match self.tags.contains(&captures[1].to_lowercase()) {
+                true => captures[0].to_string(),
+                false => String::new(),
+            }
+        });
+        Ok(cleaned.into_owned())
+    }
+
+    fn clean_element(&self, el: &mut Element) -> HandlerResult {
+        let tag = el.tag_name();
+        if !self.tags.contains(&tag) {
+            match DROPPED_WITH_CONTENT.contains(&tag.as_str()) {
+                true => el.remove(),
+                false => el.remove_and_keep_content(),
+            }
+            return Ok(());
+        }
+
+        let allowed = |name: &str| {
+            [tag.as_str(), ANY_ELEMENT].iter().any(|key| {
+                self.attributes
+                    .get(*key)
+                    .is_some_and(|names| names.contains(name))
+            })
+        };
+        let removed: Vec<String> = el
+            .attributes()
+            .iter()
+            .map(|attribute| attribute.name())
+            .filter(|name| {
+                !allowed(name)
+                    || URL_ATTRIBUTES.contains(&name.as_str())
+                        && !el.get_attribute(name).is_some_and(|url| is_safe_url(&url))
+            })
+            .collect();
+        for name in removed {
+            el.remove_attribute(&name);
+        }
+        Ok(())
+    }
+}
+
+/// Whether `url` is relative or uses one of `SAFE_SCHEMES`. Attribute values
+/// may still hold entities, so a scheme with an `&` in it isn't trusted.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = url[..end].to_ascii_lowercase();
+            SAFE_SCHEMES.contains(&scheme.as_str())
+        }
+        _ => !url
+            .split(['/', '?', '#'])
+            .next()
+            .unwrap_or("")
+            .contains('&'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::markdown::MarkdownRenderer;
+    use pulldown_cmark::{html, Parser};
+
+    fn sanitize(markdown: &str) -> String {
+        let sanitizer = Sanitizer::new(&SanitizeConfig::default());
+        let events = sanitizer.sanitize(Parser::new(markdown)).unwrap();
+        let mut output = String::new();
+        html::push_html(&mut output, events.into_iter());
+        output
+    }
+
+    fn render(markdown: &str, math: bool) -> String {
+        let mut config = Config::default();
+        config.sanitize.enabled = true;
+        MarkdownRenderer::new(&config)
+            .unwrap()
+            .render(markdown, math)
+            .unwrap()
+    }
+
+    #[test]
+    fn drops_scripts_and_event_handlers() {
+        let html = sanitize("<div onclick=\"alert(1)\">hi<script>alert(2)</script></div>\n");
+        assert!(!html.contains("onclick"), "{}", html);
+        assert!(!html.contains("script"), "{}", html);
+        assert!(html.contains("hi"), "{}", html);
+    }
+
+    #[test]
+    fn drops_javascript_urls() {
+        for href in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            " java\tscript:alert(1)",
+        ] {
+            let html = sanitize(&format!("<a href=\"{}\">x</a>\n", href));
+            assert!(!html.contains("href"), "{:?} gave {}", href, html);
+        }
+        let html = sanitize("<a href=\"https://example.com/a:b\">x</a>\n");
+        assert!(
+            html.contains("href=\"https://example.com/a:b\""),
+            "{}",
+            html
+        );
+        let html = sanitize("<a href=\"/posts/a.html\">x</a>\n");
+        assert!(html.contains("href=\"/posts/a.html\""), "{}", html);
+    }
+
+    #[test]
+    fn drops_entity_encoded_schemes() {
+        for href in [
+            "javascript&colon;alert(1)",
+            "&#106;avascript:alert(1)",
+            "java&#x09;script:alert(1)",
+            "&#x6A;&#x61;&#x76;&#x61;script:alert(1)",
+        ] {
+            let html = sanitize(&format!("<a href=\"{}\">x</a>\n", href));
+            assert!(!html.contains("href"), "{:?} gave {}", href, html);
+        }
+    }
+
+    #[test]
+    fn sanitizes_math_inside_raw_html() {
+        let html = render("<div>$<img src=x onerror=alert(3)>$</div>\n", true);
+        assert!(!html.contains("onerror"), "{}", html);
+        let html = render("a <span>$<script>alert(4)</script>$</span> b\n", true);
+        assert!(!html.contains("<script"), "{}", html);
+    }
+
+    #[test]
+    fn keeps_math_in_text() {
+        let html = render("Euler: $e^{i\\pi} + 1 = 0$ and $a_1 * b_2$\n", true);
+        assert!(html.contains("$e^{i\\pi} + 1 = 0$"), "{}", html);
+        assert!(html.contains("$a_1 * b_2$"), "{}", html);
+    }
+}
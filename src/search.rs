@@ -0,0 +1,89 @@
+use crate::config::{Config, TAGS};
+use crate::error::BuildError;
+use crate::i18n::string;
+use crate::markdown::plain_text;
+use crate::output::write_atomic;
+use crate::post::Post;
+use crate::templates::render_page;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+/// File name of the search index, written at the root of the output directory.
+pub const SEARCH_INDEX_FILE: &str = "search-index.json";
+
+/// Directory of the search page.
+pub const SEARCH_DIR: &str = "search";
+
+/// A post or page as the browser searches it, in the flat document form
+/// lunr and elasticlunr build their indexes from.
+#[derive(Debug, Serialize)]
+struct SearchDocument<'a> {
+    url: &'a str,
+    title: &'a str,
+    date: String,
+    tags: Vec<&'a str>,
+    summary: &'a str,
+    content: String,
+}
+
+/// Writes `search-index.json` listing `posts` and `pages`, but for unlisted
+/// ones, with their text,
+/// and the search page at `search/index.html` when the config asks for it.
+/// Returns the paths written, which are none unless the index is turned on.
+pub fn render_search(
+    tera: &Tera,
+    config: &Config,
+    output_dir: &Path,
+    posts: &[Post],
+    pages: &[Post],
+) -> Result<Vec<PathBuf>, BuildError> {
+    if !config.search.index {
+        return Ok(Vec::new());
+    }
+
+    let documents: Vec<SearchDocument> = posts
+        .iter()
+        .chain(pages)
+        .filter(|post| !post.metadata.unlisted)
+        .map(|post| SearchDocument {
+            url: &post.url,
+            title: &post.title,
+            date: post.date.to_string(),
+            tags: post.terms(TAGS),
+            summary: &post.summary,
+            content: plain_text(&post.content),
+        })
+        .collect();
+
+    let index_path = output_dir.join(SEARCH_INDEX_FILE);
+    // Serializing borrowed strings and plain values can't fail.
+    let index = serde_json::to_string(&documents).unwrap();
+    write_atomic(&index_path, index).map_err(|source| BuildError::Write {
+        path: index_path.clone(),
+        source,
+    })?;
+    println!(
+        "Search index generated: {:?} ({} documents)",
+        index_path,
+        documents.len()
+    );
+    let mut written = vec![index_path];
+
+    if config.search.page {
+        let mut context = Context::new();
+        context.insert("config", config);
+        context.insert("section", "search");
+        context.insert("url", &format!("/{}/index.html", SEARCH_DIR));
+        context.insert("pages", pages);
+        context.insert("title", &string(config, &config.default_language, "search"));
+        context.insert("index_url", &format!("/{}", SEARCH_INDEX_FILE));
+
+        let html_path = output_dir.join(SEARCH_DIR).join("index.html");
+        render_page(tera, config, "search.html", &context, &html_path)?;
+        crate::verbose!("Search page generated: {:?}", html_path);
+        written.push(html_path);
+    }
+
+    Ok(written)
+}
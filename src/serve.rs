@@ -0,0 +1,180 @@
+use crate::site::BuildOptions;
+use crate::watch::watch;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Request, Response, Server};
+
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// Server-sent events endpoint the injected script listens on.
+const RELOAD_PATH: &str = "/__ebolg/livereload";
+
+/// Injected before `</body>` of every served page; never written to disk.
+const RELOAD_SCRIPT: &str = r#"<script>new EventSource("/__ebolg/livereload").onmessage = () => location.reload();</script>"#;
+
+/// One sender per open live-reload connection.
+type Clients = Arc<Mutex<Vec<Sender<()>>>>;
+
+/// Serves `output_dir` over HTTP while watching `source_dir`, telling every
+/// open page to reload once a rebuild completes.
+pub fn serve(
+    source_dir: &Path,
+    output_dir: &Path,
+    config_path: &Path,
+    port: u16,
+    options: BuildOptions,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_dir)?;
+
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+    println!("Serving {:?} at http://127.0.0.1:{}/", output_dir, port);
+
+    let clients = Clients::default();
+    let root = output_dir.to_path_buf();
+    let server_clients = Arc::clone(&clients);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let root = root.clone();
+            let clients = Arc::clone(&server_clients);
+            thread::spawn(move || handle_request(request, &root, &clients));
+        }
+    });
+
+    watch(source_dir, output_dir, config_path, options, || {
+        let mut clients = clients.lock().unwrap();
+        clients.retain(|client| client.send(()).is_ok());
+        println!("Reloading {} page(s)", clients.len());
+    })
+}
+
+fn handle_request(request: Request, root: &Path, clients: &Clients) {
+    let path = request
+        .url()
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("/")
+        .to_string();
+    if path == RELOAD_PATH {
+        return stream_reloads(request, clients);
+    }
+
+    let result = match resolve_file(root, &path) {
+        Some(file) => respond_with_file(request, &file),
+        None => request.respond(Response::from_string("404 Not Found").with_status_code(404)),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to respond to {}: {}", path, e);
+    }
+}
+
+/// Maps a request path onto a file under `root`, refusing anything that would
+/// escape it. Directories resolve to their `index.html`.
+fn resolve_file(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(percent_decode(url_path.trim_start_matches('/')));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let mut file = root.join(relative);
+    if file.is_dir() {
+        file = file.join("index.html");
+    }
+    file.is_file().then_some(file)
+}
+
+fn respond_with_file(request: Request, file: &Path) -> std::io::Result<()> {
+    let mut body = fs::read(file)?;
+    let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    if extension == "html" {
+        let html = String::from_utf8_lossy(&body);
+        body = match html.rfind("</body>") {
+            Some(i) => format!("{}{}{}", &html[..i], RELOAD_SCRIPT, &html[i..]),
+            None => format!("{}{}", html, RELOAD_SCRIPT),
+        }
+        .into_bytes();
+    }
+
+    let content_type = Header::from_bytes("Content-Type", content_type(extension)).unwrap();
+    request.respond(Response::from_data(body).with_header(content_type))
+}
+
+/// Holds the connection open and writes an event each time the site is rebuilt.
+fn stream_reloads(request: Request, clients: &Clients) {
+    let (sender, receiver) = mpsc::channel();
+    clients.lock().unwrap().push(sender);
+
+    let mut writer = request.into_writer();
+    let headers =
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n";
+    if writer
+        .write_all(headers.as_bytes())
+        .and_then(|_| writer.flush())
+        .is_err()
+    {
+        return;
+    }
+
+    while receiver.recv().is_ok() {
+        if writer
+            .write_all(b"data: reload\n\n")
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+fn content_type(extension: &str) -> &'static str {
+    match extension {
+        "html" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok());
+        match (
+            bytes[i],
+            hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+        ) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
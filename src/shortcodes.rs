@@ -0,0 +1,135 @@
+use crate::markdown::RenderError;
+use crate::math::{code_span_end, fenced_code_end};
+use tera::{Context, Tera};
+
+/// Directory under `templates/` holding a template per shortcode, such as
+/// `shortcodes/youtube.html` for `{{ youtube id="..." }}`.
+pub const SHORTCODES_DIR: &str = "shortcodes";
+
+/// Replaces every `{{ name key="value" }}` outside code with the output of
+/// the template `shortcodes/<name>.html`, rendered with the arguments as its
+/// variables. Values are quoted strings, numbers or `true`/`false`. Braces
+/// naming no shortcode template are left as they are.
+///
+/// Blank lines are dropped from the output so a shortcode on a line of its
+/// own becomes a single block of HTML in the markdown.
+pub fn expand_shortcodes(markdown_content: &str, tera: &Tera) -> Result<String, RenderError> {
+    if !markdown_content.contains("{{") {
+        return Ok(markdown_content.to_string());
+    }
+
+    let mut output = String::with_capacity(markdown_content.len());
+    let mut i = 0;
+    let mut line_start = true;
+    while i < markdown_content.len() {
+        let rest = &markdown_content[i..];
+        if line_start {
+            if let Some(end) = fenced_code_end(rest) {
+                output.push_str(&rest[..end]);
+                i += end;
+                continue;
+            }
+        }
+
+        let next = rest.chars().next().unwrap();
+        line_start = next == '\n';
+        if next == '`' {
+            let end = code_span_end(rest);
+            output.push_str(&rest[..end]);
+            i += end;
+            continue;
+        }
+        if let Some((call, len)) = rest.starts_with("{{").then(|| parse(rest)).flatten() {
+            let template = format!("{}/{}.html", SHORTCODES_DIR, call.name);
+            if tera.get_template_names().any(|name| name == template) {
+                let html = tera.render(&template, &call.context)?;
+                let lines: Vec<&str> = html
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect();
+                output.push_str(&lines.join("\n"));
+                i += len;
+                continue;
+            }
+        }
+        output.push(next);
+        i += next.len_utf8();
+    }
+
+    Ok(output)
+}
+
+struct Call<'a> {
+    name: &'a str,
+    context: Context,
+}
+
+/// The shortcode call at the start of `text` and its length, or `None` if the
+/// braces hold something else.
+fn parse(text: &str) -> Option<(Call<'_>, usize)> {
+    let end = text.find("}}")?;
+    let inner = &text[2..end];
+    if inner.contains('\n') {
+        return None;
+    }
+    let mut rest = inner.trim_start();
+    let name_len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    if name.is_empty() {
+        return None;
+    }
+    rest = &rest[name_len..];
+
+    let mut context = Context::new();
+    loop {
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            break;
+        }
+        if trimmed.len() == rest.len() {
+            // Arguments are separated from the name and each other by spaces.
+            return None;
+        }
+        let (key, value) = trimmed.split_once('=')?;
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return None;
+        }
+        rest = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let (string, after) = quoted_string(quoted)?;
+                context.insert(key, &string);
+                after
+            }
+            None => {
+                let value_len = value.find(char::is_whitespace).unwrap_or(value.len());
+                match &value[..value_len] {
+                    "true" => context.insert(key, &true),
+                    "false" => context.insert(key, &false),
+                    number => match number.parse::<i64>() {
+                        Ok(number) => context.insert(key, &number),
+                        Err(_) => context.insert(key, &number.parse::<f64>().ok()?),
+                    },
+                }
+                &value[value_len..]
+            }
+        };
+    }
+    Some((Call { name, context }, end + 2))
+}
+
+/// The string up to the closing quote, with `\"` and `\\` unescaped, and the
+/// text after the quote.
+fn quoted_string(text: &str) -> Option<(String, &str)> {
+    let mut string = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, &text[i + 1..])),
+            '\\' => string.push(chars.next()?.1),
+            c => string.push(c),
+        }
+    }
+    None
+}
@@ -0,0 +1,2033 @@
+use crate::archive::{collect_archive, periods, render_archive_pages};
+use crate::authors::{collect_authors, post_authors, render_author_pages};
+use crate::cache::{hash, is_generated, BuildCache, CACHE_FILE};
+use crate::compress::{is_compressed_copy, precompress, PRECOMPRESS_FORMATS};
+use crate::config::{
+    Config, ANALYTICS_PROVIDERS, COMMENT_PROVIDERS, DEFAULT_PERMALINK, PERMALINK_PLACEHOLDERS, TAGS,
+};
+use crate::data::{load_data, DATA_DIR};
+use crate::dry_run::{report_changes, scratch_copy};
+use crate::error::BuildError;
+use crate::feed::{render_feed, FEED_FILE, JSON_FEED_FILE};
+use crate::filter::SourceFilter;
+use crate::fingerprint::{fingerprint, fingerprinted_name, Fingerprints, FINGERPRINT_EXTENSIONS};
+use crate::formats::{is_source, SOURCE_FORMATS};
+use crate::git::History;
+use crate::i18n::{load_strings, string, I18N_DIR};
+use crate::images::{images_without_alt, is_image_variant, responsive_images, variants_stale};
+use crate::includes::SNIPPETS_DIR;
+use crate::languages::{
+    base_path, base_stem, file_language, language_dir, language_rank, language_title,
+    site_languages, Translation,
+};
+use crate::links::{check_links, decorate_external_links, normalize, rewrite_post_links};
+use crate::markdown::{has_diagrams, MarkdownRenderer};
+use crate::output::{copy_atomic, write_atomic};
+use crate::paginate::{paginate, remove_extra_pages};
+use crate::post::{load_post, Post, METADATA_KEYS};
+use crate::search::render_search;
+use crate::shortcodes::expand_shortcodes;
+use crate::sitemap::{render_sitemap, write_robots, ROBOTS_FILE, SITEMAP_FILE};
+use crate::tags::{
+    collect_tags, post_tags, related_posts, render_tag_pages, series_parts, SeriesPart, Tag,
+};
+use crate::tailwind::build_stylesheet;
+use crate::templates::{
+    compile_templates, load_templates, read_templates, render_page, theme_dir, TEMPLATES_DIR,
+    THEMES_DIR,
+};
+use chrono::{Local, NaiveDate};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+/// Directories at the source root that configure the site or hold snippets,
+/// data and UI strings, and aren't built as posts.
+pub const SITE_DIRS: [&str; 5] = [TEMPLATES_DIR, THEMES_DIR, SNIPPETS_DIR, DATA_DIR, I18N_DIR];
+
+/// Directory of a theme copied to the root of the output directory.
+const STATIC_DIR: &str = "static";
+
+/// Root-level post rendered above the post list instead of as a page of its own.
+const INDEX_PAGE: &str = "index.md";
+
+/// Root-level post holding the text of the not-found page.
+const NOT_FOUND_PAGE: &str = "404.md";
+
+/// The page static hosts serve for missing URLs, at the root of the output directory.
+const NOT_FOUND_FILE: &str = "404.html";
+
+/// Directory at the source root holding standalone pages, like an about page.
+/// They use the `page.html` template and are left out of the index, the feed
+/// and the tag pages.
+pub const PAGES_DIR: &str = "pages";
+
+/// Flags from the command line that change what gets built.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BuildOptions {
+    /// Include posts marked `draft: true`.
+    pub drafts: bool,
+    /// Include posts dated after today.
+    pub future: bool,
+    /// Build every post that can be built and report the failures at the end,
+    /// instead of stopping at the first one.
+    pub keep_going: bool,
+    /// Fail posts with warnings instead of printing them.
+    pub strict: bool,
+    /// Check links to other sites as well as those within the site.
+    pub check_external: bool,
+    /// Build into a scratch copy of the output directory and list what the
+    /// build would change, leaving the real one alone.
+    pub dry_run: bool,
+    /// Delete files in the output directory that the build didn't produce.
+    pub prune: bool,
+    /// Date of posts without a `date:`, instead of their modification time,
+    /// and the day posts dated after it are left out as future posts, so
+    /// rebuilding a checkout gives the same pages.
+    pub source_date: Option<NaiveDate>,
+    /// Add the analytics script from the config to every page. Off for local
+    /// builds, so visits while writing aren't counted.
+    pub analytics: bool,
+    /// Pull the posts of the CMS `[cms]` configures into the source directory
+    /// before reading it. Off for dry runs and `check`, which leave the
+    /// source directory alone and build the posts pulled before.
+    pub pull_cms: bool,
+}
+
+impl BuildOptions {
+    /// The day the build counts as today: the source date when one is set, so
+    /// the same sources are built the same wherever and whenever they are.
+    pub fn today(&self) -> NaiveDate {
+        self.source_date
+            .unwrap_or_else(|| Local::now().date_naive())
+    }
+}
+
+/// Everything needed to render a source directory: its configuration, the
+/// loaded templates and every post, kept in memory so single files can be
+/// re-rendered without walking the whole tree again.
+pub struct Site {
+    source_dir: PathBuf,
+    output_dir: PathBuf,
+    config: Config,
+    options: BuildOptions,
+    tera: Tera,
+    renderer: MarkdownRenderer,
+    /// The root-level `index.md` and its translations, each rendered above the
+    /// post list in its language, by language.
+    index_pages: BTreeMap<String, Post>,
+    /// The root-level `404.md`, rendered as the not-found page.
+    not_found_page: Option<Post>,
+    /// Standalone pages from `PAGES_DIR`, sorted by title.
+    pages: Vec<Post>,
+    /// Every other post, newest first.
+    posts: Vec<Post>,
+    /// Files copied verbatim into the output directory.
+    assets: Vec<PathBuf>,
+    /// Directory whose contents are copied to the root of the output directory.
+    static_dir: PathBuf,
+    /// The theme's static directory, copied there first so the site's own
+    /// static files win.
+    theme_static_dir: Option<PathBuf>,
+    /// URL of each post by its normalized source path, for links between posts.
+    post_urls: HashMap<PathBuf, String>,
+    /// Rendered bodies and page keys from the previous build.
+    cache: BuildCache,
+    /// Hash of the config the markdown renderer is built from, part of the
+    /// cache key of every rendered body.
+    render_hash: u64,
+    /// Dates and contributors of the committed sources.
+    history: History,
+    /// Which source files are built, and how.
+    filter: SourceFilter,
+    /// Posts that failed to load or render, with `keep_going` set.
+    failures: Vec<BuildError>,
+    /// The real output directory of a dry run, while `output_dir` is the
+    /// scratch copy being built into.
+    dry_run_dir: Option<PathBuf>,
+}
+
+impl Site {
+    pub fn load(
+        source_dir: &Path,
+        output_dir: &Path,
+        mut config: Config,
+        options: BuildOptions,
+    ) -> Result<Site, Box<dyn Error>> {
+        validate_output_dir(source_dir, output_dir, &config)?;
+        validate_permalink(&config.permalink)?;
+        validate_taxonomies(&config)?;
+        validate_providers(&config)?;
+        validate_precompress(&config)?;
+        validate_formats(&config)?;
+        validate_cms(&config)?;
+        validate_languages(&config)?;
+        validate_authors(&config)?;
+        if !options.analytics {
+            config.analytics.provider.clear();
+        }
+        config.data = load_data(source_dir)?;
+        config.strings = load_strings(source_dir, &config)?;
+        let theme_dir = theme_dir(source_dir, config.theme.name.as_deref())?;
+        let templates = read_templates(source_dir, theme_dir.as_deref())?;
+        let site_hash = hash((
+            env!("CARGO_PKG_VERSION"),
+            serde_json::to_string(&config)?,
+            serde_json::to_string(&config.data)?,
+            serde_json::to_string(&config.strings)?,
+            &templates,
+        ));
+        let render_hash = hash(serde_json::to_string(&(
+            &config.markdown,
+            &config.code,
+            &config.typography,
+            &config.emoji,
+            &config.sanitize,
+            &config.classes,
+            &config.images,
+            &config.theme.code_theme,
+        ))?);
+        let tera = compile_templates(templates)?;
+        let dry_run_dir = options.dry_run.then(|| output_dir.to_path_buf());
+        let scratch_dir = match &dry_run_dir {
+            Some(dir) => Some(scratch_copy(dir)?),
+            None => None,
+        };
+        let output_dir = scratch_dir.as_deref().unwrap_or(output_dir);
+        let renderer = MarkdownRenderer::new(&config)?;
+        let filter = SourceFilter::new(source_dir, &config)?;
+        let static_dir = source_dir.join(&config.assets.static_dir);
+        let theme_static_dir = theme_dir.map(|dir| dir.join(STATIC_DIR));
+
+        let mut site = Site {
+            source_dir: source_dir.to_path_buf(),
+            output_dir: output_dir.to_path_buf(),
+            config,
+            options,
+            tera,
+            renderer,
+            index_pages: BTreeMap::new(),
+            not_found_page: None,
+            pages: Vec::new(),
+            posts: Vec::new(),
+            assets: Vec::new(),
+            static_dir,
+            theme_static_dir,
+            post_urls: HashMap::new(),
+            cache: BuildCache::load(output_dir, site_hash),
+            render_hash,
+            history: History::load(source_dir),
+            filter,
+            failures: Vec::new(),
+            dry_run_dir,
+        };
+
+        // The output directory may live inside the source tree (e.g. `ebolg . dist`),
+        // so it counts as walked already, like the other directories to skip.
+        fs::create_dir_all(output_dir)?;
+        let mut walked = HashSet::from([source_dir.canonicalize()?, output_dir.canonicalize()?]);
+        if let Some(dir) = site.dry_run_dir.as_ref().filter(|dir| dir.is_dir()) {
+            walked.insert(dir.canonicalize()?);
+        }
+        if let Some(theme_static_dir) = site.theme_static_dir.as_ref().filter(|dir| dir.is_dir()) {
+            collect_files(theme_static_dir, &mut site.assets)?;
+        }
+        if site.static_dir.is_dir() {
+            walked.insert(site.static_dir.canonicalize()?);
+            collect_files(&site.static_dir, &mut site.assets)?;
+        }
+
+        #[cfg(feature = "cms")]
+        if site.options.pull_cms {
+            crate::cms::pull(source_dir, &site.config)?;
+        }
+        let mut markdown_files = Vec::new();
+        collect_sources(
+            source_dir,
+            &source_dir.canonicalize()?,
+            &mut walked,
+            &site.filter,
+            &site.config,
+            &mut markdown_files,
+            &mut site.assets,
+        )?;
+        if site.config.assets.fingerprint {
+            site.config.fingerprints = site.asset_fingerprints()?;
+        }
+
+        // Rendering markdown dominates the build, so it runs on every core.
+        let posts: Vec<_> = markdown_files
+            .par_iter()
+            .map(|path| site.load_post(path))
+            .collect();
+        for (path, post) in markdown_files.iter().zip(posts) {
+            let (post, source_hash) = match post {
+                Ok(post) => post,
+                Err(e) if options.keep_going => {
+                    site.failures.push(e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if let Some((reason, _)) = skip_reason(&post, options) {
+                println!("Skipping {}: {:?}", reason, path);
+                continue;
+            }
+            site.insert_post(post, source_hash);
+        }
+        let mut collisions = site.output_collisions();
+        if !collisions.is_empty() {
+            if !options.keep_going {
+                return Err(collisions.remove(0).into());
+            }
+            site.failures.append(&mut collisions);
+        }
+        site.sort_posts();
+        site.link_translations();
+        site.forget_removed_posts();
+        site.index_post_urls();
+
+        Ok(site)
+    }
+
+    pub fn options(&self) -> BuildOptions {
+        self.options
+    }
+
+    pub fn source_dir(&self) -> &Path {
+        &self.source_dir
+    }
+
+    pub fn output_dir(&self) -> &Path {
+        &self.output_dir
+    }
+
+    /// Writes every listing and asset of the site, and the pages of posts that
+    /// changed since the previous build. A dry run lists those files instead.
+    pub fn build(&mut self) -> Result<(), Box<dyn Error>> {
+        let result = self.write_site();
+        match &self.dry_run_dir {
+            Some(dry_run_dir) => {
+                let report = report_changes(dry_run_dir, &self.output_dir);
+                result.and(report)
+            }
+            None => result,
+        }
+    }
+
+    fn write_site(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.options.prune && !is_generated(&self.output_dir)? {
+            return Err(format!(
+                "Refusing to prune {:?}: it has no {} so it may not have been generated by ebolg",
+                self.dry_run_dir.as_ref().unwrap_or(&self.output_dir),
+                CACHE_FILE
+            )
+            .into());
+        }
+        for asset in &self.assets {
+            self.copy_asset(asset)?;
+        }
+
+        let stale = self.stale_urls();
+        self.render_posts_by_url(&stale)?;
+        let listings = self.render_listings()?;
+        self.cache.save(&self.output_dir)?;
+        self.build_stylesheet()?;
+        if self.options.prune {
+            self.prune(listings)?;
+        }
+        self.check_links()?;
+        self.precompress()?;
+
+        let pages = match self.pages.len() {
+            0 => String::new(),
+            count => format!(" and {} pages", count),
+        };
+        println!(
+            "Built {} posts{} into {:?} ({} unchanged)",
+            self.posts.len(),
+            pages,
+            self.dry_run_dir.as_ref().unwrap_or(&self.output_dir),
+            self.posts.len() + self.pages.len() - stale.len()
+        );
+        self.report_failures()
+    }
+
+    /// Deletes the files in the output directory that this build didn't
+    /// produce, such as pages of renamed posts, then any directories left
+    /// empty. `listings` are the listing pages just written.
+    fn prune(&self, listings: Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+        let mut produced: HashSet<PathBuf> = listings.into_iter().collect();
+        produced.insert(self.output_dir.join(CACHE_FILE));
+        produced.extend(
+            self.posts
+                .iter()
+                .chain(&self.pages)
+                .map(|post| post.html_path.clone()),
+        );
+        produced.extend(
+            self.assets
+                .iter()
+                .map(|asset| self.asset_output_path(asset)),
+        );
+        produced.extend(
+            self.assets
+                .iter()
+                .filter_map(|asset| self.fingerprinted_path(asset)),
+        );
+        if !self.config.base_url.is_empty() {
+            for lang in site_languages(&self.config) {
+                let dir = self
+                    .output_dir
+                    .join(language_dir(&self.config, lang).trim_start_matches('/'));
+                produced.insert(dir.join(FEED_FILE));
+                produced.insert(dir.join(JSON_FEED_FILE));
+            }
+            produced.insert(self.output_dir.join(SITEMAP_FILE));
+            produced.insert(self.output_dir.join(ROBOTS_FILE));
+        }
+        if !self.config.tailwind.command.trim().is_empty() {
+            let stylesheet = self.config.theme.stylesheet.trim_start_matches('/');
+            produced.insert(self.output_dir.join(stylesheet));
+        }
+
+        let mut files = Vec::new();
+        collect_files(&self.output_dir, &mut files)?;
+        for file in files {
+            if produced.contains(&file)
+                || is_image_variant(&file, &produced)
+                || is_compressed_copy(&file, &produced)
+            {
+                continue;
+            }
+            fs::remove_file(&file).map_err(|source| BuildError::Write {
+                path: file.clone(),
+                source,
+            })?;
+            // A dry run lists the deletions in its report instead.
+            if self.dry_run_dir.is_none() {
+                println!("Pruned {:?}", file);
+            }
+        }
+        remove_empty_dirs(&self.output_dir)?;
+        Ok(())
+    }
+
+    /// Regenerates the stylesheet from the classes the pages now use, when the
+    /// Tailwind CLI is configured.
+    pub fn build_stylesheet(&self) -> Result<(), Box<dyn Error>> {
+        build_stylesheet(&self.config, &self.source_dir, &self.output_dir)
+    }
+
+    /// Writes the compressed copies of the output that `precompress` asks for,
+    /// skipping the files that haven't changed since their copies were made.
+    pub fn precompress(&self) -> Result<(), Box<dyn Error>> {
+        if self.config.precompress.is_empty() {
+            return Ok(());
+        }
+        let mut files = Vec::new();
+        collect_files(&self.output_dir, &mut files)?;
+        let count = precompress(&self.config.precompress, &files)?;
+        if count > 0 {
+            println!(
+                "Compressed copies written: {} ({})",
+                count,
+                self.config.precompress.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Warns about links in the output directory that lead nowhere, or records
+    /// them as failures with `strict`.
+    fn check_links(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut html_files = Vec::new();
+        collect_files(&self.output_dir, &mut html_files)?;
+        html_files.retain(|path| has_extension(path, &[String::from("html")]));
+        html_files.sort();
+        let sources: HashMap<PathBuf, PathBuf> = self
+            .sources()
+            .map(|post| (post.html_path.clone(), post.source_path.clone()))
+            .collect();
+
+        for broken in check_links(
+            &self.output_dir,
+            &html_files,
+            &sources,
+            &self.config.base_url,
+            self.options.check_external,
+        ) {
+            if self.options.strict {
+                self.failures.push(broken);
+            } else {
+                eprintln!("Warning: {}", broken);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reports the failures collected while loading, failing if there were
+    /// any. Nothing is rendered.
+    fn check(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.failures.is_empty() {
+            println!(
+                "Checked {} posts and {} pages: no problems found",
+                self.posts.len(),
+                self.pages.len()
+            );
+            return Ok(());
+        }
+        for failure in &self.failures {
+            eprintln!("  {}", failure);
+        }
+        Err(format!("{} problem(s) found", self.failures.len()).into())
+    }
+
+    /// Lists every failure collected with `keep_going`, failing the build if there were any.
+    fn report_failures(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+
+        eprintln!("\n{} file(s) failed to build:", self.failures.len());
+        for failure in &self.failures {
+            eprintln!("  {}", failure);
+        }
+        let count = self.failures.len();
+        self.failures.clear();
+        Err(format!("{} file(s) failed to build", count).into())
+    }
+
+    /// Re-reads a changed or newly created markdown file and re-renders it, the
+    /// posts it is (or was) linked to by prev/next, and the listing pages.
+    pub fn update_post(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let (post, source_hash) = self.load_post(path)?;
+        if self.skips(&post) {
+            // A post that just became a draft, or got a future date, disappears
+            // like a deleted one.
+            return self.remove_post(path);
+        }
+
+        if let Some(other) = self.sources().find(|other| {
+            other.source_path != path && output_key(&other.html_path) == output_key(&post.html_path)
+        }) {
+            return Err(BuildError::DuplicateOutput {
+                path: path.to_path_buf(),
+                other: other.source_path.clone(),
+                url: post.url,
+            }
+            .into());
+        }
+
+        let mut affected = self.neighbour_urls(path);
+        let url = post.url.clone();
+        if let Some(old) = self.remove_by_source(path) {
+            // A new date or slug moves the page; the old copy would linger otherwise.
+            if old.html_path != post.html_path && old.html_path.is_file() {
+                fs::remove_file(&old.html_path).map_err(|source| BuildError::Write {
+                    path: old.html_path.clone(),
+                    source,
+                })?;
+            }
+        }
+        self.insert_post(post, source_hash);
+        self.sort_posts();
+        self.link_translations();
+        self.index_post_urls();
+
+        affected.extend(self.neighbour_urls(path));
+        affected.insert(url);
+        // Posts linking to this one need the new address if it moved.
+        affected.extend(self.stale_urls());
+        self.render_posts_by_url(&affected)?;
+        self.render_listings()?;
+        self.cache.save(&self.output_dir)
+    }
+
+    /// Drops a deleted markdown file, removing its output and relinking its neighbours.
+    pub fn remove_post(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut affected = self.neighbour_urls(path);
+
+        if let Some(post) = self.remove_by_source(path) {
+            if post.html_path.is_file() {
+                fs::remove_file(&post.html_path).map_err(|source| BuildError::Write {
+                    path: post.html_path.clone(),
+                    source,
+                })?;
+                println!("HTML file removed: {:?}", post.html_path);
+            }
+        }
+        self.link_translations();
+        self.forget_removed_posts();
+        self.index_post_urls();
+
+        // Links to the removed post go back to pointing at its markdown file.
+        affected.extend(self.stale_urls());
+        self.render_posts_by_url(&affected)?;
+        self.render_listings()?;
+        self.cache.save(&self.output_dir)
+    }
+
+    /// Whether `path` is left out of the build by the hidden-file, `ignore` and
+    /// `include` rules. The static directory is copied whole, so nothing in it is.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        !path.starts_with(&self.static_dir)
+            && (self.filter.is_ignored(path) || !self.filter.is_included(path))
+    }
+
+    /// Source files of the posts and pages that include the file at `path`,
+    /// with `{{ include }}` or `{{ code }}`.
+    pub fn includers(&self, path: &Path) -> Vec<PathBuf> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.sources()
+            .filter(|post| post.includes.contains(&path))
+            .map(|post| post.source_path.clone())
+            .collect()
+    }
+
+    /// Whether `path` is a post or page in one of the configured formats,
+    /// once it isn't an asset.
+    pub fn is_source(&self, path: &Path) -> bool {
+        is_source(path, &self.config.formats)
+    }
+
+    /// Whether `path` is copied verbatim rather than rendered: anything under the
+    /// static directory or matching `assets.copy`, and files with one of the
+    /// configured asset extensions.
+    pub fn is_asset(&self, path: &Path) -> bool {
+        if path.starts_with(&self.static_dir) || self.filter.is_copied(path) {
+            return true;
+        }
+        has_extension(path, &self.config.assets.extensions)
+    }
+
+    /// Copies an asset to the matching location in the output directory, and
+    /// to its fingerprinted name when it has one.
+    pub fn copy_asset(&self, path: &Path) -> Result<(), BuildError> {
+        self.write_asset(path, &self.asset_output_path(path))?;
+        match self.fingerprinted_path(path) {
+            Some(target_path) => self.write_asset(path, &target_path),
+            None => Ok(()),
+        }
+    }
+
+    fn write_asset(&self, path: &Path, target_path: &Path) -> Result<(), BuildError> {
+        let write_error = |source| BuildError::Write {
+            path: target_path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        if self.config.minify && has_extension(path, &[String::from("css")]) {
+            let css = fs::read_to_string(path).map_err(|source| BuildError::Read {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            // Stylesheets the minifier can't parse are copied unchanged.
+            if let Ok(minified) = minifier::css::minify(&css) {
+                return write_atomic(target_path, minified.to_string()).map_err(write_error);
+            }
+        }
+        copy_atomic(path, target_path).map_err(write_error)?;
+        Ok(())
+    }
+
+    /// Removes the copy of a deleted asset from the output directory.
+    pub fn remove_asset(&self, path: &Path) -> Result<(), BuildError> {
+        let target_path = self.asset_output_path(path);
+        if target_path.is_file() {
+            fs::remove_file(&target_path).map_err(|source| BuildError::Write {
+                path: target_path.clone(),
+                source,
+            })?;
+            println!("Asset removed: {:?}", target_path);
+        }
+        Ok(())
+    }
+
+    /// Whether changing the asset at `path` changes its fingerprint, and so
+    /// every page pointing at it.
+    pub fn is_fingerprinted(&self, path: &Path) -> bool {
+        self.config.assets.fingerprint
+            && self.is_asset(path)
+            && has_extension(path, &FINGERPRINT_EXTENSIONS.map(String::from))
+    }
+
+    /// Hashes of the stylesheets, scripts and images among the assets, by the
+    /// site path they are copied to.
+    fn asset_fingerprints(&self) -> Result<Fingerprints, BuildError> {
+        let mut fingerprints = Fingerprints::new();
+        for asset in self
+            .assets
+            .iter()
+            .filter(|asset| self.is_fingerprinted(asset))
+        {
+            let contents = fs::read(asset).map_err(|source| BuildError::Read {
+                path: asset.clone(),
+                source,
+            })?;
+            if let Some(url) = self.asset_url(asset) {
+                fingerprints.insert(url, fingerprint(&contents));
+            }
+        }
+        Ok(fingerprints)
+    }
+
+    /// Where the fingerprinted copy of the asset at `path` goes, if it has one.
+    fn fingerprinted_path(&self, path: &Path) -> Option<PathBuf> {
+        let fingerprint = self.config.fingerprints.get(&self.asset_url(path)?)?;
+        let target_path = self.asset_output_path(path);
+        let name = target_path.file_name()?.to_string_lossy();
+        Some(target_path.with_file_name(fingerprinted_name(&name, fingerprint)))
+    }
+
+    /// The site path the asset at `path` is published at, like `/img/photo.png`.
+    fn asset_url(&self, path: &Path) -> Option<String> {
+        let target_path = self.asset_output_path(path);
+        let relative = target_path.strip_prefix(&self.output_dir).ok()?;
+        Some(format!(
+            "/{}",
+            relative.to_string_lossy().replace('\\', "/")
+        ))
+    }
+
+    /// Static files land at the output root; other assets mirror their source path.
+    fn asset_output_path(&self, path: &Path) -> PathBuf {
+        let static_dirs = [Some(&self.static_dir), self.theme_static_dir.as_ref()];
+        match static_dirs
+            .into_iter()
+            .flatten()
+            .find_map(|dir| path.strip_prefix(dir).ok())
+        {
+            Some(relative) => self.output_dir.join(relative),
+            None => self.output_path(path),
+        }
+    }
+
+    /// Source file of an image the post at `post_path` embeds, and where it is
+    /// copied to. Site-relative sources are looked up in the static directory,
+    /// then in the source directory. Images outside the source, static and
+    /// theme static directories aren't read, so a `src` of `../../photo.png`
+    /// can't publish a file from elsewhere.
+    fn locate_image(&self, post_path: &Path, src: &str) -> Option<(PathBuf, PathBuf)> {
+        let source_path = match src.strip_prefix('/') {
+            Some(relative) => [&self.static_dir, &self.source_dir]
+                .into_iter()
+                .map(|dir| dir.join(relative))
+                .find(|path| path.is_file())?,
+            None => post_path.parent()?.join(src),
+        };
+        let canonical = source_path.canonicalize().ok()?;
+        if !canonical.is_file() {
+            return None;
+        }
+        let dirs = [
+            Some(&self.source_dir),
+            Some(&self.static_dir),
+            self.theme_static_dir.as_ref(),
+        ];
+        let inside = dirs
+            .into_iter()
+            .flatten()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .any(|dir| canonical.starts_with(dir));
+        if !inside {
+            crate::verbose!(
+                "Not reading {:?}: outside the source directory",
+                source_path
+            );
+            return None;
+        }
+        let output_path = self.asset_output_path(&source_path);
+        // Images outside the source directory would be copied outside the
+        // output directory, so they are left where they are.
+        if !normalize(&output_path).starts_with(normalize(&self.output_dir)) {
+            crate::verbose!(
+                "Not copying {:?}: outside the source directory",
+                source_path
+            );
+            return None;
+        }
+        Some((source_path, output_path))
+    }
+
+    /// Whether the post at `path` was pulled from the CMS `[cms]` configures.
+    /// Its authors can't be trusted with the site, so its includes and
+    /// shortcodes are left as written and its HTML is always sanitized.
+    fn is_pulled(&self, path: &Path) -> bool {
+        let cms = &self.config.cms;
+        !cms.url.is_empty() && path.starts_with(self.source_dir.join(&cms.dir))
+    }
+
+    /// Reads the post at `path` and renders its body, reusing the cached HTML
+    /// when the body and what it is rendered with are unchanged. Also returns
+    /// the hash of those, the cache key.
+    fn load_post(&self, path: &Path) -> Result<(Post, u64), BuildError> {
+        let lang = file_language(&self.config, path);
+        let trusted = !self.is_pulled(path);
+        let mut post = load_post(
+            path,
+            &self.source_dir,
+            lang,
+            self.options.source_date,
+            trusted,
+        )?;
+        if let Some(history) = self.history.file(path) {
+            history.apply(&mut post);
+        }
+        let page_path = self.permalink(path, &post);
+        if page_path.split('/').any(|segment| segment == "..") {
+            return Err(BuildError::OutsideOutput {
+                path: path.to_path_buf(),
+                url: page_path,
+            });
+        }
+        post.html_path = self.output_dir.join(page_path.trim_start_matches('/'));
+        post.url = match page_path.strip_suffix("index.html") {
+            Some(dir) => dir.to_string(),
+            None => page_path,
+        };
+        post.authors = post_authors(&self.config, &post);
+
+        // Whether math is left alone, and whether the post is trusted, change
+        // its HTML as much as the body does.
+        let math = post.math(&self.config);
+        let source_hash = hash((&post.content, math, trusted, self.render_hash));
+        let locate = |src: &str| self.locate_image(path, src);
+        let cached = self
+            .cache
+            .html(&post.url, source_hash)
+            // Variants deleted with the output directory are written again.
+            .filter(|html| !variants_stale(html, locate));
+        post.html = match cached {
+            Some(html) => html.to_string(),
+            None => match trusted {
+                true => expand_shortcodes(&post.content, &self.tera)
+                    .and_then(|markdown| self.renderer.render(&markdown, math)),
+                false => self.renderer.render_untrusted(&post.content, math),
+            }
+            .and_then(|html| responsive_images(&html, &self.config.images, locate))
+            .map_err(|source| BuildError::Markdown {
+                path: path.to_path_buf(),
+                source,
+            })?,
+        };
+        self.check_front_matter(&post)?;
+        self.check_alt_text(&post)?;
+        Ok((post, source_hash))
+    }
+
+    /// Warns about front-matter keys that neither ebolg, a taxonomy nor the
+    /// config's `extra` keys account for, about required keys a post leaves
+    /// out and about terms outside a taxonomy's `terms`, or fails the post
+    /// with `strict`.
+    fn check_front_matter(&self, post: &Post) -> Result<(), BuildError> {
+        let settings = &self.config.front_matter;
+        let unknown = post
+            .keys
+            .iter()
+            .filter(|(key, _)| {
+                post.metadata.extra.contains_key(key)
+                    && !self.config.taxonomies.contains_key(key)
+                    && !settings.extra.contains(key)
+            })
+            .map(|(key, line)| (format!("unknown key {:?}", key), *line));
+        let required = match self.is_post(&post.source_path, &post.lang) {
+            true => settings.required.as_slice(),
+            false => &[],
+        };
+        let missing = required
+            .iter()
+            .filter(|key| !post.keys.iter().any(|(other, _)| other == *key))
+            .map(|key| (format!("missing required key {:?}", key), 1));
+        let disallowed = self
+            .config
+            .taxonomies
+            .iter()
+            .filter(|(_, settings)| !settings.terms.is_empty())
+            .flat_map(|(taxonomy, settings)| {
+                let line = post
+                    .keys
+                    .iter()
+                    .find(|(key, _)| key == taxonomy)
+                    .map_or(1, |(_, line)| *line);
+                post.terms(taxonomy)
+                    .into_iter()
+                    .filter(|term| {
+                        let slug = slug::slugify(term);
+                        !settings
+                            .terms
+                            .iter()
+                            .any(|allowed| slug::slugify(allowed) == slug)
+                    })
+                    .map(move |term| {
+                        let message =
+                            format!("{:?} is not one of the terms of {:?}", term, taxonomy);
+                        (message, line)
+                    })
+            });
+
+        for (message, line) in unknown.chain(missing).chain(disallowed) {
+            let error = BuildError::FrontMatter {
+                path: post.source_path.clone(),
+                line,
+                column: 1,
+                message,
+            };
+            if self.options.strict {
+                return Err(error);
+            }
+            eprintln!("Warning: {}", error);
+        }
+        Ok(())
+    }
+
+    /// Warns about images without alt text, or fails the post with `strict`.
+    fn check_alt_text(&self, post: &Post) -> Result<(), BuildError> {
+        for src in images_without_alt(&post.html) {
+            if self.options.strict {
+                return Err(BuildError::MissingAlt {
+                    path: post.source_path.clone(),
+                    src,
+                });
+            }
+            eprintln!(
+                "Warning: image {} in {} has no alt text",
+                src,
+                post.source_path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether the build options leave `post` out entirely.
+    pub fn skips(&self, post: &Post) -> bool {
+        skip_reason(post, self.options).is_some()
+    }
+
+    fn insert_post(&mut self, post: Post, source_hash: u64) {
+        self.cache.set_html(&post.url, source_hash, &post.html);
+        if self.is_index_page(&post.source_path, &post.lang) {
+            self.index_pages.insert(post.lang.clone(), post);
+        } else if self.is_not_found_page(&post.source_path) {
+            self.not_found_page = Some(post);
+        } else if self.is_page(&post.source_path) {
+            self.pages.push(post);
+        } else {
+            self.posts.push(post);
+        }
+    }
+
+    /// Every post and page, whatever its kind.
+    fn sources(&self) -> impl Iterator<Item = &Post> {
+        self.posts
+            .iter()
+            .chain(&self.pages)
+            .chain(self.index_pages.values())
+            .chain(&self.not_found_page)
+    }
+
+    /// A failure for every source written to the same page as one before it,
+    /// which would silently replace it.
+    fn output_collisions(&self) -> Vec<BuildError> {
+        let mut sources: HashMap<String, &Post> = HashMap::new();
+        let mut collisions = Vec::new();
+        for post in self.sources() {
+            if let Some(other) = sources.insert(output_key(&post.html_path), post) {
+                collisions.push(BuildError::DuplicateOutput {
+                    path: post.source_path.clone(),
+                    other: other.source_path.clone(),
+                    url: post.url.clone(),
+                });
+            }
+        }
+        collisions
+    }
+
+    fn remove_by_source(&mut self, path: &Path) -> Option<Post> {
+        if let Some(lang) = self
+            .index_pages
+            .iter()
+            .find_map(|(lang, page)| (page.source_path == path).then(|| lang.clone()))
+        {
+            return self.index_pages.remove(&lang);
+        }
+        if self
+            .not_found_page
+            .as_ref()
+            .is_some_and(|page| page.source_path == path)
+        {
+            return self.not_found_page.take();
+        }
+        if let Some(position) = self.pages.iter().position(|page| page.source_path == path) {
+            return Some(self.pages.remove(position));
+        }
+
+        let position = self
+            .posts
+            .iter()
+            .position(|post| post.source_path == path)?;
+        Some(self.posts.remove(position))
+    }
+
+    /// Drops cache entries of posts that are no longer part of the site.
+    fn forget_removed_posts(&mut self) {
+        let urls: BTreeSet<&str> = self
+            .posts
+            .iter()
+            .chain(&self.pages)
+            .chain(self.index_pages.values())
+            .chain(&self.not_found_page)
+            .map(|post| post.url.as_str())
+            .collect();
+        self.cache.retain(|url| urls.contains(url));
+    }
+
+    fn index_post_urls(&mut self) {
+        self.post_urls = self
+            .posts
+            .iter()
+            .chain(&self.pages)
+            .chain(self.index_pages.values())
+            .chain(&self.not_found_page)
+            .map(|post| (normalize(&post.source_path), post.url.clone()))
+            .collect();
+    }
+
+    /// Points every post and page at its versions in the other languages:
+    /// those with the same path once the language suffix is dropped.
+    fn link_translations(&mut self) {
+        let mut versions: HashMap<PathBuf, Vec<Translation>> = HashMap::new();
+        for post in self.posts.iter().chain(&self.pages) {
+            versions
+                .entry(base_path(&post.source_path, &post.lang))
+                .or_default()
+                .push(Translation::new(&self.config, &post.lang, &post.url));
+        }
+        for post in self.posts.iter_mut().chain(&mut self.pages) {
+            let mut translations: Vec<Translation> = versions
+                [&base_path(&post.source_path, &post.lang)]
+                .iter()
+                .filter(|translation| translation.lang != post.lang)
+                .cloned()
+                .collect();
+            translations.sort_by_key(|translation| language_rank(&self.config, &translation.lang));
+            post.translations = translations;
+        }
+    }
+
+    /// The body of `post` with links to other posts' markdown files pointed at their pages,
+    /// and links to other sites marked up as `[external_links]` asks.
+    fn linked_html(&self, post: &Post) -> String {
+        let html = rewrite_post_links(
+            &post.html,
+            &post.source_path,
+            &self.source_dir,
+            &self.post_urls,
+        );
+        decorate_external_links(&html, &self.config.base_url, &self.config.external_links)
+    }
+
+    /// URLs of the posts and pages whose page is missing or would render
+    /// differently now.
+    fn stale_urls(&self) -> BTreeSet<String> {
+        let posts = (0..self.posts.len()).map(|i| (&self.posts[i], self.page_key(i)));
+        let pages = self
+            .pages
+            .iter()
+            .map(|page| (page, self.standalone_page_key(page)));
+        posts
+            .chain(pages)
+            .filter(|(post, key)| {
+                !post.html_path.is_file() || !self.cache.page_is_fresh(&post.url, *key)
+            })
+            .map(|(post, _)| post.url.clone())
+            .collect()
+    }
+
+    /// Keeps the posts and pages of each language together, in the order of
+    /// `site_languages` and with the unlisted ones last, for `posts_in` and
+    /// `pages_in`.
+    fn sort_posts(&mut self) {
+        let config = &self.config;
+        let rank = |post: &Post| (language_rank(config, &post.lang), post.metadata.unlisted);
+        self.posts.sort_by(|a, b| {
+            rank(a)
+                .cmp(&rank(b))
+                .then_with(|| b.date.cmp(&a.date))
+                .then_with(|| a.url.cmp(&b.url))
+        });
+        self.pages.sort_by(|a, b| {
+            rank(a)
+                .cmp(&rank(b))
+                .then_with(|| a.title.cmp(&b.title))
+                .then_with(|| a.url.cmp(&b.url))
+        });
+    }
+
+    /// The listed posts written in `lang`, newest first.
+    fn posts_in(&self, lang: &str) -> &[Post] {
+        in_language(&self.config, &self.posts, lang)
+    }
+
+    /// The listed standalone pages written in `lang`, sorted by title.
+    fn pages_in(&self, lang: &str) -> &[Post] {
+        in_language(&self.config, &self.pages, lang)
+    }
+
+    /// Whether `path` is the root-level `index.md` or one of its translations.
+    fn is_index_page(&self, path: &Path, lang: &str) -> bool {
+        base_path(path, lang) == self.source_dir.join(INDEX_PAGE)
+    }
+
+    /// Whether `path` is the root-level `404.md`, in any of the source formats.
+    fn is_not_found_page(&self, path: &Path) -> bool {
+        path.with_extension("md") == self.source_dir.join(NOT_FOUND_PAGE)
+    }
+
+    fn is_page(&self, path: &Path) -> bool {
+        path.starts_with(self.source_dir.join(PAGES_DIR))
+    }
+
+    /// Whether the file at `path` is a dated post rather than a page, an
+    /// index or the not-found text.
+    fn is_post(&self, path: &Path, lang: &str) -> bool {
+        !self.is_index_page(path, lang) && !self.is_not_found_page(path) && !self.is_page(path)
+    }
+
+    /// URLs of the posts next to `path` in the post order, if it is a post.
+    fn neighbour_urls(&self, path: &Path) -> BTreeSet<String> {
+        let mut urls = BTreeSet::new();
+        if let Some(i) = self.posts.iter().position(|post| post.source_path == path) {
+            let neighbours = [i.checked_sub(1), Some(i + 1)];
+            for post in neighbours
+                .into_iter()
+                .flatten()
+                .filter_map(|j| self.posts.get(j))
+            {
+                urls.insert(post.url.clone());
+            }
+        }
+        urls
+    }
+
+    fn render_posts_by_url(&mut self, urls: &BTreeSet<String>) -> Result<(), BuildError> {
+        let indices: Vec<usize> = (0..self.posts.len())
+            .filter(|&i| urls.contains(&self.posts[i].url))
+            .collect();
+        self.render_pages(&indices)?;
+
+        for i in 0..self.pages.len() {
+            let page = &self.pages[i];
+            if !urls.contains(&page.url) {
+                continue;
+            }
+            let result = render_standalone_page(
+                &self.tera,
+                &self.config,
+                page,
+                &self.linked_html(page),
+                self.pages_in(&page.lang),
+            );
+            match result {
+                Ok(()) => {
+                    let page_key = self.standalone_page_key(page);
+                    self.cache.set_page(&self.pages[i].url, page_key);
+                }
+                Err(e) if self.options.keep_going => self.failures.push(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the pages of the posts at `indices` and records them in the cache.
+    /// Posts are sorted and linked before this point, so each page only needs its
+    /// own index to find its neighbours and the pages can render in parallel.
+    fn render_pages(&mut self, indices: &[usize]) -> Result<(), BuildError> {
+        let results: Vec<_> = indices
+            .par_iter()
+            .map(|&i| self.render_post_at(i))
+            .collect();
+        for (&i, result) in indices.iter().zip(results) {
+            match result {
+                Ok(()) => {
+                    let page_key = self.page_key(i);
+                    self.cache.set_page(&self.posts[i].url, page_key);
+                }
+                Err(e) if self.options.keep_going => self.failures.push(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Newest first: the previous post is the next entry in the list, the next post the one before.
+    /// Both are in the post's language. Unlisted posts have neither and are
+    /// neither, sorting after the listed ones.
+    fn neighbours(&self, i: usize) -> (Option<&Post>, Option<&Post>) {
+        let post = &self.posts[i];
+        if post.metadata.unlisted {
+            return (None, None);
+        }
+        let neighbour = |other: &&Post| other.lang == post.lang && !other.metadata.unlisted;
+        let prev_post = self.posts.get(i + 1);
+        let next_post = i.checked_sub(1).and_then(|j| self.posts.get(j));
+        (prev_post.filter(neighbour), next_post.filter(neighbour))
+    }
+
+    fn related(&self, i: usize) -> Related<'_> {
+        let (prev_post, next_post) = self.neighbours(i);
+        let post = &self.posts[i];
+        let posts = self.posts_in(&post.lang);
+        Related {
+            prev_post,
+            next_post,
+            series: series_parts(&self.config, post, posts),
+            related_posts: related_posts(post, posts, self.config.related.count),
+            pages: self.pages_in(&post.lang),
+        }
+    }
+
+    fn render_post_at(&self, i: usize) -> Result<(), BuildError> {
+        render_post(
+            &self.tera,
+            &self.config,
+            &self.posts[i],
+            &self.linked_html(&self.posts[i]),
+            &self.related(i),
+        )
+    }
+
+    /// Hash of everything the page of the post at `i` is rendered from.
+    fn page_key(&self, i: usize) -> u64 {
+        let post = &self.posts[i];
+        let context = serde_json::to_string(&(post, self.related(i))).unwrap_or_default();
+        hash((context, self.linked_html(post), &self.config.fingerprints))
+    }
+
+    /// Hash of everything a standalone page is rendered from.
+    fn standalone_page_key(&self, page: &Post) -> u64 {
+        let context = serde_json::to_string(&(page, self.pages_in(&page.lang))).unwrap_or_default();
+        hash((context, self.linked_html(page), &self.config.fingerprints))
+    }
+
+    /// Renders the pages built from the whole post collection. Every language
+    /// has taxonomy, archive and index pages and a feed of its own, listing
+    /// the posts written in it.
+    fn render_listings(&self) -> Result<Vec<PathBuf>, BuildError> {
+        let mut written = Vec::new();
+        let languages = site_languages(&self.config);
+        let mut collections = Vec::new();
+        for lang in &languages {
+            let dir = language_dir(&self.config, lang);
+            let posts = self.posts_in(lang);
+            let pages = self.pages_in(lang);
+            let mut taxonomies = BTreeMap::new();
+            for (taxonomy, settings) in &self.config.taxonomies {
+                let terms = collect_tags(posts, taxonomy, settings.ordered, &dir);
+                written.extend(render_tag_pages(
+                    &self.tera,
+                    &self.config,
+                    &self.output_dir,
+                    lang,
+                    taxonomy,
+                    settings,
+                    &terms,
+                    pages,
+                    self.options.today(),
+                )?);
+                taxonomies.insert(taxonomy.as_str(), terms);
+            }
+            let years = collect_archive(posts, &dir);
+            written.extend(render_archive_pages(
+                &self.tera,
+                &self.config,
+                &self.output_dir,
+                lang,
+                &years,
+                pages,
+            )?);
+            let authors = collect_authors(posts);
+            written.extend(render_author_pages(
+                &self.tera,
+                &self.config,
+                &self.output_dir,
+                lang,
+                &authors,
+                pages,
+            )?);
+            render_feed(
+                &self.tera,
+                &self.config,
+                &self.output_dir,
+                &dir,
+                language_title(&self.config, lang),
+                posts,
+                self.options.today(),
+            )?;
+            collections.push((*lang, taxonomies, years, authors));
+        }
+
+        // The sitemap lists the default index itself; the others are listings.
+        let translated_indexes: Vec<(String, Vec<&Post>)> = languages[1..]
+            .iter()
+            .map(|lang| {
+                let url = format!("{}index.html", language_dir(&self.config, lang));
+                (url, self.posts_in(lang).iter().collect())
+            })
+            .collect();
+        let listings: Vec<(&str, &[&Post])> = collections
+            .iter()
+            .flat_map(|(_, taxonomies, years, authors)| {
+                taxonomies
+                    .values()
+                    .flatten()
+                    .map(|tag| (tag.url.as_str(), tag.posts.as_slice()))
+                    .chain(
+                        periods(years).map(|period| (period.url.as_str(), period.posts.as_slice())),
+                    )
+                    .chain(
+                        authors
+                            .iter()
+                            .map(|entry| (entry.author.url.as_str(), entry.posts.as_slice())),
+                    )
+            })
+            .chain(
+                translated_indexes
+                    .iter()
+                    .map(|(url, posts)| (url.as_str(), posts.as_slice())),
+            )
+            .collect();
+        written.extend(render_search(
+            &self.tera,
+            &self.config,
+            &self.output_dir,
+            &self.posts,
+            &self.pages,
+        )?);
+        render_sitemap(
+            &self.tera,
+            &self.config,
+            &self.output_dir,
+            self.index_pages.get(&self.config.default_language),
+            &self.posts,
+            &self.pages,
+            &listings,
+        )?;
+
+        if !self.provides_static(ROBOTS_FILE) {
+            write_robots(&self.config, &self.output_dir)?;
+        }
+
+        for (lang, taxonomies, _, _) in &collections {
+            written.extend(self.render_index_page(lang, taxonomies)?);
+        }
+        written.push(self.render_not_found_page()?);
+        Ok(written)
+    }
+
+    /// Writes `404.html` with the `404.html` template, which shows the text of
+    /// a root-level `404.md` or a default message.
+    fn render_not_found_page(&self) -> Result<PathBuf, BuildError> {
+        let title = match &self.not_found_page {
+            Some(page) => page.title.clone(),
+            None => string(&self.config, &self.config.default_language, "not_found"),
+        };
+        let content = self
+            .not_found_page
+            .as_ref()
+            .map_or(String::new(), |page| self.linked_html(page));
+
+        let mut context = Context::new();
+        context.insert("config", &self.config);
+        context.insert("title", &title);
+        context.insert("content", &content);
+        context.insert("section", "404");
+        context.insert("url", &format!("/{}", NOT_FOUND_FILE));
+        let pages: Vec<&Post> = self
+            .pages
+            .iter()
+            .filter(|page| !page.metadata.unlisted)
+            .collect();
+        context.insert("pages", &pages);
+
+        let html_path = self.output_dir.join(NOT_FOUND_FILE);
+        render_page(
+            &self.tera,
+            &self.config,
+            NOT_FOUND_FILE,
+            &context,
+            &html_path,
+        )?;
+        crate::verbose!("Not-found page generated: {:?}", html_path);
+        Ok(html_path)
+    }
+
+    /// Whether the static directory, or the theme's, has a file at `path`
+    /// relative to the output root, which replaces the generated one.
+    fn provides_static(&self, path: &str) -> bool {
+        let output_path = self.output_dir.join(path);
+        self.assets
+            .iter()
+            .any(|asset| self.asset_output_path(asset) == output_path)
+    }
+
+    /// Writes the `index.html` of `lang` listing every post in it, newest first. A
+    /// root-level `index.md`, or its translation, supplies the page title and the
+    /// content shown above the list; otherwise the configured site title is used.
+    fn render_index_page(
+        &self,
+        lang: &str,
+        taxonomies: &BTreeMap<&str, Vec<Tag>>,
+    ) -> Result<Vec<PathBuf>, BuildError> {
+        let mut written = Vec::new();
+        let index_page = self.index_pages.get(lang);
+        let site_title = language_title(&self.config, lang);
+        let title = match index_page {
+            Some(page) => page.title.clone(),
+            None if !site_title.is_empty() => site_title.to_string(),
+            None => string(&self.config, lang, "posts"),
+        };
+        let intro = index_page.map_or(String::new(), |page| self.linked_html(page));
+        let description = index_page.map_or("", |page| page.summary.as_str());
+        let translations: Vec<Translation> = site_languages(&self.config)
+            .into_iter()
+            .filter(|other| *other != lang)
+            .map(|other| Translation::new(&self.config, other, &language_dir(&self.config, other)))
+            .collect();
+
+        let dir = language_dir(&self.config, lang);
+        let posts = self.posts_in(lang);
+        let pages = self.pages_in(lang);
+        let listing = paginate(posts, self.config.paginate, &dir);
+        for page in &listing {
+            let mut context = Context::new();
+            context.insert("config", &self.config);
+            context.insert("title", &title);
+            context.insert("description", description);
+            // The introduction belongs on the front page only.
+            let content = if page.paginator.current == 1 {
+                intro.as_str()
+            } else {
+                ""
+            };
+            context.insert("content", content);
+            context.insert("posts", page.posts);
+            context.insert("paginator", &page.paginator);
+            context.insert("tags", &taxonomies.get(TAGS));
+            context.insert("taxonomies", taxonomies);
+            context.insert("section", "index");
+            context.insert("url", &page.path);
+            context.insert("lang", lang);
+            context.insert("translations", &translations);
+            context.insert("pages", pages);
+
+            let html_path = self.output_dir.join(page.path.trim_start_matches('/'));
+            render_page(&self.tera, &self.config, "index.html", &context, &html_path)?;
+            written.push(html_path);
+        }
+        remove_extra_pages(&self.output_dir, &dir, listing.len())?;
+        println!(
+            "Index generated: {:?} ({} posts on {} pages)",
+            self.output_dir
+                .join(dir.trim_start_matches('/'))
+                .join("index.html"),
+            posts.len(),
+            listing.len()
+        );
+
+        Ok(written)
+    }
+
+    /// Mirrors a source path into the output directory.
+    fn output_path(&self, path: &Path) -> PathBuf {
+        let relative = path.strip_prefix(&self.source_dir).unwrap_or(path);
+        self.output_dir.join(relative)
+    }
+
+    /// Expands the configured permalink pattern into the output path of a post,
+    /// relative to the output directory and starting with `/`. Pages aren't
+    /// dated, so they mirror their path under `PAGES_DIR` at the output root,
+    /// in the pattern's style of `.html` files or directories. Hosts look for
+    /// the not-found page at a fixed path, while `index.md` is rendered into the
+    /// index. Posts in other languages than the default one go under `/<lang>/`.
+    fn permalink(&self, path: &Path, post: &Post) -> String {
+        if self.is_not_found_page(path) {
+            return format!("/{}", NOT_FOUND_FILE);
+        }
+        let lang_dir = language_dir(&self.config, &post.lang);
+        if self.is_index_page(path, &post.lang) {
+            return format!("{}index.html", lang_dir);
+        }
+        let pages_dir = self.source_dir.join(PAGES_DIR);
+        let (relative, pattern) = match path.strip_prefix(&pages_dir) {
+            Ok(relative) if self.config.permalink.ends_with('/') => (relative, "/{dir}/{slug}/"),
+            Ok(relative) => (relative, DEFAULT_PERMALINK),
+            Err(_) => (
+                path.strip_prefix(&self.source_dir).unwrap_or(path),
+                self.config.permalink.as_str(),
+            ),
+        };
+        let dir: Vec<_> = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let stem = base_stem(relative, &post.lang);
+        let slug = post.metadata.slug.as_deref().unwrap_or(&stem);
+
+        let expanded = pattern
+            .replace("{year}", &post.date.format("%Y").to_string())
+            .replace("{month}", &post.date.format("%m").to_string())
+            .replace("{day}", &post.date.format("%d").to_string())
+            .replace("{dir}", &dir.join("/"))
+            .replace("{slug}", slug);
+
+        let expanded = format!("{}{}", lang_dir, expanded);
+
+        // Empty placeholders, like `{dir}` for posts at the root, leave doubled slashes.
+        let mut page_path: String = expanded
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .flat_map(|segment| ["/", segment])
+            .collect();
+        if expanded.ends_with('/') {
+            page_path.push_str("/index.html");
+        }
+        page_path
+    }
+}
+
+/// The other posts and pages a post's page links to.
+#[derive(Debug, Default, Serialize)]
+pub struct Related<'a> {
+    pub prev_post: Option<&'a Post>,
+    pub next_post: Option<&'a Post>,
+    pub series: Vec<SeriesPart<'a>>,
+    /// Posts sharing the most tags, for the "Related posts" list.
+    pub related_posts: Vec<&'a Post>,
+    pub pages: &'a [Post],
+}
+
+pub fn render_post(
+    tera: &Tera,
+    config: &Config,
+    post: &Post,
+    content: &str,
+    related: &Related,
+) -> Result<(), BuildError> {
+    let mut context = Context::new();
+    context.insert("config", config);
+    context.insert("title", &post.title);
+    context.insert("description", &post.summary);
+    context.insert("content", content);
+    context.insert("post", post);
+    context.insert("prev_post", &related.prev_post);
+    context.insert("next_post", &related.next_post);
+    let taxonomies: BTreeMap<&str, Vec<Tag>> = config
+        .taxonomies
+        .keys()
+        .map(|taxonomy| (taxonomy.as_str(), post_tags(config, post, taxonomy)))
+        .collect();
+    context.insert("tags", &taxonomies.get(TAGS));
+    context.insert("taxonomies", &taxonomies);
+    context.insert("series", &related.series);
+    context.insert("related_posts", &related.related_posts);
+    context.insert("math", &post.math(config));
+    context.insert("diagrams", &has_diagrams(&post.content));
+    context.insert("comments", &post.comments(config));
+    context.insert("section", "post");
+    context.insert("url", &post.url);
+    context.insert("lang", &post.lang);
+    context.insert("translations", &post.translations);
+    context.insert("pages", related.pages);
+
+    let html_length = render_page(
+        tera,
+        config,
+        &post.template("post"),
+        &context,
+        &post.html_path,
+    )?;
+    crate::verbose!("HTML content length: {}", html_length);
+    crate::verbose!("HTML file generated: {:?}", post.html_path);
+
+    Ok(())
+}
+
+/// Writes a standalone page with the `page.html` template.
+pub fn render_standalone_page(
+    tera: &Tera,
+    config: &Config,
+    page: &Post,
+    content: &str,
+    pages: &[Post],
+) -> Result<(), BuildError> {
+    let mut context = Context::new();
+    context.insert("config", config);
+    context.insert("title", &page.title);
+    context.insert("description", &page.summary);
+    context.insert("content", content);
+    context.insert("page", page);
+    context.insert("math", &page.math(config));
+    context.insert("diagrams", &has_diagrams(&page.content));
+    context.insert("section", "page");
+    context.insert("url", &page.url);
+    context.insert("lang", &page.lang);
+    context.insert("translations", &page.translations);
+    context.insert("pages", pages);
+
+    render_page(
+        tera,
+        config,
+        &page.template("page"),
+        &context,
+        &page.html_path,
+    )?;
+    crate::verbose!("Page generated: {:?}", page.html_path);
+
+    Ok(())
+}
+
+/// Building into the source directory, or a directory holding it, would write
+/// pages among the posts and read them back as sources, and building into
+/// the static directory would copy each build into the next.
+fn validate_output_dir(
+    source_dir: &Path,
+    output_dir: &Path,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let source_dir = resolve_path(source_dir)?;
+    let output_dir = resolve_path(output_dir)?;
+    if source_dir.starts_with(&output_dir) {
+        return Err(format!(
+            "Refusing to build into {:?}: it contains the source directory",
+            output_dir
+        )
+        .into());
+    }
+    let static_dir = resolve_path(&source_dir.join(&config.assets.static_dir))?;
+    if output_dir.starts_with(&static_dir) {
+        return Err(format!(
+            "Refusing to build into {:?}: it is inside the static directory {:?}",
+            output_dir, static_dir
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// `path` made absolute with its symlinks resolved, as far as it exists.
+fn resolve_path(path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let path = normalize(&std::path::absolute(path)?);
+    let mut existing = path.as_path();
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => return Ok(path),
+        }
+    }
+    let canonical = existing.canonicalize()?;
+    match path.strip_prefix(existing)? {
+        rest if rest.as_os_str().is_empty() => Ok(canonical),
+        rest => Ok(canonical.join(rest)),
+    }
+}
+
+fn validate_permalink(pattern: &str) -> Result<(), Box<dyn Error>> {
+    let placeholder = Regex::new(r"\{([^}]*)\}").unwrap();
+    for captures in placeholder.captures_iter(pattern) {
+        let name = &captures[1];
+        if !PERMALINK_PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder {{{}}} in permalink {:?}, expected one of: {}",
+                name,
+                pattern,
+                PERMALINK_PLACEHOLDERS.join(", ")
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Taxonomy names are front-matter keys and output directories, so they must
+/// be slugs and can't be a key ebolg already reads.
+fn validate_taxonomies(config: &Config) -> Result<(), Box<dyn Error>> {
+    for taxonomy in config.taxonomies.keys() {
+        if slug::slugify(taxonomy) != *taxonomy {
+            return Err(format!(
+                "Invalid taxonomy name {:?}, expected lowercase letters, digits and dashes",
+                taxonomy
+            )
+            .into());
+        }
+        if METADATA_KEYS.contains(&taxonomy.as_str()) {
+            return Err(format!(
+                "Taxonomy {:?} clashes with the front-matter key of the same name",
+                taxonomy
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// The comments and analytics providers must be ones the templates know.
+fn validate_providers(config: &Config) -> Result<(), Box<dyn Error>> {
+    let settings = [
+        (
+            "comments",
+            &config.comments.provider,
+            COMMENT_PROVIDERS.as_slice(),
+        ),
+        (
+            "analytics",
+            &config.analytics.provider,
+            ANALYTICS_PROVIDERS.as_slice(),
+        ),
+    ];
+    for (name, provider, providers) in settings {
+        if !provider.is_empty() && !providers.contains(&provider.as_str()) {
+            return Err(format!(
+                "Unknown {} provider {:?}, expected one of: {}",
+                name,
+                provider,
+                providers.join(", ")
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn validate_precompress(config: &Config) -> Result<(), Box<dyn Error>> {
+    let formats: Vec<&str> = PRECOMPRESS_FORMATS.iter().map(|(name, _)| *name).collect();
+    if let Some(format) = config
+        .precompress
+        .iter()
+        .find(|format| !formats.contains(&format.as_str()))
+    {
+        return Err(format!(
+            "Unknown precompress format {:?}, expected one of: {}",
+            format,
+            formats.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn validate_formats(config: &Config) -> Result<(), Box<dyn Error>> {
+    if let Some(format) = config
+        .formats
+        .iter()
+        .find(|format| !SOURCE_FORMATS.contains(&format.as_str()))
+    {
+        let hint = match format.as_str() {
+            "adoc" => " (adoc needs ebolg built with the asciidoc feature)",
+            _ => "",
+        };
+        return Err(format!(
+            "Unknown format {:?}, expected one of: {}{}",
+            format,
+            SOURCE_FORMATS.join(", "),
+            hint
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Posts can only be pulled from a CMS by ebolg built with the `cms` feature.
+fn validate_cms(config: &Config) -> Result<(), Box<dyn Error>> {
+    if cfg!(not(feature = "cms")) && !config.cms.url.is_empty() {
+        return Err(
+            "[cms] sets a url, but pulling posts needs ebolg built with the cms feature".into(),
+        );
+    }
+    Ok(())
+}
+
+/// Language codes name output directories and file suffixes, so they must be slugs.
+fn validate_languages(config: &Config) -> Result<(), Box<dyn Error>> {
+    for lang in std::iter::once(&config.default_language).chain(config.languages.keys()) {
+        if lang.is_empty() || slug::slugify(lang) != *lang {
+            return Err(format!(
+                "Invalid language code {:?}, expected lowercase letters, digits and dashes",
+                lang
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Output paths compared case-insensitively, since `Post.html` and
+/// `post.html` are the same file on macOS and Windows.
+fn output_key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// Why the build options leave `post` out, if they do, with the flag that
+/// builds it anyway. Future posts are those dated after today, so a daily
+/// rebuild publishes them on their date, or after the source date when one
+/// is set, so the same sources publish the same posts wherever they're built.
+fn skip_reason(post: &Post, options: BuildOptions) -> Option<(&'static str, &'static str)> {
+    if post.metadata.draft && !options.drafts {
+        return Some(("draft", "--drafts"));
+    }
+    if post.date > options.today() && !options.future {
+        return Some(("future post", "--future"));
+    }
+    None
+}
+
+/// Loads the site at `source_dir` as a strict build with drafts would, and
+/// reports every problem found in its sources without writing the site: bad
+/// front matter, dates that don't parse, terms outside a taxonomy's list,
+/// posts sharing a permalink and anything else that fails a post.
+pub fn check(
+    source_dir: &Path,
+    config: Config,
+    options: BuildOptions,
+) -> Result<(), Box<dyn Error>> {
+    let options = BuildOptions {
+        drafts: true,
+        future: true,
+        keep_going: true,
+        strict: true,
+        dry_run: false,
+        prune: false,
+        pull_cms: false,
+        ..options
+    };
+    // Loading needs an output directory to look up cached bodies in.
+    let scratch_dir = std::env::temp_dir().join(format!("ebolg-check-{}", std::process::id()));
+    let result =
+        Site::load(source_dir, &scratch_dir, config, options).and_then(|mut site| site.check());
+    if scratch_dir.exists() {
+        fs::remove_dir_all(&scratch_dir)?;
+    }
+    result
+}
+
+/// Author keys name the directories of author pages, so they must be slugs.
+fn validate_authors(config: &Config) -> Result<(), Box<dyn Error>> {
+    for id in config.authors.keys() {
+        if slug::slugify(id) != *id {
+            return Err(format!(
+                "Invalid author key {:?}, expected lowercase letters, digits and dashes",
+                id
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// The run of listed `posts`, sorted by `Site::sort_posts`, written in `lang`.
+fn in_language<'a>(config: &Config, posts: &'a [Post], lang: &str) -> &'a [Post] {
+    let rank = language_rank(config, lang);
+    let start = posts.partition_point(|post| language_rank(config, &post.lang) < rank);
+    let end = posts.partition_point(|post| {
+        (language_rank(config, &post.lang), post.metadata.unlisted) < (rank, true)
+    });
+    &posts[start..end]
+}
+
+/// Walks `dir_path` gathering sources in the configured formats and assets,
+/// skipping the site configuration directories of `root`, the canonicalized
+/// source directory, and the files `filter` leaves out. `walked` holds the canonicalized
+/// directories walked so far, along with those to skip, so no directory is
+/// walked twice. Symlinks to directories inside `root` aren't followed, since
+/// the directory is walked under its own name.
+fn collect_sources(
+    dir_path: &Path,
+    root: &Path,
+    walked: &mut HashSet<PathBuf>,
+    filter: &SourceFilter,
+    config: &Config,
+    markdown_files: &mut Vec<PathBuf>,
+    assets: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    for path in sorted_entries(dir_path)? {
+        if filter.is_ignored(&path) {
+            crate::verbose!("Ignored: {:?}", path);
+            continue;
+        }
+        if path.is_dir() {
+            let canonical = path.canonicalize()?;
+            let dir_name = canonical.file_name().unwrap_or_default();
+            if canonical.parent() == Some(root)
+                && SITE_DIRS.iter().any(|dir| OsStr::new(dir) == dir_name)
+            {
+                continue;
+            }
+            if path.is_symlink() && canonical.starts_with(root) {
+                crate::verbose!("Skipping {:?}: links into the source directory", path);
+                continue;
+            }
+            if !walked.insert(canonical) {
+                crate::verbose!("Skipping {:?}: a directory already walked or skipped", path);
+                continue;
+            }
+
+            collect_sources(&path, root, walked, filter, config, markdown_files, assets)?;
+        } else {
+            let copied = filter.is_copied(&path) || has_extension(&path, &config.assets.extensions);
+            let markdown = is_source(&path, &config.formats);
+            if !copied && !markdown {
+                continue;
+            }
+            if !filter.is_included(&path) {
+                crate::verbose!("Not included: {:?}", path);
+            } else if filter.is_copied(&path) || !markdown {
+                assets.push(path);
+            } else {
+                markdown_files.push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Gathers every file below `dir_path`, following each directory once
+/// however many symlinks lead to it.
+fn collect_files(dir_path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let mut walked = HashSet::from([dir_path.canonicalize()?]);
+    collect_files_in(dir_path, &mut walked, files)
+}
+
+fn collect_files_in(
+    dir_path: &Path,
+    walked: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    for path in sorted_entries(dir_path)? {
+        if !path.is_dir() {
+            files.push(path);
+        } else if walked.insert(path.canonicalize()?) {
+            collect_files_in(&path, walked, files)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The entries of a directory by name, since `read_dir` order depends on the
+/// file system and every build should come out the same.
+pub fn sorted_entries(dir_path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(dir_path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Removes the directories under `dir` that have no files left, returning
+/// whether `dir` itself ended up empty.
+fn remove_empty_dirs(dir: &Path) -> Result<bool, Box<dyn Error>> {
+    let mut empty = true;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() && remove_empty_dirs(&path)? {
+            fs::remove_dir(&path)?;
+        } else {
+            empty = false;
+        }
+    }
+    Ok(empty)
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension().and_then(OsStr::to_str).is_some_and(|ext| {
+        extensions
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(ext))
+    })
+}
+
+/// Renders a single post, in any of the `SOURCE_FORMATS`, into `output_dir`,
+/// without any listing pages.
+pub fn build_file(
+    source_path: &Path,
+    output_dir: &Path,
+    config: &Config,
+    options: BuildOptions,
+) -> Result<(), Box<dyn Error>> {
+    let formats = SOURCE_FORMATS.map(String::from);
+    if !is_source(source_path, &formats) {
+        return Err(format!(
+            "Only {} files can be built on their own",
+            SOURCE_FORMATS.join(", ")
+        )
+        .into());
+    }
+
+    let site_dir = source_path.parent().unwrap_or(Path::new("."));
+    let theme_dir = theme_dir(site_dir, config.theme.name.as_deref())?;
+    let tera = load_templates(site_dir, theme_dir.as_deref())?;
+    let renderer = MarkdownRenderer::new(config)?;
+    let mut config = Config {
+        data: load_data(site_dir)?,
+        strings: load_strings(site_dir, config)?,
+        ..config.clone()
+    };
+    if !options.analytics {
+        config.analytics.provider.clear();
+    }
+    let config = &config;
+
+    let file_name = source_path.file_name().unwrap().to_str().unwrap();
+    let target_file = output_dir.join(file_name).with_extension("html");
+    let url = format!("/{}", target_file.file_name().unwrap().to_string_lossy());
+    let lang = file_language(config, source_path);
+    let mut post = load_post(source_path, site_dir, lang, options.source_date, true)?;
+    if let Some(history) = History::load(site_dir).file(source_path) {
+        history.apply(&mut post);
+    }
+    post.html_path = target_file;
+    post.url = url;
+    post.authors = post_authors(config, &post);
+    if let Some((reason, flag)) = skip_reason(&post, options) {
+        println!(
+            "Skipping {}: {:?} (pass {} to build it)",
+            reason, source_path, flag
+        );
+        return Ok(());
+    }
+    post.html = expand_shortcodes(&post.content, &tera)
+        .and_then(|markdown| renderer.render(&markdown, post.math(config)))
+        .map_err(|source| BuildError::Markdown {
+            path: source_path.to_path_buf(),
+            source,
+        })?;
+
+    let scratch_dir = match options.dry_run {
+        true => Some(scratch_copy(output_dir)?),
+        false => None,
+    };
+    if let Some(scratch_dir) = &scratch_dir {
+        post.html_path = scratch_dir.join(post.html_path.file_name().unwrap());
+    }
+    let html = decorate_external_links(&post.html, &config.base_url, &config.external_links);
+    let result = render_post(&tera, config, &post, &html, &Related::default());
+    match scratch_dir {
+        Some(scratch_dir) => {
+            let report = report_changes(output_dir, &scratch_dir);
+            Ok(result.map_err(Box::from).and(report)?)
+        }
+        None => Ok(result?),
+    }
+}
@@ -0,0 +1,107 @@
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::output::write_atomic;
+use crate::post::Post;
+use crate::templates::render_page;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::path::Path;
+use tera::{Context, Tera};
+
+/// File name of the sitemap, written at the root of the output directory.
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+
+/// File name of the crawler rules pointing at the sitemap, written next to it.
+pub const ROBOTS_FILE: &str = "robots.txt";
+
+#[derive(Debug, Serialize)]
+struct SitemapEntry {
+    loc: String,
+    lastmod: NaiveDate,
+}
+
+fn newest_date<'a>(posts: impl Iterator<Item = &'a Post>) -> Option<NaiveDate> {
+    posts.map(|post| post.updated).max()
+}
+
+/// The posts of `posts` that aren't unlisted.
+fn listed(posts: &[Post]) -> impl Iterator<Item = &Post> {
+    posts.iter().filter(|post| !post.metadata.unlisted)
+}
+
+/// Writes a sitemap listing the index, every listed post and the `listings`, such as
+/// tag and archive pages, by URL. Posts are dated by their `updated` date, and
+/// listing pages take the latest one among their posts. Like the feed, this needs
+/// `base_url` since sitemap locations must be absolute.
+pub fn render_sitemap(
+    tera: &Tera,
+    config: &Config,
+    output_dir: &Path,
+    index_page: Option<&Post>,
+    posts: &[Post],
+    pages: &[Post],
+    listings: &[(&str, &[&Post])],
+) -> Result<(), BuildError> {
+    if config.base_url.is_empty() {
+        println!(
+            "Skipping {}: set base_url in ebolg.toml to generate it",
+            SITEMAP_FILE
+        );
+        return Ok(());
+    }
+
+    let mut entries = Vec::new();
+    if let Some(lastmod) = newest_date(listed(posts).chain(index_page)) {
+        entries.push(SitemapEntry {
+            loc: config.absolute_url("/index.html"),
+            lastmod,
+        });
+    }
+    entries.extend(listed(posts).chain(listed(pages)).map(|post| SitemapEntry {
+        loc: config.absolute_url(&post.url),
+        lastmod: post.updated,
+    }));
+    for (url, posts) in listings {
+        if let Some(lastmod) = newest_date(posts.iter().copied()) {
+            entries.push(SitemapEntry {
+                loc: config.absolute_url(url),
+                lastmod,
+            });
+        }
+    }
+
+    let mut context = Context::new();
+    context.insert("config", config);
+    context.insert("entries", &entries);
+
+    let sitemap_path = output_dir.join(SITEMAP_FILE);
+    render_page(tera, config, SITEMAP_FILE, &context, &sitemap_path)?;
+    println!(
+        "Sitemap generated: {:?} ({} urls)",
+        sitemap_path,
+        entries.len()
+    );
+
+    Ok(())
+}
+
+/// Writes a `robots.txt` allowing every crawler and pointing them at the
+/// sitemap. Like the sitemap, this needs `base_url`.
+pub fn write_robots(config: &Config, output_dir: &Path) -> Result<(), BuildError> {
+    if config.base_url.is_empty() {
+        return Ok(());
+    }
+
+    let robots_path = output_dir.join(ROBOTS_FILE);
+    let robots = format!(
+        "User-agent: *\nAllow: /\n\nSitemap: {}\n",
+        config.absolute_url(&format!("/{}", SITEMAP_FILE))
+    );
+    write_atomic(&robots_path, robots).map_err(|source| BuildError::Write {
+        path: robots_path.clone(),
+        source,
+    })?;
+    crate::verbose!("Robots file generated: {:?}", robots_path);
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use crate::config::{Config, TaxonomyConfig, TAGS};
+use crate::error::BuildError;
+use crate::feed::write_feeds;
+use crate::i18n::string;
+use crate::languages::{language_dir, language_title};
+use crate::paginate::{paginate, remove_extra_pages};
+use crate::post::{humanize, Post};
+use crate::templates::render_page;
+use chrono::NaiveDate;
+use serde::Serialize;
+use slug::slugify;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+/// A term of a taxonomy, such as one tag, with the posts filed under it.
+#[derive(Debug, Serialize)]
+pub struct Tag<'a> {
+    pub taxonomy: &'a str,
+    pub name: &'a str,
+    pub slug: String,
+    pub url: String,
+    pub count: usize,
+    pub posts: Vec<&'a Post>,
+}
+
+impl<'a> Tag<'a> {
+    /// A term whose pages go under `dir`, the directory of a language.
+    fn new(taxonomy: &'a str, name: &'a str, slug: String, dir: &str) -> Self {
+        Tag {
+            taxonomy,
+            name,
+            url: format!("{}{}/{}/index.html", dir, taxonomy, slug),
+            slug,
+            count: 0,
+            posts: Vec::new(),
+        }
+    }
+}
+
+/// Where a post sits in a term of an ordered taxonomy, such as a series.
+#[derive(Debug, Serialize)]
+pub struct SeriesPart<'a> {
+    pub taxonomy: &'a str,
+    pub name: &'a str,
+    pub url: String,
+    /// 1-based position of the post, oldest first.
+    pub part: usize,
+    pub count: usize,
+    pub prev: Option<&'a Post>,
+    pub next: Option<&'a Post>,
+    pub posts: Vec<&'a Post>,
+}
+
+/// Groups `posts` by their terms in `taxonomy`, by slug so `Rust` and `rust`
+/// share an archive page under `dir`. Terms are sorted by slug and each keeps
+/// the posts in the order they were given, or reversed for an `ordered` taxonomy.
+pub fn collect_tags<'a>(
+    posts: &'a [Post],
+    taxonomy: &'a str,
+    ordered: bool,
+    dir: &str,
+) -> Vec<Tag<'a>> {
+    let mut tags: BTreeMap<String, Tag> = BTreeMap::new();
+
+    for post in posts {
+        for name in post.terms(taxonomy) {
+            let slug = slugify(name);
+            if slug.is_empty() {
+                continue;
+            }
+
+            let tag = tags
+                .entry(slug.clone())
+                .or_insert_with(|| Tag::new(taxonomy, name, slug, dir));
+            if !tag
+                .posts
+                .last()
+                .is_some_and(|last| std::ptr::eq(*last, post))
+            {
+                tag.posts.push(post);
+                tag.count += 1;
+            }
+        }
+    }
+
+    let mut tags: Vec<Tag> = tags.into_values().collect();
+    if ordered {
+        for tag in &mut tags {
+            tag.posts.reverse();
+        }
+    }
+    tags
+}
+
+/// The terms of a single post, without their post lists, for linking to the
+/// archives in its language.
+pub fn post_tags<'a>(config: &Config, post: &'a Post, taxonomy: &'a str) -> Vec<Tag<'a>> {
+    let dir = language_dir(config, &post.lang);
+    post.terms(taxonomy)
+        .into_iter()
+        .map(|name| Tag::new(taxonomy, name, slugify(name), &dir))
+        .filter(|tag| !tag.slug.is_empty())
+        .collect()
+}
+
+/// Up to `count` other posts sharing tags with `post`, those sharing the most
+/// first and newer ones first among equals.
+pub fn related_posts<'a>(post: &Post, posts: &'a [Post], count: usize) -> Vec<&'a Post> {
+    let slugs = |post: &Post| -> BTreeSet<String> {
+        post.terms(TAGS)
+            .into_iter()
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .collect()
+    };
+    let tags = slugs(post);
+    if tags.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(usize, &Post)> = posts
+        .iter()
+        .filter(|other| !std::ptr::eq(*other, post))
+        .map(|other| (slugs(other).intersection(&tags).count(), other))
+        .filter(|(shared, _)| *shared > 0)
+        .collect();
+    // Stable, so posts sharing as many tags stay newest first.
+    scored.sort_by_key(|(shared, _)| Reverse(*shared));
+    scored
+        .into_iter()
+        .take(count)
+        .map(|(_, other)| other)
+        .collect()
+}
+
+/// The position of `post` in every term of the ordered taxonomies it belongs
+/// to. `posts` is every post in its language, newest first.
+pub fn series_parts<'a>(config: &'a Config, post: &Post, posts: &'a [Post]) -> Vec<SeriesPart<'a>> {
+    let dir = language_dir(config, &post.lang);
+    let mut parts = Vec::new();
+    for (taxonomy, settings) in &config.taxonomies {
+        if !settings.ordered {
+            continue;
+        }
+        for tag in collect_tags(posts, taxonomy, true, &dir) {
+            let Some(i) = tag
+                .posts
+                .iter()
+                .position(|member| std::ptr::eq(*member, post))
+            else {
+                continue;
+            };
+            parts.push(SeriesPart {
+                taxonomy,
+                name: tag.name,
+                part: i + 1,
+                count: tag.count,
+                prev: i.checked_sub(1).map(|j| tag.posts[j]),
+                next: tag.posts.get(i + 1).copied(),
+                url: tag.url,
+                posts: tag.posts,
+            });
+        }
+    }
+    parts
+}
+
+/// Writes `<taxonomy>/<slug>/index.html` for every term of `taxonomy`, and
+/// `<taxonomy>/<slug>/page/<n>/index.html` when the term has more than one page,
+/// under the directory of `lang`, the terms' language, along with the term's
+/// feeds when `settings` asks for them, dated `today` when they have no
+/// entries. Returns the paths of the files written.
+#[allow(clippy::too_many_arguments)]
+pub fn render_tag_pages(
+    tera: &Tera,
+    config: &Config,
+    output_dir: &Path,
+    lang: &str,
+    taxonomy: &str,
+    settings: &TaxonomyConfig,
+    tags: &[Tag],
+    pages: &[Post],
+    today: NaiveDate,
+) -> Result<Vec<PathBuf>, BuildError> {
+    let mut written = Vec::new();
+    // Feed ids must be absolute, like those of the site's feed.
+    let feeds = settings.feed && !config.base_url.is_empty();
+    for tag in tags {
+        let title = if taxonomy == TAGS {
+            string(config, lang, "tagged").replace("{name}", tag.name)
+        } else {
+            format!("{}: {}", humanize(taxonomy), tag.name)
+        };
+
+        let dir = tag.url.trim_end_matches("index.html");
+        let listing = paginate(&tag.posts, config.paginate, dir);
+        for page in &listing {
+            let mut context = Context::new();
+            context.insert("config", config);
+            context.insert("section", "tag");
+            context.insert("url", &page.path);
+            context.insert("lang", lang);
+            context.insert("pages", pages);
+            context.insert("title", &title);
+            context.insert("taxonomy", taxonomy);
+            context.insert("tag", tag);
+            context.insert("posts", page.posts);
+            context.insert("paginator", &page.paginator);
+            if feeds {
+                context.insert("feed_dir", dir);
+            }
+
+            let html_path = output_dir.join(page.path.trim_start_matches('/'));
+            render_page(tera, config, "tag.html", &context, &html_path)?;
+            crate::verbose!("Tag page generated: {:?} ({} posts)", html_path, tag.count);
+            written.push(html_path);
+        }
+        remove_extra_pages(output_dir, dir, listing.len())?;
+
+        if feeds {
+            // Feeds list the newest posts first, even those of ordered terms.
+            let mut posts = tag.posts.clone();
+            if settings.ordered {
+                posts.reverse();
+            }
+            let feed_title = match language_title(config, lang) {
+                "" => title.clone(),
+                site_title => format!("{}: {}", site_title, title),
+            };
+            let feeds = write_feeds(tera, config, output_dir, dir, &feed_title, &posts, today)?;
+            crate::verbose!("Tag feed generated: {:?} ({} entries)", feeds[0], tag.count);
+            written.extend(feeds);
+        }
+    }
+
+    Ok(written)
+}
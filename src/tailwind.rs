@@ -0,0 +1,60 @@
+use crate::config::Config;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the configured Tailwind CLI over the generated pages, writing only the
+/// classes they use to the stylesheet that `theme.stylesheet` links. The CLI
+/// runs in `source_dir`, so it finds a `tailwind.config.js` there.
+pub fn build_stylesheet(
+    config: &Config,
+    source_dir: &Path,
+    output_dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut words = config.tailwind.command.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(());
+    };
+    let stylesheet = &config.theme.stylesheet;
+    if !stylesheet.starts_with('/') || stylesheet.starts_with("//") {
+        return Err(format!(
+            "Cannot generate the stylesheet {:?}: theme.stylesheet must be a path within the site",
+            stylesheet
+        )
+        .into());
+    }
+    let stylesheet_path = output_dir.join(stylesheet.trim_start_matches('/'));
+    let output_dir = output_dir.canonicalize()?;
+
+    let mut command = Command::new(program);
+    command
+        .args(words)
+        .current_dir(source_dir)
+        .arg("--output")
+        .arg(&stylesheet_path)
+        .arg("--content")
+        .arg(output_dir.join("**").join("*.html"));
+    if let Some(input) = &config.tailwind.input {
+        command.arg("--input").arg(source_dir.join(input));
+    }
+    if config.tailwind.minify {
+        command.arg("--minify");
+    }
+
+    crate::verbose!("Running {:?}", command);
+    let output = command
+        .output()
+        .map_err(|e| format!("Could not run {:?}: {}", config.tailwind.command, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{:?} failed ({}):\n{}",
+            config.tailwind.command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )
+        .into());
+    }
+    println!("Stylesheet generated: {:?}", stylesheet_path);
+
+    Ok(())
+}
@@ -0,0 +1,233 @@
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::fingerprint::rewrite_asset_urls;
+use crate::i18n::default_strings;
+use crate::output::write_atomic;
+use crate::shortcodes::SHORTCODES_DIR;
+use crate::site::sorted_entries;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::{Context, Filter, Tera};
+
+/// Directory under the source root whose `.html` files replace the built-in templates.
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Directory under the source root holding one directory per theme. A theme has
+/// a `templates/` directory replacing built-in templates, and a `static/`
+/// directory copied to the root of the output directory.
+pub const THEMES_DIR: &str = "themes";
+
+const DEFAULT_TEMPLATES: [(&str, &str); 15] = [
+    ("base.html", include_str!("../templates/base.html")),
+    ("macros.html", include_str!("../templates/macros.html")),
+    ("post.html", include_str!("../templates/post.html")),
+    ("page.html", include_str!("../templates/page.html")),
+    ("index.html", include_str!("../templates/index.html")),
+    ("tag.html", include_str!("../templates/tag.html")),
+    ("archive.html", include_str!("../templates/archive.html")),
+    ("period.html", include_str!("../templates/period.html")),
+    ("author.html", include_str!("../templates/author.html")),
+    ("search.html", include_str!("../templates/search.html")),
+    ("404.html", include_str!("../templates/404.html")),
+    ("feed.xml", include_str!("../templates/feed.xml")),
+    ("sitemap.xml", include_str!("../templates/sitemap.xml")),
+    (
+        "shortcodes/youtube.html",
+        include_str!("../templates/shortcodes/youtube.html"),
+    ),
+    (
+        "shortcodes/figure.html",
+        include_str!("../templates/shortcodes/figure.html"),
+    ),
+];
+
+/// Loads the built-in templates, overriding any that the theme in `theme_dir`,
+/// then `<source>/templates/`, provide under the same file name, shortcodes in their
+/// `shortcodes/` directories. `.html` and `.xml` templates are autoescaped.
+pub fn load_templates(source_dir: &Path, theme_dir: Option<&Path>) -> Result<Tera, Box<dyn Error>> {
+    compile_templates(read_templates(source_dir, theme_dir)?)
+}
+
+/// Directory of the theme called `name`, or `None` for the built-in look.
+pub fn theme_dir(source_dir: &Path, name: Option<&str>) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    let theme_dir = source_dir.join(THEMES_DIR).join(name);
+    if !theme_dir.is_dir() {
+        return Err(format!(
+            "Theme {:?} not found: {:?} is not a directory",
+            name, theme_dir
+        )
+        .into());
+    }
+    Ok(Some(theme_dir))
+}
+
+/// Name and source of every template, built-in ones replaced by their overrides.
+pub fn read_templates(
+    source_dir: &Path,
+    theme_dir: Option<&Path>,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut templates: Vec<(String, String)> = DEFAULT_TEMPLATES
+        .iter()
+        .map(|(name, content)| (name.to_string(), content.to_string()))
+        .collect();
+
+    let mut templates_dirs = vec![source_dir.join(TEMPLATES_DIR)];
+    if let Some(theme_dir) = theme_dir {
+        templates_dirs.insert(0, theme_dir.join(TEMPLATES_DIR));
+    }
+    for templates_dir in templates_dirs {
+        override_templates(&templates_dir, "", &mut templates)?;
+        let shortcodes_dir = templates_dir.join(SHORTCODES_DIR);
+        let prefix = format!("{}/", SHORTCODES_DIR);
+        override_templates(&shortcodes_dir, &prefix, &mut templates)?;
+    }
+
+    Ok(templates)
+}
+
+/// Replaces `templates` with the `.html` and `.xml` files of the same name,
+/// after `prefix`, in `templates_dir`, which may not exist, and adds the others.
+fn override_templates(
+    templates_dir: &Path,
+    prefix: &str,
+    templates: &mut Vec<(String, String)>,
+) -> Result<(), Box<dyn Error>> {
+    if templates_dir.is_dir() {
+        for path in sorted_entries(templates_dir)? {
+            if !matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("html" | "xml")
+            ) {
+                continue;
+            }
+
+            let name = format!("{}{}", prefix, path.file_name().unwrap().to_string_lossy());
+            let content = fs::read_to_string(&path)?;
+            crate::verbose!("Using template override: {:?}", path);
+
+            templates.retain(|(existing, _)| *existing != name);
+            templates.push((name, content));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn compile_templates(templates: Vec<(String, String)>) -> Result<Tera, Box<dyn Error>> {
+    let mut tera = Tera::default();
+    tera.set_escape_fn(escape_html);
+    tera.register_filter("js_literal", JsLiteral);
+    tera.add_raw_templates(templates)?;
+
+    Ok(tera)
+}
+
+/// Renders `template` and writes the result to `path`, creating its directory.
+/// The template also receives the site's `data`. HTML pages are pointed at
+/// fingerprinted assets, and minified when the config asks for it. Returns
+/// the length of the written page.
+pub fn render_page(
+    tera: &Tera,
+    config: &Config,
+    template: &str,
+    context: &Context,
+    path: &Path,
+) -> Result<usize, BuildError> {
+    let mut context = context.clone();
+    context.insert("data", &config.data);
+    // The UI strings of the page's language, `lang` when the page has one.
+    let lang = context
+        .get("lang")
+        .and_then(|lang| lang.as_str())
+        .unwrap_or(&config.default_language);
+    match config.strings.get(lang) {
+        Some(strings) => context.insert("strings", strings),
+        None => context.insert("strings", &default_strings()),
+    }
+    let mut page = tera
+        .render(template, &context)
+        .map_err(|source| BuildError::Template {
+            template: template.to_string(),
+            path: path.to_path_buf(),
+            source,
+        })?;
+    if !config.fingerprints.is_empty() && template.ends_with(".html") {
+        let url = context
+            .get("url")
+            .and_then(|url| url.as_str())
+            .unwrap_or("/");
+        page = rewrite_asset_urls(&page, url, &config.base_url, &config.fingerprints);
+    }
+    if config.minify && template.ends_with(".html") {
+        page = minify_html(&page);
+    }
+
+    let write_error = |source| BuildError::Write {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(write_error)?;
+    }
+    write_atomic(path, &page).map_err(write_error)?;
+
+    Ok(page.len())
+}
+
+/// Strips whitespace, comments and optional markup from a page, along with
+/// its inline styles and scripts. `<pre>` blocks keep their whitespace.
+pub fn minify_html(page: &str) -> String {
+    let cfg = minify_html::Cfg {
+        minify_css: true,
+        minify_js: true,
+        ..minify_html::Cfg::new()
+    };
+    String::from_utf8(minify_html::minify(page.as_bytes(), &cfg))
+        .unwrap_or_else(|_| page.to_string())
+}
+
+/// The `js_literal` filter: writes a value as a JavaScript literal, quoted
+/// when it is a string, for passing it to an inline `<script>`. HTML escaping
+/// doesn't protect script text, so `<`, `>` and `&` are written as `\u`
+/// escapes, which a `</script>` in the value can't close the script with.
+struct JsLiteral;
+
+impl Filter for JsLiteral {
+    fn filter(&self, value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+        let literal = serde_json::to_string(value).map_err(tera::Error::json)?;
+        Ok(Value::String(
+            literal
+                .replace('<', "\\u003c")
+                .replace('>', "\\u003e")
+                .replace('&', "\\u0026"),
+        ))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Escapes the characters that are significant in HTML text and quoted attributes.
+/// Unlike Tera's default escaper this leaves `/` alone, so URLs stay readable.
+pub fn escape_html(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,49 @@
+use crate::markdown::headings;
+use serde::Serialize;
+
+/// A heading in a post's table of contents, with the headings nested under it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TocEntry {
+    pub level: usize,
+    /// Anchor id of the rendered heading, so `#{{ id }}` links to it.
+    pub id: String,
+    pub title: String,
+    pub children: Vec<TocEntry>,
+}
+
+/// Nests the post's headings by level. A heading belongs under the closest
+/// earlier heading of a higher level, so skipped levels don't add empty entries.
+pub fn table_of_contents(markdown_content: &str) -> Vec<TocEntry> {
+    let mut roots = Vec::new();
+    // Open entries from the outermost in; each is attached to its parent once
+    // a heading at its level or above closes it.
+    let mut open: Vec<TocEntry> = Vec::new();
+
+    for heading in headings(markdown_content) {
+        while open
+            .last()
+            .is_some_and(|entry| entry.level >= heading.level)
+        {
+            close(&mut open, &mut roots);
+        }
+        open.push(TocEntry {
+            level: heading.level,
+            id: heading.id,
+            title: heading.title,
+            children: Vec::new(),
+        });
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+
+    roots
+}
+
+fn close(open: &mut Vec<TocEntry>, roots: &mut Vec<TocEntry>) {
+    let entry = open.pop().unwrap();
+    match open.last_mut() {
+        Some(parent) => parent.children.push(entry),
+        None => roots.push(entry),
+    }
+}
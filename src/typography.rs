@@ -0,0 +1,91 @@
+use crate::config::TypographyConfig;
+use pulldown_cmark::{CowStr, Event, Tag};
+use regex::Regex;
+
+const NO_BREAK_SPACE: char = '\u{a0}';
+
+/// Puts a no-break space between the number and the unit matched by
+/// `Typographer::units`.
+const UNIT_SPACING: &str = "${1}\u{a0}${2}${3}";
+
+/// Units kept on the line of the number before them, as in `5 km` or `40 %`.
+const UNITS: [&str; 47] = [
+    "%", "‰", "°", "°C", "°F", "K", "nm", "µm", "mm", "cm", "m", "km", "in", "ft", "mi", "mg", "g",
+    "kg", "t", "lb", "oz", "ml", "cl", "l", "L", "ns", "µs", "ms", "s", "min", "h", "Hz", "kHz",
+    "MHz", "GHz", "B", "kB", "KB", "MB", "GB", "TB", "KiB", "MiB", "GiB", "TiB", "px", "W",
+];
+
+/// The typographic touches of `[typography]`, applied to the prose of a post.
+/// Code and headings are left alone, the latter since their text makes
+/// their anchor ids. Quotes and dashes are pulldown-cmark's smart
+/// punctuation, which the renderer turns on alongside.
+pub struct Typographer {
+    /// Matches a number and the space and unit after it, when `units` is set.
+    units: Option<Regex>,
+    widows: bool,
+}
+
+impl Typographer {
+    pub fn new(config: &TypographyConfig) -> Typographer {
+        let units = UNITS.map(regex::escape).join("|");
+        Typographer {
+            units: config
+                .units
+                .then(|| Regex::new(&format!(r"(\d) ({})([^\p{{L}}\p{{N}}]|$)", units)).unwrap()),
+            widows: config.widows,
+        }
+    }
+
+    pub fn apply<'a>(&self, events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+        let mut output: Vec<Event<'a>> = Vec::new();
+        let mut skipped = 0;
+        // Position in `output` of the last text of the open paragraph.
+        let mut last_text: Option<usize> = None;
+        for event in events {
+            match event {
+                Event::Start(Tag::CodeBlock(_) | Tag::Heading(..)) => skipped += 1,
+                Event::End(Tag::CodeBlock(_) | Tag::Heading(..)) => skipped -= 1,
+                Event::Start(Tag::Paragraph) => last_text = None,
+                Event::End(Tag::Paragraph) => {
+                    if let Some(i) = last_text.take().filter(|_| self.widows) {
+                        if let Event::Text(text) = &output[i] {
+                            output[i] = Event::Text(join_last_words(text).into());
+                        }
+                    }
+                }
+                Event::Text(text) if skipped == 0 => {
+                    last_text = Some(output.len());
+                    output.push(Event::Text(self.space_units(text)));
+                    continue;
+                }
+                Event::SoftBreak | Event::HardBreak | Event::Code(_) | Event::Html(_) => {
+                    last_text = None;
+                }
+                _ => {}
+            }
+            output.push(event);
+        }
+        output
+    }
+
+    fn space_units<'a>(&self, text: CowStr<'a>) -> CowStr<'a> {
+        match &self.units {
+            Some(units) if units.is_match(&text) => {
+                units.replace_all(&text, UNIT_SPACING).into_owned().into()
+            }
+            _ => text,
+        }
+    }
+}
+
+/// `text` with the space before its last word made unbreakable, so a
+/// paragraph doesn't end on a line holding a single word.
+fn join_last_words(text: &str) -> String {
+    let trimmed = text.trim_end();
+    match trimmed.rfind(' ') {
+        Some(i) if !trimmed[..i].trim().is_empty() => {
+            format!("{}{}{}", &text[..i], NO_BREAK_SPACE, &text[i + 1..])
+        }
+        _ => text.to_string(),
+    }
+}
@@ -0,0 +1,144 @@
+use crate::config::Config;
+use crate::data::DATA_DIR;
+use crate::i18n::I18N_DIR;
+use crate::includes::SNIPPETS_DIR;
+use crate::site::{BuildOptions, Site};
+use crate::templates::{TEMPLATES_DIR, THEMES_DIR};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long to wait for further file events before rebuilding.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Builds the site, then re-renders only what changed whenever a file under
+/// `source_dir` is saved, calling `on_rebuild` after each successful rebuild.
+/// A failed rebuild is reported and the watcher keeps running, so a
+/// half-written post does not end the session.
+pub fn watch(
+    source_dir: &Path,
+    output_dir: &Path,
+    config_path: &Path,
+    options: BuildOptions,
+    mut on_rebuild: impl FnMut(),
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_dir)?;
+    let source_dir = source_dir.canonicalize()?;
+    let output_dir = output_dir.canonicalize()?;
+    let config_path = config_path
+        .canonicalize()
+        .unwrap_or(config_path.to_path_buf());
+
+    let config = Config::load(&config_path)?;
+    let mut site = Site::load(&source_dir, &output_dir, config, options)?;
+    // With --keep-going the rest of the site is built around the failed posts,
+    // so watch them for a fix rather than ending the session.
+    match site.build() {
+        Err(e) if options.keep_going => eprintln!("Build failed: {}", e),
+        result => result?,
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&source_dir, RecursiveMode::Recursive)?;
+    println!(
+        "\nWatching {:?} for changes, press Ctrl+C to stop.",
+        source_dir
+    );
+
+    loop {
+        let mut changed = BTreeSet::new();
+        collect_changes(rx.recv()?, &mut changed);
+        // Editors often save in several steps; gather the whole burst first.
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            collect_changes(event, &mut changed);
+        }
+        changed.retain(|path| !path.starts_with(&output_dir) && !site.is_ignored(path));
+        if changed.is_empty() {
+            continue;
+        }
+
+        match apply_changes(&mut site, &config_path, &changed) {
+            Ok(()) => on_rebuild(),
+            Err(e) => eprintln!("Rebuild failed: {}", e),
+        }
+    }
+}
+
+fn collect_changes(event: notify::Result<Event>, changed: &mut BTreeSet<PathBuf>) {
+    match event {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => changed.extend(event.paths),
+        Ok(_) => {}
+        Err(e) => eprintln!("Watch error: {}", e),
+    }
+}
+
+/// Changes to the config, templates, snippets, data or UI strings affect every page
+/// and trigger a full rebuild; markdown and asset changes are applied file by file,
+/// along with the posts including a changed file.
+fn apply_changes(
+    site: &mut Site,
+    config_path: &Path,
+    changed: &BTreeSet<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let source_dir = site.source_dir().to_path_buf();
+    let full_rebuild = changed.iter().any(|path| {
+        path == config_path
+            || path.starts_with(source_dir.join(TEMPLATES_DIR))
+            || path.starts_with(source_dir.join(THEMES_DIR))
+            || path.starts_with(source_dir.join(SNIPPETS_DIR))
+            || path.starts_with(source_dir.join(DATA_DIR))
+            || path.starts_with(source_dir.join(I18N_DIR))
+    });
+
+    // A new fingerprint renames the asset in every page.
+    let fingerprinted = changed.iter().any(|path| site.is_fingerprinted(path));
+    if full_rebuild || fingerprinted {
+        match full_rebuild {
+            true => println!("\nConfiguration changed, rebuilding everything"),
+            false => println!("\nFingerprinted asset changed, rebuilding everything"),
+        }
+        let output_dir = site.output_dir().to_path_buf();
+        let config = Config::load(config_path)?;
+        *site = Site::load(&source_dir, &output_dir, config, site.options())?;
+        return site.build();
+    }
+
+    // Posts including a changed file, say with `{{ code }}`, are rendered
+    // again along with it.
+    let includers: BTreeSet<PathBuf> = changed
+        .iter()
+        .flat_map(|path| site.includers(path))
+        .filter(|path| !changed.contains(path))
+        .collect();
+
+    let mut rendered = false;
+    for path in changed.iter().chain(&includers) {
+        if site.is_asset(path) {
+            if path.is_file() {
+                site.copy_asset(path)?;
+            } else {
+                site.remove_asset(path)?;
+            }
+            continue;
+        }
+        if !site.is_source(path) {
+            continue;
+        }
+        match path.is_file() {
+            true => site.update_post(path)?,
+            false => site.remove_post(path)?,
+        }
+        rendered = true;
+    }
+    if rendered {
+        site.build_stylesheet()?;
+    }
+    site.precompress()?;
+
+    Ok(())
+}